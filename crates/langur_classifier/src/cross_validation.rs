@@ -0,0 +1,115 @@
+use crate::{tokens, Corpus, TrainingOptions};
+
+/// A sample with a known language, along with the candidate languages the
+/// classifier has to pick from.
+#[derive(Debug, Clone)]
+pub struct LabeledSample {
+    pub language: String,
+    pub content: String,
+    pub candidates: Vec<String>,
+}
+
+/// Outcome of [`cross_validate`].
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct CrossValidation {
+    pub correct: usize,
+    pub total: usize,
+}
+
+impl CrossValidation {
+    pub fn accuracy(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        (self.correct as f64) / (self.total as f64)
+    }
+}
+
+/// Runs k-fold cross-validation over `samples`.
+///
+/// Sample `i` is held out in fold `i % folds`, and classified by a model
+/// trained on all the other folds, with n-grams up to `ngram_order` as
+/// features. Only samples with more than one candidate are scored, since
+/// those are the only ones the classifier is used for.
+pub fn cross_validate(
+    samples: &[LabeledSample],
+    folds: usize,
+    options: &TrainingOptions,
+    ngram_order: usize,
+) -> CrossValidation {
+    assert!(folds >= 2, "cross-validation requires at least 2 folds");
+
+    let tokenized: Vec<Vec<&str>> = samples
+        .iter()
+        .map(|sample| tokens(&sample.content).collect())
+        .collect();
+
+    let mut result = CrossValidation::default();
+    for fold in 0..folds {
+        let mut corpus = Corpus::new().with_ngram_order(ngram_order);
+        for (i, sample) in samples.iter().enumerate() {
+            if i % folds != fold {
+                corpus.add_tokens(&sample.language, tokenized[i].iter().copied());
+            }
+        }
+        let model = corpus.train(options);
+
+        for (i, sample) in samples.iter().enumerate() {
+            if i % folds != fold || sample.candidates.len() < 2 {
+                continue;
+            }
+            let candidates: Vec<&str> = sample.candidates.iter().map(String::as_str).collect();
+            result.total += 1;
+            if model.classify_tokens(&tokenized[i], &candidates) == Some(&sample.language[..]) {
+                result.correct += 1;
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(language: &str, content: &str) -> LabeledSample {
+        LabeledSample {
+            language: language.to_owned(),
+            content: content.to_owned(),
+            candidates: vec!["Python".to_owned(), "Rust".to_owned()],
+        }
+    }
+
+    #[test]
+    fn test_cross_validate() {
+        let samples = vec![
+            sample("Rust", "fn main() { let x = 5; }"),
+            sample("Rust", "fn helper() -> u32 { let y = 5; y }"),
+            sample("Python", "def main(): x = 5"),
+            sample("Python", "def helper(): y = 5; return y"),
+        ];
+        let result = cross_validate(&samples, 2, &TrainingOptions::default(), 1);
+        assert_eq!(
+            result,
+            CrossValidation {
+                correct: 4,
+                total: 4
+            }
+        );
+        assert_eq!(result.accuracy(), 1.0);
+
+        let result = cross_validate(&samples, 2, &TrainingOptions::default(), 2);
+        assert_eq!(result.correct, 4);
+    }
+
+    #[test]
+    fn test_cross_validate_skips_unambiguous_samples() {
+        let mut samples = vec![
+            sample("Rust", "fn main() {}"),
+            sample("Python", "def main(): pass"),
+        ];
+        samples[0].candidates.truncate(1);
+        let result = cross_validate(&samples, 2, &TrainingOptions::default(), 1);
+        assert_eq!(result.total, 1);
+    }
+}
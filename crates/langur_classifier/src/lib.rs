@@ -0,0 +1,682 @@
+//! A multinomial Naive Bayes classifier over the key tokens produced by
+//! [`langur_tokenizer`].
+//!
+//! Training happens ahead of time in the codegen tools, while scoring happens
+//! at runtime in Langur; keeping both in this crate guarantees that the
+//! probabilities are computed and consumed the same way.
+
+mod cross_validation;
+mod serialization;
+
+pub use cross_validation::{cross_validate, CrossValidation, LabeledSample};
+pub use serialization::{CompactModel, Precision};
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fs,
+    io::{self, BufRead},
+    ops::Range,
+    path::Path,
+};
+
+use langur_tokenizer::{Dialect, StreamTokenizer};
+
+/// Tokens longer than this are ignored, both when training and when classifying.
+pub const MAX_TOKEN_BYTES: usize = 32;
+
+/// The longest runs of consecutive tokens that can be used as features,
+/// see [`features`].
+pub const MAX_NGRAM_ORDER: usize = 3;
+
+/// Returns the tokens of `content` that are used as features by the classifier.
+///
+/// # Examples
+/// ```
+/// let tokens: Vec<&str> = langur_classifier::tokens("let x = \"hello\";").collect();
+/// assert_eq!(tokens, vec!["let", "x", "=", ";"]);
+/// ```
+pub fn tokens(content: &str) -> impl Iterator<Item = &str> {
+    langur_tokenizer::get_key_tokens(content).filter(|token| token.len() <= MAX_TOKEN_BYTES)
+}
+
+/// Same as [`tokens`], but with the comments and strings of `dialect`, so
+/// that e.g. `#include` in C is kept instead of dropped as a comment.
+///
+/// # Examples
+/// ```
+/// use langur_tokenizer::Dialect;
+///
+/// let dialect = Dialect::for_language("C").unwrap();
+/// let tokens: Vec<&str> =
+///     langur_classifier::tokens_with_dialect("#include <a.h> // b", dialect).collect();
+/// assert_eq!(tokens, vec!["#", "include", "<", "a", ".", "h", ">"]);
+/// ```
+pub fn tokens_with_dialect<'a>(
+    content: &'a str,
+    dialect: &'a Dialect,
+) -> impl Iterator<Item = &'a str> {
+    langur_tokenizer::get_key_tokens_with_dialect(content, dialect)
+        .filter(|token| token.len() <= MAX_TOKEN_BYTES)
+}
+
+/// Same as [`tokens`], but read incrementally with a [`StreamTokenizer`], so
+/// that the content is only read about as far as the tokens taken. The
+/// tokens are owned, since the stream doesn't keep the content around.
+///
+/// # Examples
+/// ```
+/// use langur_classifier::StreamTokens;
+/// use langur_tokenizer::StreamTokenizer;
+///
+/// let stream = StreamTokenizer::new("let x = \"hello\";".as_bytes());
+/// let tokens: Vec<String> = StreamTokens::new(stream).collect::<Result<_, _>>().unwrap();
+/// assert_eq!(tokens, vec!["let", "x", "=", ";"]);
+/// ```
+pub struct StreamTokens<R> {
+    stream: StreamTokenizer<R>,
+    tokens: VecDeque<String>,
+}
+
+impl<R: BufRead> StreamTokens<R> {
+    pub fn new(stream: StreamTokenizer<R>) -> Self {
+        StreamTokens {
+            stream,
+            tokens: VecDeque::new(),
+        }
+    }
+
+    pub fn stream(&self) -> &StreamTokenizer<R> {
+        &self.stream
+    }
+}
+
+impl<R: BufRead> Iterator for StreamTokens<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.tokens.is_empty() {
+            match self.stream.next_tokens() {
+                Ok(Some(tokens)) => self.tokens.extend(
+                    tokens
+                        .into_iter()
+                        .filter_map(|(token, _)| langur_tokenizer::key_token(token))
+                        .filter(|token| token.len() <= MAX_TOKEN_BYTES)
+                        .map(str::to_owned),
+                ),
+                Ok(None) => return None,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        self.tokens.pop_front().map(Ok)
+    }
+}
+
+/// Returns the features scored by a model with the given n-gram order: the
+/// tokens themselves, followed by every run of 2 up to `ngram_order`
+/// consecutive tokens joined by spaces, like `: :` or `< T >`.
+///
+/// # Examples
+/// ```
+/// let tokens: Vec<&str> = langur_classifier::tokens("a::b").collect();
+/// let features = langur_classifier::features(&tokens, 2);
+/// assert_eq!(features, vec!["a", ":", ":", "b", "a :", ": :", ": b"]);
+/// ```
+pub fn features<'t>(tokens: &[&'t str], ngram_order: usize) -> Vec<Cow<'t, str>> {
+    features_in(tokens, 0..tokens.len(), ngram_order)
+}
+
+/// Returns the features of [`features`] that start at the tokens in
+/// `range`, so that scoring consecutive ranges one at a time adds up to
+/// scoring all the features. N-grams starting in `range` may end after it.
+///
+/// # Examples
+/// ```
+/// let tokens: Vec<&str> = langur_classifier::tokens("a::b").collect();
+/// let features = langur_classifier::features_in(&tokens, 2..4, 2);
+/// assert_eq!(features, vec![":", "b", ": b"]);
+/// ```
+pub fn features_in<'t>(
+    tokens: &[&'t str],
+    range: Range<usize>,
+    ngram_order: usize,
+) -> Vec<Cow<'t, str>> {
+    let mut features: Vec<Cow<str>> = tokens[range.clone()]
+        .iter()
+        .map(|&token| Cow::Borrowed(token))
+        .collect();
+    for n in 2..=ngram_order {
+        let end = range.end.min((tokens.len() + 1).saturating_sub(n));
+        if range.start < end {
+            features.extend(
+                tokens[range.start..end + n - 1]
+                    .windows(n)
+                    .map(|ngram| Cow::Owned(ngram.join(" "))),
+            );
+        }
+    }
+    features
+}
+
+/// Computes the unnormalized log posterior of a language for the given tokens.
+///
+/// `log_prob` looks up the log probability of a token for the language; tokens
+/// for which it returns `None` are scored with `unseen_log_prob`.
+pub fn score<'t, I, F>(tokens: I, log_prior: f64, unseen_log_prob: f64, log_prob: F) -> f64
+where
+    I: IntoIterator<Item = &'t str>,
+    F: Fn(&str) -> Option<f64>,
+{
+    tokens.into_iter().fold(log_prior, |acc, token| {
+        acc + log_prob(token).unwrap_or(unseen_log_prob)
+    })
+}
+
+/// The log probability of tokens that a language was never seen with, when
+/// training without smoothing.
+pub const DEFAULT_UNSEEN_LOG_PROB: f64 = -19.0;
+
+/// How token probabilities are estimated from the token counts.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Smoothing {
+    /// Maximum likelihood estimates; tokens that were never seen for a language
+    /// get a flat log probability instead.
+    None { unseen_log_prob: f64 },
+    /// Additive smoothing, where every token in the vocabulary of the whole
+    /// corpus gets `alpha` pseudo-counts for every language.
+    Laplace { alpha: f64 },
+}
+
+/// How the prior probability of each language is estimated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Priors {
+    /// All languages are equally likely.
+    Uniform,
+    /// Languages are weighted by the number of samples they were trained on.
+    SampleCounts,
+    /// Languages are weighted by the relative weights in the table, for example
+    /// based on popularity. Languages missing from the table get the smallest
+    /// weight present in the table.
+    Table(HashMap<String, f64>),
+}
+
+/// How a [`Corpus`] is trained. The default is no smoothing, with
+/// [`DEFAULT_UNSEEN_LOG_PROB`] for unseen tokens, and uniform priors, which
+/// is what the built-in classifier is trained with. Smoothing and priors are
+/// opt-in until they're shown to beat it on Linguist's samples.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrainingOptions {
+    pub smoothing: Smoothing,
+    pub priors: Priors,
+}
+
+impl Default for TrainingOptions {
+    fn default() -> Self {
+        TrainingOptions {
+            smoothing: Smoothing::None {
+                unseen_log_prob: DEFAULT_UNSEEN_LOG_PROB,
+            },
+            priors: Priors::Uniform,
+        }
+    }
+}
+
+/// Token statistics gathered for a single language.
+#[derive(Debug, Default, Clone)]
+pub struct TokenCounts {
+    /// Number of samples that contributed to these counts.
+    pub samples: u32,
+    /// Sum of all the values in `tokens`.
+    pub total_tokens: u64,
+    pub tokens: HashMap<String, u32>,
+}
+
+/// Labeled training data, stored as per-language token counts.
+#[derive(Debug, Clone)]
+pub struct Corpus {
+    languages: BTreeMap<String, TokenCounts>,
+    dialects: bool,
+    ngram_order: usize,
+}
+
+impl Default for Corpus {
+    fn default() -> Self {
+        Corpus {
+            languages: BTreeMap::new(),
+            dialects: false,
+            ngram_order: 1,
+        }
+    }
+}
+
+impl Corpus {
+    pub fn new() -> Self {
+        Corpus::default()
+    }
+
+    /// Creates a corpus whose samples are tokenized with the dialect of their
+    /// language, if there is one, see [`tokens_with_dialect`]. Models trained
+    /// on it should classify with the same dialects.
+    pub fn with_dialects() -> Self {
+        Corpus {
+            dialects: true,
+            ..Corpus::default()
+        }
+    }
+
+    /// Also counts runs of 2 up to `ngram_order` consecutive tokens as
+    /// features, see [`features`]. Models trained on the corpus score the
+    /// same features.
+    ///
+    /// Panics if `ngram_order` is 0 or greater than [`MAX_NGRAM_ORDER`].
+    pub fn with_ngram_order(mut self, ngram_order: usize) -> Self {
+        assert!(
+            (1..=MAX_NGRAM_ORDER).contains(&ngram_order),
+            "n-gram order must be between 1 and {}",
+            MAX_NGRAM_ORDER
+        );
+        self.ngram_order = ngram_order;
+        self
+    }
+
+    /// Tokenizes `content` and adds it as a sample for `language`.
+    pub fn add_sample(&mut self, language: &str, content: &str) {
+        match Dialect::for_language(language).filter(|_| self.dialects) {
+            Some(dialect) => self.add_tokens(language, tokens_with_dialect(content, dialect)),
+            None => self.add_tokens(language, tokens(content)),
+        }
+    }
+
+    /// Adds an already tokenized sample for `language`.
+    pub fn add_tokens<'t, I>(&mut self, language: &str, tokens: I)
+    where
+        I: IntoIterator<Item = &'t str>,
+    {
+        let tokens: Vec<&str> = tokens.into_iter().collect();
+        let features = features(&tokens, self.ngram_order);
+        let counts = self.languages.entry(language.to_owned()).or_default();
+        counts.samples += 1;
+        for feature in features {
+            counts.total_tokens += 1;
+            *counts.tokens.entry(feature.into_owned()).or_insert(0) += 1;
+        }
+    }
+
+    /// Adds every file under `dir` as a sample, labeled with the name of the
+    /// top-level sub-directory it is in, e.g. `dir/Rust/src/main.rs` is a
+    /// Rust sample. Files that aren't valid UTF-8 are skipped, as are files
+    /// directly inside `dir`.
+    pub fn add_directory<P: AsRef<Path>>(&mut self, dir: P) -> io::Result<()> {
+        let mut languages: Vec<_> = fs::read_dir(dir)?.collect::<io::Result<_>>()?;
+        languages.sort_by_key(|entry| entry.file_name());
+        for entry in languages {
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            let language = entry.file_name().to_string_lossy().into_owned();
+            self.add_samples_in(&language, &entry.path())?;
+        }
+        Ok(())
+    }
+
+    fn add_samples_in(&mut self, language: &str, dir: &Path) -> io::Result<()> {
+        let mut entries: Vec<_> = fs::read_dir(dir)?.collect::<io::Result<_>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                self.add_samples_in(language, &path)?;
+            } else if let Ok(content) = String::from_utf8(fs::read(&path)?) {
+                self.add_sample(language, &content);
+            }
+        }
+        Ok(())
+    }
+
+    /// Iterates over the languages in the corpus, in sorted order.
+    pub fn languages(&self) -> impl Iterator<Item = (&str, &TokenCounts)> {
+        self.languages
+            .iter()
+            .map(|(language, counts)| (language.as_str(), counts))
+    }
+
+    /// The number of distinct tokens across all languages.
+    pub fn vocabulary_size(&self) -> usize {
+        self.languages
+            .values()
+            .flat_map(|counts| counts.tokens.keys())
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    pub fn train(&self, options: &TrainingOptions) -> Model {
+        let vocabulary_size = self.vocabulary_size() as f64;
+        let log_priors = self.log_priors(&options.priors);
+
+        let languages = self
+            .languages
+            .iter()
+            .map(|(language, counts)| {
+                let total_tokens = counts.total_tokens as f64;
+                let (pseudo_count, denominator, unseen_log_prob) = match options.smoothing {
+                    Smoothing::None { unseen_log_prob } => (0.0, total_tokens, unseen_log_prob),
+                    Smoothing::Laplace { alpha } => {
+                        let denominator = total_tokens + alpha * vocabulary_size;
+                        (alpha, denominator, (alpha / denominator).ln())
+                    }
+                };
+                let token_log_probs = counts
+                    .tokens
+                    .iter()
+                    .map(|(token, &count)| {
+                        let probability = (count as f64 + pseudo_count) / denominator;
+                        (token.clone(), probability.ln())
+                    })
+                    .collect();
+                let model = LanguageModel {
+                    log_prior: log_priors[language.as_str()],
+                    unseen_log_prob,
+                    token_log_probs,
+                };
+                (language.clone(), model)
+            })
+            .collect();
+
+        Model {
+            languages,
+            ngram_order: self.ngram_order,
+        }
+    }
+
+    fn log_priors(&self, priors: &Priors) -> HashMap<&str, f64> {
+        let weights: Vec<(&str, f64)> = match priors {
+            Priors::Uniform => self
+                .languages()
+                .map(|(language, _)| (language, 1.0))
+                .collect(),
+            Priors::SampleCounts => self
+                .languages()
+                .map(|(language, counts)| (language, counts.samples as f64))
+                .collect(),
+            Priors::Table(table) => {
+                let default_weight = table
+                    .values()
+                    .copied()
+                    .filter(|weight| *weight > 0.0)
+                    .fold(f64::INFINITY, f64::min);
+                let default_weight = if default_weight.is_finite() {
+                    default_weight
+                } else {
+                    1.0
+                };
+                self.languages()
+                    .map(|(language, _)| {
+                        let weight = table
+                            .get(language)
+                            .copied()
+                            .filter(|weight| *weight > 0.0)
+                            .unwrap_or(default_weight);
+                        (language, weight)
+                    })
+                    .collect()
+            }
+        };
+
+        let total: f64 = weights.iter().map(|(_, weight)| weight).sum();
+        weights
+            .into_iter()
+            .map(|(language, weight)| (language, (weight / total).ln()))
+            .collect()
+    }
+}
+
+/// The trained parameters for a single language.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LanguageModel {
+    pub log_prior: f64,
+    /// Log probability used for tokens missing from `token_log_probs`.
+    pub unseen_log_prob: f64,
+    pub token_log_probs: HashMap<String, f64>,
+}
+
+impl LanguageModel {
+    pub fn score<'t, I>(&self, tokens: I) -> f64
+    where
+        I: IntoIterator<Item = &'t str>,
+    {
+        score(tokens, self.log_prior, self.unseen_log_prob, |token| {
+            self.token_log_probs.get(token).copied()
+        })
+    }
+}
+
+/// A trained classifier, keyed by language name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Model {
+    pub languages: BTreeMap<String, LanguageModel>,
+    /// The longest runs of consecutive tokens scored as features, see
+    /// [`features`].
+    pub ngram_order: usize,
+}
+
+impl Default for Model {
+    fn default() -> Self {
+        Model {
+            languages: BTreeMap::new(),
+            ngram_order: 1,
+        }
+    }
+}
+
+impl Model {
+    /// Returns the most likely language out of `candidates`.
+    ///
+    /// Candidates without any training data are never picked unless all
+    /// candidates lack training data, in which case the first one is returned.
+    /// Ties are broken in favor of the earlier candidate.
+    pub fn classify<'c>(&self, content: &str, candidates: &[&'c str]) -> Option<&'c str> {
+        let tokens: Vec<_> = tokens(content).collect();
+        self.classify_tokens(&tokens, candidates)
+    }
+
+    pub fn classify_tokens<'c>(&self, tokens: &[&str], candidates: &[&'c str]) -> Option<&'c str> {
+        let features = features(tokens, self.ngram_order);
+        let mut best: Option<(&'c str, f64)> = None;
+        for &candidate in candidates {
+            let score = match self.languages.get(candidate) {
+                Some(model) => model.score(features.iter().map(AsRef::as_ref)),
+                None => f64::NEG_INFINITY,
+            };
+            match best {
+                Some((_, best_score)) if best_score >= score => {}
+                _ => best = Some((candidate, score)),
+            }
+        }
+        best.map(|(language, _)| language)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corpus() -> Corpus {
+        let mut corpus = Corpus::new();
+        corpus.add_sample("Rust", "fn main() { let x = 5; }");
+        corpus.add_sample("Rust", "fn helper() -> u32 { 5 }");
+        corpus.add_sample("Python", "def main(): x = 5");
+        corpus
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_tokens_skip_long_tokens() {
+        let long_identifier = "a".repeat(MAX_TOKEN_BYTES + 1);
+        let content = format!("let {} = 1;", long_identifier);
+        let tokens: Vec<_> = tokens(&content).collect();
+        assert_eq!(tokens, vec!["let", "=", ";"]);
+    }
+
+    #[test]
+    fn test_stream_tokens() {
+        let content = format!("/* a */ let {} = \"b\"; // c\nx", "y".repeat(40));
+        let reader = io::BufReader::with_capacity(3, content.as_bytes());
+        let streamed: Vec<String> = StreamTokens::new(StreamTokenizer::new(reader))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(streamed, tokens(&content).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_add_directory() {
+        let dir = std::env::temp_dir().join("langur_classifier_add_directory");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("Rust/nested")).unwrap();
+        fs::create_dir_all(dir.join("Python")).unwrap();
+        fs::write(dir.join("Rust/main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.join("Rust/nested/lib.rs"), "pub fn lib() {}").unwrap();
+        fs::write(dir.join("Python/main.py"), "def main(): pass").unwrap();
+        fs::write(dir.join("Python/binary"), b"\xff\xfe").unwrap();
+        fs::write(dir.join("README"), "not a sample").unwrap();
+
+        let mut corpus = Corpus::new();
+        corpus.add_directory(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let samples: Vec<_> = corpus
+            .languages()
+            .map(|(language, counts)| (language, counts.samples))
+            .collect();
+        assert_eq!(samples, vec![("Python", 1), ("Rust", 2)]);
+    }
+
+    #[test]
+    fn test_corpus_with_dialects() {
+        let mut corpus = Corpus::with_dialects();
+        corpus.add_sample("Python", "x -- y # note");
+        corpus.add_sample("Unknown", "x -- y # note");
+        let tokens: Vec<_> = corpus
+            .languages()
+            .map(|(language, counts)| (language, counts.total_tokens))
+            .collect();
+        assert_eq!(tokens, vec![("Python", 4), ("Unknown", 1)]);
+    }
+
+    #[test]
+    fn test_laplace_smoothing() {
+        let corpus = corpus();
+        let vocabulary_size = corpus.vocabulary_size() as f64;
+        let options = TrainingOptions {
+            smoothing: Smoothing::Laplace { alpha: 1.0 },
+            ..TrainingOptions::default()
+        };
+        let model = corpus.train(&options);
+        let python = &model.languages["Python"];
+        // def main ( ) : x = 5 -> "5" is a number, so 7 key tokens
+        let denominator = 7.0 + vocabulary_size;
+        assert_close(python.token_log_probs["def"], (2.0 / denominator).ln());
+        assert_close(python.unseen_log_prob, (1.0 / denominator).ln());
+        assert!(!python.token_log_probs.contains_key("fn"));
+    }
+
+    #[test]
+    fn test_no_smoothing() {
+        let model = corpus().train(&TrainingOptions::default());
+        let python = &model.languages["Python"];
+        assert_close(python.token_log_probs["def"], (1.0f64 / 7.0).ln());
+        assert_close(python.unseen_log_prob, -19.0);
+        assert_close(python.log_prior, 0.5f64.ln());
+    }
+
+    #[test]
+    fn test_sample_count_priors() {
+        let options = TrainingOptions {
+            priors: Priors::SampleCounts,
+            ..TrainingOptions::default()
+        };
+        let model = corpus().train(&options);
+        assert_close(model.languages["Rust"].log_prior, (2.0f64 / 3.0).ln());
+        assert_close(model.languages["Python"].log_prior, (1.0f64 / 3.0).ln());
+    }
+
+    #[test]
+    fn test_table_priors() {
+        let table = HashMap::from([("Rust".to_owned(), 3.0), ("Python".to_owned(), 1.0)]);
+        let mut corpus = corpus();
+        corpus.add_sample("Go", "func main() {}");
+        let options = TrainingOptions {
+            priors: Priors::Table(table),
+            ..TrainingOptions::default()
+        };
+        let model = corpus.train(&options);
+        // Missing languages get the smallest weight in the table.
+        assert_close(model.languages["Rust"].log_prior, (3.0f64 / 5.0).ln());
+        assert_close(model.languages["Python"].log_prior, (1.0f64 / 5.0).ln());
+        assert_close(model.languages["Go"].log_prior, (1.0f64 / 5.0).ln());
+    }
+
+    #[test]
+    fn test_classify() {
+        let model = corpus().train(&TrainingOptions::default());
+        assert_eq!(
+            model.classify("fn foo() -> u32 { 1 }", &["Python", "Rust"]),
+            Some("Rust")
+        );
+        assert_eq!(
+            model.classify("def foo(): return 1", &["Rust", "Python"]),
+            Some("Python")
+        );
+        assert_eq!(
+            model.classify("def", &["Unknown", "Python"]),
+            Some("Python")
+        );
+        assert_eq!(model.classify("def", &["Unknown"]), Some("Unknown"));
+        assert_eq!(model.classify("def", &[]), None);
+    }
+
+    #[test]
+    fn test_ngram_features() {
+        // The same tokens in a different order.
+        let train = |corpus: Corpus| {
+            let mut corpus = corpus;
+            corpus.add_sample("C++", "a::b");
+            corpus.add_sample("C", "b:a:");
+            corpus.train(&TrainingOptions::default())
+        };
+        let unigrams = train(Corpus::new());
+        assert_eq!(unigrams.classify("c::d", &["C", "C++"]), Some("C"));
+
+        let bigrams = train(Corpus::new().with_ngram_order(2));
+        assert_eq!(bigrams.ngram_order, 2);
+        assert!(bigrams.languages["C++"].token_log_probs.contains_key(": :"));
+        assert_eq!(bigrams.classify("c::d", &["C", "C++"]), Some("C++"));
+    }
+
+    #[test]
+    fn test_features_in_ranges() {
+        let tokens: Vec<&str> = tokens("a < b > :: c ( d )").collect();
+        for ngram_order in 1..=MAX_NGRAM_ORDER {
+            let mut expected = features(&tokens, ngram_order);
+            expected.sort();
+            for batch in 1..=tokens.len() + 1 {
+                let mut features: Vec<_> = (0..tokens.len())
+                    .step_by(batch)
+                    .flat_map(|start| {
+                        let end = (start + batch).min(tokens.len());
+                        features_in(&tokens, start..end, ngram_order)
+                    })
+                    .collect();
+                features.sort();
+                assert_eq!(
+                    features, expected,
+                    "order {}, batches of {}",
+                    ngram_order, batch
+                );
+            }
+        }
+        assert!(features_in(&tokens, 3..3, 3).is_empty());
+    }
+}
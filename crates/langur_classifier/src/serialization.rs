@@ -0,0 +1,601 @@
+//! A compact binary encoding for [`Model`], which is also how the built-in
+//! model is embedded in Langur.
+//!
+//! Integers marked as varints are LEB128-encoded, everything else is
+//! little-endian.
+//!
+//! ```text
+//! magic       b"LGRM"
+//! version     u16
+//! precision   u8, see `Precision`
+//! n-gram order u8, see `Model::ngram_order`
+//! tokens      varint count, then per token: u8 length, UTF-8 bytes
+//! languages   varint count, then per language:
+//!               varint name length, UTF-8 name,
+//!               f32 log prior, f32 unseen log probability,
+//!               varint entry count,
+//!               for `Precision::U16`: f32 minimum and f32 maximum log probability,
+//!               token indices: varint delta from the previous index,
+//!               log probabilities: f32 or u16 each, in the same order
+//! ```
+//!
+//! Tokens are interned in a single sorted table shared by all languages,
+//! since the same tokens tend to show up in many languages, and each
+//! language only stores the tokens it has seen. N-gram features are stored
+//! as tokens too.
+
+use crate::{LanguageModel, Model, MAX_NGRAM_ORDER, MAX_TOKEN_BYTES};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    hash::{BuildHasherDefault, Hasher},
+    io::{self, Read, Write},
+    path::Path,
+};
+
+const MAGIC: &[u8; 4] = b"LGRM";
+const VERSION: u16 = 1;
+
+/// The longest feature, an n-gram of the longest tokens joined by spaces.
+const MAX_FEATURE_BYTES: usize = MAX_NGRAM_ORDER * (MAX_TOKEN_BYTES + 1) - 1;
+
+/// How log probabilities are stored.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Precision {
+    F32,
+    /// Linearly quantized between the smallest and largest log probability
+    /// of each language, which halves the size of the model.
+    #[default]
+    U16,
+}
+
+impl Precision {
+    fn tag(self) -> u8 {
+        match self {
+            Precision::F32 => 0,
+            Precision::U16 => 1,
+        }
+    }
+
+    fn from_tag(tag: u8) -> io::Result<Precision> {
+        match tag {
+            0 => Ok(Precision::F32),
+            1 => Ok(Precision::U16),
+            _ => Err(invalid_data(format!("unknown precision {}", tag))),
+        }
+    }
+}
+
+impl Model {
+    /// Doesn't create the file if the model can't be serialized.
+    pub fn save<P: AsRef<Path>>(&self, path: P, precision: Precision) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes, precision)?;
+        fs::write(path, bytes)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Model> {
+        Ok(CompactModel::from_bytes(&fs::read(path)?)?.to_model())
+    }
+
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<Model> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Ok(CompactModel::from_bytes(&bytes)?.to_model())
+    }
+
+    pub fn write_to<W: Write>(&self, mut writer: W, precision: Precision) -> io::Result<()> {
+        if !(1..=MAX_NGRAM_ORDER).contains(&self.ngram_order) {
+            return Err(invalid_data(format!(
+                "unsupported n-gram order {}",
+                self.ngram_order
+            )));
+        }
+        let tokens: BTreeSet<&str> = self
+            .languages
+            .values()
+            .flat_map(|language| language.token_log_probs.keys())
+            .map(String::as_str)
+            .collect();
+        // Checked before anything is written, so that an invalid model
+        // doesn't leave a partial file behind.
+        if let Some(token) = tokens.iter().find(|token| token.len() > MAX_FEATURE_BYTES) {
+            return Err(invalid_data(format!("token is too long: {:?}", token)));
+        }
+        let token_indices: HashMap<&str, u32> = tokens
+            .iter()
+            .enumerate()
+            .map(|(index, token)| (*token, index as u32))
+            .collect();
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&[precision.tag()])?;
+        writer.write_all(&[self.ngram_order as u8])?;
+
+        write_varint(&mut writer, tokens.len())?;
+        for token in tokens.iter() {
+            writer.write_all(&[token.len() as u8])?;
+            writer.write_all(token.as_bytes())?;
+        }
+
+        // Sorted so that the same model always serializes to the same bytes.
+        let mut languages: Vec<_> = self.languages.iter().collect();
+        languages.sort_by_key(|(name, _)| name.as_str());
+        write_varint(&mut writer, languages.len())?;
+        for (name, language) in languages {
+            write_varint(&mut writer, name.len())?;
+            writer.write_all(name.as_bytes())?;
+            writer.write_all(&(language.log_prior as f32).to_le_bytes())?;
+            writer.write_all(&(language.unseen_log_prob as f32).to_le_bytes())?;
+
+            let mut entries: Vec<(u32, f64)> = language
+                .token_log_probs
+                .iter()
+                .map(|(token, log_prob)| (token_indices[token.as_str()], *log_prob))
+                .collect();
+            entries.sort_by_key(|(index, _)| *index);
+            write_varint(&mut writer, entries.len())?;
+
+            let quantizer = match precision {
+                Precision::F32 => None,
+                Precision::U16 => {
+                    let values = entries.iter().map(|(_, log_prob)| *log_prob as f32);
+                    let min = values.clone().fold(f32::INFINITY, f32::min);
+                    let max = values.fold(f32::NEG_INFINITY, f32::max);
+                    let quantizer = if entries.is_empty() {
+                        Quantizer { min: 0.0, max: 0.0 }
+                    } else {
+                        Quantizer { min, max }
+                    };
+                    writer.write_all(&quantizer.min.to_le_bytes())?;
+                    writer.write_all(&quantizer.max.to_le_bytes())?;
+                    Some(quantizer)
+                }
+            };
+
+            let mut previous = 0;
+            for (index, _) in entries.iter() {
+                write_varint(&mut writer, (index - previous) as usize)?;
+                previous = *index;
+            }
+            for (_, log_prob) in entries.iter() {
+                match &quantizer {
+                    None => writer.write_all(&(*log_prob as f32).to_le_bytes())?,
+                    Some(quantizer) => {
+                        writer.write_all(&quantizer.quantize(*log_prob as f32).to_le_bytes())?
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The decoded form of a serialized [`Model`], optimized for scoring rather
+/// than for updating: tokens are mapped to indices once per classification,
+/// and each language maps token indices to their log probabilities.
+#[derive(Debug, Default, Clone)]
+pub struct CompactModel {
+    token_indices: HashMap<Box<str>, u32>,
+    languages: HashMap<Box<str>, CompactLanguageModel>,
+    ngram_order: usize,
+}
+
+#[derive(Debug, Default, Clone)]
+struct CompactLanguageModel {
+    log_prior: f64,
+    unseen_log_prob: f64,
+    /// Hashed rather than sorted, since a binary search for every token and
+    /// candidate made classification noticeably slower.
+    log_probs: HashMap<u32, f32, BuildHasherDefault<TokenIndexHasher>>,
+}
+
+/// Token indices are already small and unique, so they're spread over the
+/// hash table with a multiplication instead of SipHash, which would take
+/// longer than the binary search it replaces.
+#[derive(Default)]
+struct TokenIndexHasher(u64);
+
+impl Hasher for TokenIndexHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, _: &[u8]) {
+        unreachable!("only token indices are hashed");
+    }
+
+    fn write_u32(&mut self, index: u32) {
+        self.0 = (index as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    }
+}
+
+impl CompactLanguageModel {
+    fn log_prob(&self, token_index: u32) -> Option<f64> {
+        self.log_probs
+            .get(&token_index)
+            .map(|log_prob| *log_prob as f64)
+    }
+}
+
+impl CompactModel {
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<CompactModel> {
+        let mut reader = bytes;
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a langur classifier model".to_owned()));
+        }
+        let version = read_u16(&mut reader)?;
+        if version != VERSION {
+            return Err(invalid_data(format!(
+                "unsupported classifier model version {}",
+                version
+            )));
+        }
+        let precision = Precision::from_tag(read_u8(&mut reader)?)?;
+        let ngram_order = read_u8(&mut reader)? as usize;
+        if !(1..=MAX_NGRAM_ORDER).contains(&ngram_order) {
+            return Err(invalid_data(format!(
+                "unsupported n-gram order {}",
+                ngram_order
+            )));
+        }
+
+        let token_count = read_varint(&mut reader)?;
+        let mut token_indices = HashMap::with_capacity(token_count.min(bytes.len()));
+        for index in 0..token_count {
+            let len = read_u8(&mut reader)? as usize;
+            if len > MAX_FEATURE_BYTES {
+                return Err(invalid_data(format!("token is too long: {} bytes", len)));
+            }
+            token_indices.insert(read_string(&mut reader, len)?, index as u32);
+        }
+
+        let language_count = read_varint(&mut reader)?;
+        let mut languages = HashMap::with_capacity(language_count.min(bytes.len()));
+        for _ in 0..language_count {
+            let name_len = read_varint(&mut reader)?;
+            let name = read_string(&mut reader, name_len)?;
+            let log_prior = read_f32(&mut reader)? as f64;
+            let unseen_log_prob = read_f32(&mut reader)? as f64;
+            let entry_count = read_varint(&mut reader)?;
+            // Guards against allocating huge vectors for corrupted counts.
+            if entry_count > token_count {
+                return Err(invalid_data(format!(
+                    "{} has more entries than there are tokens",
+                    name
+                )));
+            }
+            let quantizer = match precision {
+                Precision::F32 => None,
+                Precision::U16 => Some(Quantizer {
+                    min: read_f32(&mut reader)?,
+                    max: read_f32(&mut reader)?,
+                }),
+            };
+
+            let mut indices = Vec::with_capacity(entry_count);
+            let mut index = 0;
+            for position in 0..entry_count {
+                let delta = read_varint(&mut reader)?;
+                if position > 0 && delta == 0 {
+                    return Err(invalid_data(format!("{} has duplicate tokens", name)));
+                }
+                index += delta;
+                if index >= token_count {
+                    return Err(invalid_data(format!(
+                        "token index {} is out of bounds",
+                        index
+                    )));
+                }
+                indices.push(index as u32);
+            }
+            let mut log_probs = HashMap::with_capacity_and_hasher(entry_count, Default::default());
+            for index in indices {
+                let log_prob = match &quantizer {
+                    None => read_f32(&mut reader)?,
+                    Some(quantizer) => quantizer.dequantize(read_u16(&mut reader)?),
+                };
+                log_probs.insert(index, log_prob);
+            }
+
+            languages.insert(
+                name,
+                CompactLanguageModel {
+                    log_prior,
+                    unseen_log_prob,
+                    log_probs,
+                },
+            );
+        }
+        if !reader.is_empty() {
+            return Err(invalid_data("trailing data after the model".to_owned()));
+        }
+
+        Ok(CompactModel {
+            token_indices,
+            languages,
+            ngram_order,
+        })
+    }
+
+    /// The longest runs of consecutive tokens scored as features, see
+    /// [`features`](crate::features).
+    pub fn ngram_order(&self) -> usize {
+        self.ngram_order
+    }
+
+    pub fn contains(&self, language: &str) -> bool {
+        self.languages.contains_key(language)
+    }
+
+    /// Maps tokens to their index in the model, so that they can be scored
+    /// for multiple languages without looking up the strings again. Models
+    /// with n-grams expect the [`features`](crate::features) of the tokens.
+    pub fn token_indices<'t, I>(&self, tokens: I) -> Vec<Option<u32>>
+    where
+        I: IntoIterator<Item = &'t str>,
+    {
+        tokens
+            .into_iter()
+            .map(|token| self.token_indices.get(token).copied())
+            .collect()
+    }
+
+    /// Computes the same score as [`LanguageModel::score`] for tokens mapped
+    /// with [`token_indices`](Self::token_indices). Returns None if the model
+    /// doesn't know about `language`.
+    pub fn score(&self, language: &str, token_indices: &[Option<u32>]) -> Option<f64> {
+        let model = self.languages.get(language)?;
+        Some(token_indices.iter().fold(model.log_prior, |acc, index| {
+            acc + index
+                .and_then(|index| model.log_prob(index))
+                .unwrap_or(model.unseen_log_prob)
+        }))
+    }
+
+    pub fn to_model(&self) -> Model {
+        let mut tokens = vec![""; self.token_indices.len()];
+        for (token, index) in self.token_indices.iter() {
+            tokens[*index as usize] = token;
+        }
+        let languages = self
+            .languages
+            .iter()
+            .map(|(name, language)| {
+                let token_log_probs = language
+                    .log_probs
+                    .iter()
+                    .map(|(index, log_prob)| (tokens[*index as usize].to_owned(), *log_prob as f64))
+                    .collect();
+                let model = LanguageModel {
+                    log_prior: language.log_prior,
+                    unseen_log_prob: language.unseen_log_prob,
+                    token_log_probs,
+                };
+                (name.to_string(), model)
+            })
+            .collect();
+        Model {
+            languages,
+            ngram_order: self.ngram_order,
+        }
+    }
+}
+
+struct Quantizer {
+    min: f32,
+    max: f32,
+}
+
+impl Quantizer {
+    fn step(&self) -> f32 {
+        (self.max - self.min) / u16::MAX as f32
+    }
+
+    fn quantize(&self, value: f32) -> u16 {
+        if self.step() == 0.0 {
+            return 0;
+        }
+        ((value - self.min) / self.step()).round() as u16
+    }
+
+    fn dequantize(&self, value: u16) -> f32 {
+        self.min + value as f32 * self.step()
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn write_varint<W: Write>(writer: &mut W, value: usize) -> io::Result<()> {
+    let mut value =
+        u32::try_from(value).map_err(|_| invalid_data(format!("value is too large: {}", value)))?;
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return writer.write_all(&[byte]);
+        }
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_varint<R: Read>(reader: &mut R) -> io::Result<usize> {
+    let mut value: u32 = 0;
+    for shift in (0..32).step_by(7) {
+        let byte = read_u8(reader)?;
+        value |= ((byte & 0x7f) as u32)
+            .checked_shl(shift)
+            .filter(|bits| bits >> shift == (byte & 0x7f) as u32)
+            .ok_or_else(|| invalid_data("varint is too large".to_owned()))?;
+        if byte & 0x80 == 0 {
+            return Ok(value as usize);
+        }
+    }
+    Err(invalid_data("varint is too large".to_owned()))
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let mut buf = [0; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u16<R: Read>(reader: &mut R) -> io::Result<u16> {
+    let mut buf = [0; 2];
+    reader.read_exact(&mut buf)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+fn read_f32<R: Read>(reader: &mut R) -> io::Result<f32> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(f32::from_le_bytes(buf))
+}
+
+fn read_string<R: Read>(reader: &mut R, len: usize) -> io::Result<Box<str>> {
+    let mut buf = vec![0; len];
+    reader.read_exact(&mut buf)?;
+    String::from_utf8(buf)
+        .map(String::into_boxed_str)
+        .map_err(|e| invalid_data(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{features, tokens, Corpus, TrainingOptions};
+
+    fn model() -> Model {
+        let mut corpus = Corpus::new();
+        corpus.add_sample("Rust", "fn main() { let x = 5; }");
+        corpus.add_sample("Python", "def main(): x = 5");
+        corpus.train(&TrainingOptions::default())
+    }
+
+    fn round_trip(model: &Model, precision: Precision) -> Model {
+        let mut buf = Vec::new();
+        model.write_to(&mut buf, precision).unwrap();
+        Model::read_from(&buf[..]).unwrap()
+    }
+
+    fn assert_close(a: &Model, b: &Model, tolerance: f64) {
+        assert_eq!(
+            a.languages.keys().collect::<Vec<_>>(),
+            b.languages.keys().collect::<Vec<_>>()
+        );
+        for (name, a) in a.languages.iter() {
+            let b = &b.languages[name];
+            assert!((a.log_prior - b.log_prior).abs() < 1e-6);
+            assert!((a.unseen_log_prob - b.unseen_log_prob).abs() < 1e-6);
+            assert_eq!(a.token_log_probs.len(), b.token_log_probs.len());
+            for (token, log_prob) in a.token_log_probs.iter() {
+                assert!((b.token_log_probs[token] - log_prob).abs() < tolerance);
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let model = model();
+        assert_close(&model, &round_trip(&model, Precision::F32), 1e-6);
+        // Log probabilities span a few units, so the quantization error is
+        // around 1e-4.
+        assert_close(&model, &round_trip(&model, Precision::U16), 1e-3);
+    }
+
+    #[test]
+    fn test_compact_model_scores() {
+        let model = model();
+        let mut buf = Vec::new();
+        model.write_to(&mut buf, Precision::F32).unwrap();
+        let compact = CompactModel::from_bytes(&buf).unwrap();
+
+        let content = "fn helper() { let y = 6; } unknown_token";
+        let token_indices = compact.token_indices(tokens(content));
+        for (name, language) in model.languages.iter() {
+            let expected = language.score(tokens(content));
+            let score = compact.score(name, &token_indices).unwrap();
+            assert!((score - expected).abs() < 1e-4, "{} != {}", score, expected);
+        }
+        assert_eq!(compact.score("Go", &token_indices), None);
+    }
+
+    #[test]
+    fn test_ngram_round_trip() {
+        let mut corpus = Corpus::new().with_ngram_order(3);
+        corpus.add_sample("Rust", "impl<T> Foo<T> { fn f() -> u32 {} }");
+        corpus.add_sample("C++", "template <T> std::vector<T> v;");
+        let model = corpus.train(&TrainingOptions::default());
+        let round_tripped = round_trip(&model, Precision::F32);
+        assert_eq!(round_tripped.ngram_order, 3);
+        assert_close(&model, &round_tripped, 1e-6);
+
+        let mut buf = Vec::new();
+        model.write_to(&mut buf, Precision::F32).unwrap();
+        let compact = CompactModel::from_bytes(&buf).unwrap();
+        assert_eq!(compact.ngram_order(), 3);
+        let tokens: Vec<_> = tokens("std::vector<T> w;").collect();
+        let features = features(&tokens, 3);
+        let token_indices = compact.token_indices(features.iter().map(AsRef::as_ref));
+        let expected = model.languages["C++"].score(features.iter().map(AsRef::as_ref));
+        let score = compact.score("C++", &token_indices).unwrap();
+        assert!((score - expected).abs() < 1e-4, "{} != {}", score, expected);
+    }
+
+    #[test]
+    fn test_write_invalid_model() {
+        let mut model = model();
+        model.ngram_order = MAX_NGRAM_ORDER + 1;
+        let mut buf = Vec::new();
+        assert!(model.write_to(&mut buf, Precision::U16).is_err());
+        assert!(buf.is_empty());
+
+        let mut model = self::model();
+        let token = "x".repeat(MAX_FEATURE_BYTES + 1);
+        model
+            .languages
+            .get_mut("Rust")
+            .unwrap()
+            .token_log_probs
+            .insert(token, -1.0);
+        assert!(model.write_to(&mut buf, Precision::U16).is_err());
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_varint() {
+        for value in [0, 1, 127, 128, 300, 16384, u32::MAX as usize] {
+            let mut buf = Vec::new();
+            write_varint(&mut buf, value).unwrap();
+            assert_eq!(read_varint(&mut &buf[..]).unwrap(), value);
+        }
+        assert!(read_varint(&mut &[0xff, 0xff, 0xff, 0xff, 0x7f][..]).is_err());
+    }
+
+    #[test]
+    fn test_invalid_model() {
+        assert!(Model::read_from(&b"LGRX\x01\x00\x00\x01\x00\x00"[..]).is_err());
+        // Unknown version.
+        assert!(Model::read_from(&b"LGRM\x02\x00\x00\x01\x00\x00"[..]).is_err());
+        // Unknown precision.
+        assert!(Model::read_from(&b"LGRM\x01\x00\x07\x01\x00\x00"[..]).is_err());
+        // Unsupported n-gram order.
+        assert!(Model::read_from(&b"LGRM\x01\x00\x00\x04\x00\x00"[..]).is_err());
+        assert_eq!(
+            Model::read_from(&b"LGRM\x01\x00\x00\x01\x00\x00"[..]).unwrap(),
+            Model::default()
+        );
+        assert!(Model::read_from(&b""[..]).is_err());
+
+        let mut buf = Vec::new();
+        model().write_to(&mut buf, Precision::U16).unwrap();
+        assert!(Model::read_from(&buf[..buf.len() - 1]).is_err());
+        buf.push(0);
+        assert!(Model::read_from(&buf[..]).is_err());
+    }
+}
@@ -0,0 +1,164 @@
+#ifndef LANGUR_H
+#define LANGUR_H
+
+/* Generated with cbindgen from src/lib.rs, do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The outcome of `langur_detect_bytes`.
+typedef enum LangurStatus {
+  // A language was detected.
+  LANGUR_STATUS_OK,
+  // The language couldn't be determined.
+  LANGUR_STATUS_UNKNOWN,
+  // A pointer was NULL or the filename wasn't valid UTF-8.
+  LANGUR_STATUS_INVALID_ARGUMENT,
+  // The content wasn't valid UTF-8, or detection failed unexpectedly.
+  LANGUR_STATUS_ERROR,
+} LangurStatus;
+
+// The strategy that detected a language, see `langur::Detection`.
+typedef enum LangurStrategy {
+  LANGUR_STRATEGY_FILENAME,
+  LANGUR_STRATEGY_EXTENSION,
+  LANGUR_STRATEGY_SHEBANG,
+  LANGUR_STRATEGY_HEURISTICS,
+  LANGUR_STRATEGY_CLASSIFIER,
+  LANGUR_STRATEGY_MODELINE,
+  LANGUR_STRATEGY_CONTEXT,
+} LangurStrategy;
+
+// The languages detected in a directory, from `langur_breakdown`.
+//
+// Languages are sorted by the number of files, most files first,
+// and the files of each language are sorted by path.
+typedef struct LangurBreakdown LangurBreakdown;
+
+// The language of a single file and how it was detected.
+typedef struct LangurDetection {
+  // The Linguist `language_id`.
+  int64_t language;
+  enum LangurStrategy strategy;
+} LangurDetection;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Detects the language of a file from its name and content, without
+// reading it from disk. The filename may include directories.
+//
+// On `LANGUR_STATUS_OK`, the result is written to `detection`,
+// which is left untouched otherwise.
+//
+// # Safety
+// `filename` must be a NUL-terminated string, `content` must point to
+// `content_len` readable bytes (or may be NULL if `content_len` is 0),
+// and `detection` must be valid for writes.
+enum LangurStatus langur_detect_bytes(const char *filename,
+                                      const uint8_t *content,
+                                      size_t content_len,
+                                      struct LangurDetection *detection);
+
+// Returns the name of a language, as listed in Linguist's languages.yml
+// file, or NULL if `language` isn't a known `language_id`.
+const char *langur_language_name(int64_t language);
+
+// Writes the `language_id`s of the languages that use the extension of
+// `filename` to `languages`, without reading the file, and returns how
+// many there are. At most `capacity` ids are written, so if the result is
+// larger than `capacity`, call it again with a larger buffer.
+//
+// Returns 0 if the extension is unknown, or if `filename` is NULL or not
+// valid UTF-8.
+//
+// # Safety
+// `filename` must be NULL or a NUL-terminated string, and `languages`
+// must be valid for `capacity` writes (or may be NULL if `capacity` is 0).
+size_t langur_languages_by_extension(const char *filename, int64_t *languages, size_t capacity);
+
+// Returns true if `path` is vendored, like `node_modules/`, which
+// `langur_breakdown` skips. `path` is relative to the root of a repository,
+// and directories should end with a `/`.
+//
+// # Safety
+// `path` must be NULL or a NUL-terminated string.
+bool langur_is_vendor(const char *path);
+
+// Returns true if `path` is documentation, like `docs/` or a README,
+// which `langur_breakdown` skips. Paths are the same as for
+// `langur_is_vendor`.
+//
+// # Safety
+// `path` must be NULL or a NUL-terminated string.
+bool langur_is_documentation(const char *path);
+
+// Returns true if the file at `path` with the given content was generated,
+// like a lock file or a file with a "Code generated by" header.
+// Paths are the same as for `langur_is_vendor`.
+//
+// # Safety
+// `path` must be NULL or a NUL-terminated string, and `content` must point
+// to `content_len` readable bytes (or may be NULL if `content_len` is 0).
+bool langur_is_generated(const char *path, const uint8_t *content, size_t content_len);
+
+// Detects the languages of all the files in a directory, skipping
+// ignored, vendored and documentation files like the `langur` CLI does.
+//
+// Returns NULL if `path` is NULL or not valid UTF-8. The result must be
+// freed with `langur_breakdown_free`.
+//
+// # Safety
+// `path` must be NULL or a NUL-terminated string.
+struct LangurBreakdown *langur_breakdown(const char *path);
+
+// Returns the number of languages in a breakdown.
+//
+// # Safety
+// `breakdown` must come from `langur_breakdown` and not have been freed.
+size_t langur_breakdown_language_count(const struct LangurBreakdown *breakdown);
+
+// Returns the `language_id` of the language at `index`, or -1 if `index`
+// is out of bounds.
+//
+// # Safety
+// `breakdown` must come from `langur_breakdown` and not have been freed.
+int64_t langur_breakdown_language(const struct LangurBreakdown *breakdown, size_t index);
+
+// Returns the number of files detected as the language at
+// `language_index`, or 0 if `language_index` is out of bounds.
+//
+// # Safety
+// `breakdown` must come from `langur_breakdown` and not have been freed.
+size_t langur_breakdown_file_count(const struct LangurBreakdown *breakdown, size_t language_index);
+
+// Returns the path of a file detected as the language at `language_index`,
+// or NULL if either index is out of bounds. Unless `strategy` is NULL,
+// the strategy that detected the file is written to it.
+//
+// Paths that aren't valid UTF-8 are converted lossily.
+//
+// # Safety
+// `breakdown` must come from `langur_breakdown` and not have been freed,
+// and `strategy` must be NULL or valid for writes.
+const char *langur_breakdown_file(const struct LangurBreakdown *breakdown,
+                                  size_t language_index,
+                                  size_t file_index,
+                                  enum LangurStrategy *strategy);
+
+// Frees a breakdown. Passing NULL is allowed and does nothing.
+//
+// # Safety
+// `breakdown` must be NULL or come from `langur_breakdown`, and must not
+// be used afterwards.
+void langur_breakdown_free(struct LangurBreakdown *breakdown);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* LANGUR_H */
@@ -0,0 +1,580 @@
+//! C bindings for Langur. The header is generated from this file with
+//! [cbindgen](https://github.com/mozilla/cbindgen) and checked in as
+//! `include/langur.h`, see Development.md for how to regenerate it.
+//!
+//! Languages are identified by their `language_id` from Linguist's
+//! languages.yml file, see `langur_language_name`.
+//!
+//! Strings returned by these functions are owned by Langur and must not be
+//! freed by the caller. Language names are never freed, and the paths in a
+//! breakdown are valid until the breakdown is passed to
+//! `langur_breakdown_free`.
+//!
+//! Panics are caught at the FFI boundary and reported as errors.
+
+use lazy_static::lazy_static;
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    ffi::{CStr, CString},
+    os::raw::c_char,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    ptr, slice,
+};
+
+use langur::{Detection, Language};
+
+/// The strategy that detected a language, see `langur::Detection`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LangurStrategy {
+    Filename,
+    Extension,
+    Shebang,
+    Heuristics,
+    Classifier,
+    Modeline,
+    Context,
+}
+
+/// The outcome of `langur_detect_bytes`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LangurStatus {
+    /// A language was detected.
+    Ok,
+    /// The language couldn't be determined.
+    Unknown,
+    /// A pointer was NULL or the filename wasn't valid UTF-8.
+    InvalidArgument,
+    /// The content wasn't valid UTF-8, or detection failed unexpectedly.
+    Error,
+}
+
+/// The language of a single file and how it was detected.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LangurDetection {
+    /// The Linguist `language_id`.
+    pub language: i64,
+    pub strategy: LangurStrategy,
+}
+
+impl From<Detection> for LangurDetection {
+    fn from(detection: Detection) -> Self {
+        let strategy = match detection {
+            Detection::Filename(_) => LangurStrategy::Filename,
+            Detection::Extension(_) => LangurStrategy::Extension,
+            Detection::Shebang(_) => LangurStrategy::Shebang,
+            Detection::Heuristics(_) => LangurStrategy::Heuristics,
+            Detection::Classifier(_) => LangurStrategy::Classifier,
+            Detection::Modeline(_) => LangurStrategy::Modeline,
+            Detection::Context(_) => LangurStrategy::Context,
+        };
+        LangurDetection {
+            language: detection.language().id(),
+            strategy,
+        }
+    }
+}
+
+/// The languages detected in a directory, from `langur_breakdown`.
+///
+/// Languages are sorted by the number of files, most files first,
+/// and the files of each language are sorted by path.
+pub struct LangurBreakdown {
+    languages: Vec<BreakdownLanguage>,
+}
+
+struct BreakdownLanguage {
+    language: i64,
+    files: Vec<BreakdownFile>,
+}
+
+struct BreakdownFile {
+    path: CString,
+    strategy: LangurStrategy,
+}
+
+lazy_static! {
+    static ref LANGUAGE_NAMES: HashMap<i64, CString> = Language::VARIANTS
+        .iter()
+        .map(|language| {
+            let name = CString::new(language.name()).expect("names don't contain NUL");
+            (language.id(), name)
+        })
+        .collect();
+}
+
+/// Returns None for NULL and for strings that aren't valid UTF-8.
+///
+/// # Safety
+/// `s` must be NULL or a NUL-terminated string.
+unsafe fn to_str<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        return None;
+    }
+    CStr::from_ptr(s).to_str().ok()
+}
+
+/// Detects the language of a file from its name and content, without
+/// reading it from disk. The filename may include directories.
+///
+/// On `LANGUR_STATUS_OK`, the result is written to `detection`,
+/// which is left untouched otherwise.
+///
+/// # Safety
+/// `filename` must be a NUL-terminated string, `content` must point to
+/// `content_len` readable bytes (or may be NULL if `content_len` is 0),
+/// and `detection` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn langur_detect_bytes(
+    filename: *const c_char,
+    content: *const u8,
+    content_len: usize,
+    detection: *mut LangurDetection,
+) -> LangurStatus {
+    let filename = match to_str(filename) {
+        Some(filename) => filename,
+        None => return LangurStatus::InvalidArgument,
+    };
+    if detection.is_null() || (content.is_null() && content_len > 0) {
+        return LangurStatus::InvalidArgument;
+    }
+    let content = if content_len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(content, content_len)
+    };
+
+    match panic::catch_unwind(|| langur::detect_bytes(Path::new(filename), content)) {
+        Ok(Ok(Some(result))) => {
+            *detection = result.into();
+            LangurStatus::Ok
+        }
+        Ok(Ok(None)) => LangurStatus::Unknown,
+        Ok(Err(_)) | Err(_) => LangurStatus::Error,
+    }
+}
+
+/// Returns the name of a language, as listed in Linguist's languages.yml
+/// file, or NULL if `language` isn't a known `language_id`.
+#[no_mangle]
+pub extern "C" fn langur_language_name(language: i64) -> *const c_char {
+    match Language::try_from(language) {
+        Ok(language) => LANGUAGE_NAMES
+            .get(&language.id())
+            .map_or(ptr::null(), |name| name.as_ptr()),
+        Err(()) => ptr::null(),
+    }
+}
+
+/// Writes the `language_id`s of the languages that use the extension of
+/// `filename` to `languages`, without reading the file, and returns how
+/// many there are. At most `capacity` ids are written, so if the result is
+/// larger than `capacity`, call it again with a larger buffer.
+///
+/// Returns 0 if the extension is unknown, or if `filename` is NULL or not
+/// valid UTF-8.
+///
+/// # Safety
+/// `filename` must be NULL or a NUL-terminated string, and `languages`
+/// must be valid for `capacity` writes (or may be NULL if `capacity` is 0).
+#[no_mangle]
+pub unsafe extern "C" fn langur_languages_by_extension(
+    filename: *const c_char,
+    languages: *mut i64,
+    capacity: usize,
+) -> usize {
+    let filename = match to_str(filename) {
+        Some(filename) => filename,
+        None => return 0,
+    };
+    let candidates =
+        match panic::catch_unwind(|| langur::languages_by_extension(Path::new(filename))) {
+            Ok(candidates) => candidates,
+            Err(_) => return 0,
+        };
+    if !languages.is_null() {
+        for (i, language) in candidates.iter().take(capacity).enumerate() {
+            *languages.add(i) = language.id();
+        }
+    }
+    candidates.len()
+}
+
+/// Returns true if `path` is vendored, like `node_modules/`, which
+/// `langur_breakdown` skips. `path` is relative to the root of a repository,
+/// and directories should end with a `/`.
+///
+/// # Safety
+/// `path` must be NULL or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn langur_is_vendor(path: *const c_char) -> bool {
+    match to_str(path) {
+        Some(path) => panic::catch_unwind(|| langur::is_vendor(path)).unwrap_or(false),
+        None => false,
+    }
+}
+
+/// Returns true if `path` is documentation, like `docs/` or a README,
+/// which `langur_breakdown` skips. Paths are the same as for
+/// `langur_is_vendor`.
+///
+/// # Safety
+/// `path` must be NULL or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn langur_is_documentation(path: *const c_char) -> bool {
+    match to_str(path) {
+        Some(path) => panic::catch_unwind(|| langur::is_documentation(path)).unwrap_or(false),
+        None => false,
+    }
+}
+
+/// Returns true if the file at `path` with the given content was generated,
+/// like a lock file or a file with a "Code generated by" header.
+/// Paths are the same as for `langur_is_vendor`.
+///
+/// # Safety
+/// `path` must be NULL or a NUL-terminated string, and `content` must point
+/// to `content_len` readable bytes (or may be NULL if `content_len` is 0).
+#[no_mangle]
+pub unsafe extern "C" fn langur_is_generated(
+    path: *const c_char,
+    content: *const u8,
+    content_len: usize,
+) -> bool {
+    let path = match to_str(path) {
+        Some(path) => path,
+        None => return false,
+    };
+    if content.is_null() && content_len > 0 {
+        return false;
+    }
+    let content = if content_len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(content, content_len)
+    };
+    panic::catch_unwind(|| langur::is_generated(path, content)).unwrap_or(false)
+}
+
+/// Detects the languages of all the files in a directory, skipping
+/// ignored, vendored and documentation files like the `langur` CLI does.
+///
+/// Returns NULL if `path` is NULL or not valid UTF-8. The result must be
+/// freed with `langur_breakdown_free`.
+///
+/// # Safety
+/// `path` must be NULL or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn langur_breakdown(path: *const c_char) -> *mut LangurBreakdown {
+    let path = match to_str(path) {
+        Some(path) => path,
+        None => return ptr::null_mut(),
+    };
+    let breakdown = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut languages: Vec<BreakdownLanguage> =
+            langur::get_language_breakdown(path, &Default::default())
+                .into_iter()
+                .map(|(language, detections)| {
+                    let mut files: Vec<BreakdownFile> = detections
+                        .into_iter()
+                        .filter_map(|(detection, path)| {
+                            Some(BreakdownFile {
+                                path: CString::new(path.to_string_lossy().into_owned()).ok()?,
+                                strategy: LangurDetection::from(detection).strategy,
+                            })
+                        })
+                        .collect();
+                    files.sort_by(|a, b| a.path.cmp(&b.path));
+                    BreakdownLanguage {
+                        language: language.id(),
+                        files,
+                    }
+                })
+                .collect();
+        languages.sort_by(|a, b| {
+            b.files
+                .len()
+                .cmp(&a.files.len())
+                .then(a.language.cmp(&b.language))
+        });
+        LangurBreakdown { languages }
+    }));
+    match breakdown {
+        Ok(breakdown) => Box::into_raw(Box::new(breakdown)),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Returns the number of languages in a breakdown.
+///
+/// # Safety
+/// `breakdown` must come from `langur_breakdown` and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn langur_breakdown_language_count(
+    breakdown: *const LangurBreakdown,
+) -> usize {
+    breakdown
+        .as_ref()
+        .map_or(0, |breakdown| breakdown.languages.len())
+}
+
+/// Returns the `language_id` of the language at `index`, or -1 if `index`
+/// is out of bounds.
+///
+/// # Safety
+/// `breakdown` must come from `langur_breakdown` and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn langur_breakdown_language(
+    breakdown: *const LangurBreakdown,
+    index: usize,
+) -> i64 {
+    breakdown
+        .as_ref()
+        .and_then(|breakdown| breakdown.languages.get(index))
+        .map_or(-1, |language| language.language)
+}
+
+/// Returns the number of files detected as the language at
+/// `language_index`, or 0 if `language_index` is out of bounds.
+///
+/// # Safety
+/// `breakdown` must come from `langur_breakdown` and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn langur_breakdown_file_count(
+    breakdown: *const LangurBreakdown,
+    language_index: usize,
+) -> usize {
+    breakdown
+        .as_ref()
+        .and_then(|breakdown| breakdown.languages.get(language_index))
+        .map_or(0, |language| language.files.len())
+}
+
+/// Returns the path of a file detected as the language at `language_index`,
+/// or NULL if either index is out of bounds. Unless `strategy` is NULL,
+/// the strategy that detected the file is written to it.
+///
+/// Paths that aren't valid UTF-8 are converted lossily.
+///
+/// # Safety
+/// `breakdown` must come from `langur_breakdown` and not have been freed,
+/// and `strategy` must be NULL or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn langur_breakdown_file(
+    breakdown: *const LangurBreakdown,
+    language_index: usize,
+    file_index: usize,
+    strategy: *mut LangurStrategy,
+) -> *const c_char {
+    let file = breakdown
+        .as_ref()
+        .and_then(|breakdown| breakdown.languages.get(language_index))
+        .and_then(|language| language.files.get(file_index));
+    match file {
+        Some(file) => {
+            if !strategy.is_null() {
+                *strategy = file.strategy;
+            }
+            file.path.as_ptr()
+        }
+        None => ptr::null(),
+    }
+}
+
+/// Frees a breakdown. Passing NULL is allowed and does nothing.
+///
+/// # Safety
+/// `breakdown` must be NULL or come from `langur_breakdown`, and must not
+/// be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn langur_breakdown_free(breakdown: *mut LangurBreakdown) {
+    if !breakdown.is_null() {
+        drop(Box::from_raw(breakdown));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use langur::ids;
+    use std::fs;
+
+    fn c_string(s: &str) -> CString {
+        CString::new(s).unwrap()
+    }
+
+    fn detect(filename: &str, content: &[u8]) -> (LangurStatus, Option<LangurDetection>) {
+        let filename = c_string(filename);
+        let mut detection = LangurDetection {
+            language: -1,
+            strategy: LangurStrategy::Filename,
+        };
+        let status = unsafe {
+            langur_detect_bytes(
+                filename.as_ptr(),
+                content.as_ptr(),
+                content.len(),
+                &mut detection,
+            )
+        };
+        let detection = match status {
+            LangurStatus::Ok => Some(detection),
+            _ => None,
+        };
+        (status, detection)
+    }
+
+    #[test]
+    fn test_detect_bytes() {
+        assert_eq!(
+            detect("src/lib.purs", b""),
+            (
+                LangurStatus::Ok,
+                Some(LangurDetection {
+                    language: ids::PureScript.id(),
+                    strategy: LangurStrategy::Extension,
+                })
+            )
+        );
+        assert_eq!(
+            detect("run", b"#!/usr/bin/env python\n"),
+            (
+                LangurStatus::Ok,
+                Some(LangurDetection {
+                    language: ids::Python.id(),
+                    strategy: LangurStrategy::Shebang,
+                })
+            )
+        );
+        assert_eq!(detect("unknown", b"???"), (LangurStatus::Unknown, None));
+        assert_eq!(detect("a.es", b"\xff\xfe"), (LangurStatus::Error, None));
+
+        let mut detection = LangurDetection {
+            language: -1,
+            strategy: LangurStrategy::Filename,
+        };
+        let status = unsafe { langur_detect_bytes(ptr::null(), ptr::null(), 0, &mut detection) };
+        assert_eq!(status, LangurStatus::InvalidArgument);
+        let filename = c_string("a.rs");
+        let status =
+            unsafe { langur_detect_bytes(filename.as_ptr(), ptr::null(), 1, &mut detection) };
+        assert_eq!(status, LangurStatus::InvalidArgument);
+    }
+
+    #[test]
+    fn test_language_name() {
+        let name = langur_language_name(ids::Rust.id());
+        assert_eq!(unsafe { CStr::from_ptr(name) }.to_str(), Ok("Rust"));
+        assert!(langur_language_name(-1).is_null());
+    }
+
+    #[test]
+    fn test_languages_by_extension() {
+        let filename = c_string("src/main.h");
+        let count = unsafe { langur_languages_by_extension(filename.as_ptr(), ptr::null_mut(), 0) };
+        assert!(count > 1);
+        let mut languages = vec![-1; count];
+        let written = unsafe {
+            langur_languages_by_extension(filename.as_ptr(), languages.as_mut_ptr(), count)
+        };
+        assert_eq!(written, count);
+        assert!(languages.contains(&ids::C.id()));
+
+        let mut language = -1;
+        let filename = c_string("Main.purs");
+        assert_eq!(
+            unsafe { langur_languages_by_extension(filename.as_ptr(), &mut language, 1) },
+            1
+        );
+        assert_eq!(language, ids::PureScript.id());
+        let filename = c_string("unknown");
+        assert_eq!(
+            unsafe { langur_languages_by_extension(filename.as_ptr(), &mut language, 1) },
+            0
+        );
+    }
+
+    #[test]
+    fn test_filters() {
+        let vendored = c_string("node_modules/");
+        let readme = c_string("README.md");
+        let lock_file = c_string("Cargo.lock");
+        unsafe {
+            assert!(langur_is_vendor(vendored.as_ptr()));
+            assert!(!langur_is_vendor(readme.as_ptr()));
+            assert!(langur_is_documentation(readme.as_ptr()));
+            assert!(!langur_is_documentation(ptr::null()));
+            assert!(langur_is_generated(lock_file.as_ptr(), ptr::null(), 0));
+            assert!(!langur_is_generated(readme.as_ptr(), ptr::null(), 0));
+        }
+    }
+
+    #[test]
+    fn test_breakdown() {
+        let dir = std::env::temp_dir().join("langur_ffi_breakdown");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.join("src/lib.rs"), "pub fn lib() {}").unwrap();
+        fs::write(dir.join("build.py"), "print('hi')").unwrap();
+
+        let path = c_string(dir.to_str().unwrap());
+        let breakdown = unsafe { langur_breakdown(path.as_ptr()) };
+        assert!(!breakdown.is_null());
+
+        let mut languages = Vec::new();
+        for language_index in 0..unsafe { langur_breakdown_language_count(breakdown) } {
+            let mut files = Vec::new();
+            for file_index in 0..unsafe { langur_breakdown_file_count(breakdown, language_index) } {
+                let mut strategy = LangurStrategy::Classifier;
+                let path = unsafe {
+                    CStr::from_ptr(langur_breakdown_file(
+                        breakdown,
+                        language_index,
+                        file_index,
+                        &mut strategy,
+                    ))
+                };
+                let path = Path::new(path.to_str().unwrap());
+                let path = path
+                    .strip_prefix(&dir)
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .to_owned();
+                files.push((path, strategy));
+            }
+            let language = unsafe { langur_breakdown_language(breakdown, language_index) };
+            languages.push((language, files));
+        }
+        assert_eq!(unsafe { langur_breakdown_language(breakdown, 2) }, -1);
+        assert_eq!(unsafe { langur_breakdown_file_count(breakdown, 2) }, 0);
+        assert!(unsafe { langur_breakdown_file(breakdown, 0, 2, ptr::null_mut()) }.is_null());
+        unsafe { langur_breakdown_free(breakdown) };
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            languages,
+            vec![
+                (
+                    ids::Rust.id(),
+                    vec![
+                        ("src/lib.rs".to_owned(), LangurStrategy::Heuristics),
+                        ("src/main.rs".to_owned(), LangurStrategy::Heuristics),
+                    ]
+                ),
+                (
+                    ids::Python.id(),
+                    vec![("build.py".to_owned(), LangurStrategy::Extension)]
+                ),
+            ]
+        );
+
+        assert!(unsafe { langur_breakdown(ptr::null()) }.is_null());
+        unsafe { langur_breakdown_free(ptr::null_mut()) };
+    }
+}
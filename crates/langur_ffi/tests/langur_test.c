@@ -0,0 +1,154 @@
+// Exercises the C API the way a C or C++ caller would.
+
+#define _XOPEN_SOURCE 700
+
+#include "langur.h"
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+
+static int failures = 0;
+
+#define CHECK(condition)                                                       \
+  do {                                                                         \
+    if (!(condition)) {                                                        \
+      fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__,         \
+              #condition);                                                     \
+      failures++;                                                              \
+    }                                                                          \
+  } while (0)
+
+static void write_file(const char *path, const char *content) {
+  FILE *file = fopen(path, "w");
+  if (file == NULL) {
+    perror(path);
+    exit(1);
+  }
+  fputs(content, file);
+  fclose(file);
+}
+
+static void test_detect_bytes(void) {
+  LangurDetection detection;
+  const char *content = "#!/usr/bin/env python\nprint('hi')\n";
+
+  CHECK(langur_detect_bytes("run", (const uint8_t *)content, strlen(content),
+                            &detection) == LANGUR_STATUS_OK);
+  CHECK(detection.strategy == LANGUR_STRATEGY_SHEBANG);
+  CHECK(strcmp(langur_language_name(detection.language), "Python") == 0);
+
+  CHECK(langur_detect_bytes("src/Main.purs", NULL, 0, &detection) ==
+        LANGUR_STATUS_OK);
+  CHECK(detection.strategy == LANGUR_STRATEGY_EXTENSION);
+  CHECK(strcmp(langur_language_name(detection.language), "PureScript") == 0);
+
+  CHECK(langur_detect_bytes("unknown", (const uint8_t *)"???", 3,
+                            &detection) == LANGUR_STATUS_UNKNOWN);
+  CHECK(langur_detect_bytes("a.es", (const uint8_t *)"\xff\xfe", 2,
+                            &detection) == LANGUR_STATUS_ERROR);
+  CHECK(langur_detect_bytes(NULL, NULL, 0, &detection) ==
+        LANGUR_STATUS_INVALID_ARGUMENT);
+  CHECK(langur_detect_bytes("a.rs", NULL, 0, NULL) ==
+        LANGUR_STATUS_INVALID_ARGUMENT);
+}
+
+static void test_language_name(void) {
+  CHECK(langur_language_name(-1) == NULL);
+}
+
+static void test_languages_by_extension(void) {
+  int64_t languages[64];
+  size_t count = langur_languages_by_extension("src/main.h", NULL, 0);
+  CHECK(count > 1 && count <= 64);
+  CHECK(langur_languages_by_extension("src/main.h", languages, 64) == count);
+  int found_c = 0;
+  for (size_t i = 0; i < count && i < 64; i++) {
+    found_c |= strcmp(langur_language_name(languages[i]), "C") == 0;
+  }
+  CHECK(found_c);
+
+  CHECK(langur_languages_by_extension("Main.purs", languages, 1) == 1);
+  CHECK(strcmp(langur_language_name(languages[0]), "PureScript") == 0);
+  CHECK(langur_languages_by_extension("unknown", languages, 64) == 0);
+}
+
+static void test_filters(void) {
+  CHECK(langur_is_vendor("node_modules/"));
+  CHECK(!langur_is_vendor("src/main.rs"));
+  CHECK(langur_is_documentation("docs/index.md"));
+  CHECK(!langur_is_documentation(NULL));
+
+  const char *content = "// Code generated by stringer. DO NOT EDIT.\n";
+  CHECK(langur_is_generated("kind_string.go", (const uint8_t *)content,
+                            strlen(content)));
+  CHECK(!langur_is_generated("kind.go", NULL, 0));
+}
+
+static void test_breakdown(void) {
+  const char *tmp = getenv("TEST_TMPDIR");
+  char dir[1024];
+  char path[4096];
+  snprintf(dir, sizeof(dir), "%s/langur_test_XXXXXX", tmp ? tmp : "/tmp");
+  if (mkdtemp(dir) == NULL) {
+    perror("mkdtemp");
+    exit(1);
+  }
+  snprintf(path, sizeof(path), "%s/main.rs", dir);
+  write_file(path, "fn main() {}\n");
+  snprintf(path, sizeof(path), "%s/lib.rs", dir);
+  write_file(path, "pub fn lib() {}\n");
+  snprintf(path, sizeof(path), "%s/build.py", dir);
+  write_file(path, "print('hi')\n");
+
+  LangurBreakdown *breakdown = langur_breakdown(dir);
+  CHECK(breakdown != NULL);
+  CHECK(langur_breakdown_language_count(breakdown) == 2);
+
+  // Rust has the most files, so it comes first.
+  CHECK(strcmp(langur_language_name(langur_breakdown_language(breakdown, 0)),
+               "Rust") == 0);
+  CHECK(langur_breakdown_file_count(breakdown, 0) == 2);
+  LangurStrategy strategy;
+  const char *file = langur_breakdown_file(breakdown, 0, 0, &strategy);
+  snprintf(path, sizeof(path), "%s/lib.rs", dir);
+  CHECK(file != NULL && strcmp(file, path) == 0);
+
+  CHECK(strcmp(langur_language_name(langur_breakdown_language(breakdown, 1)),
+               "Python") == 0);
+  file = langur_breakdown_file(breakdown, 1, 0, &strategy);
+  snprintf(path, sizeof(path), "%s/build.py", dir);
+  CHECK(file != NULL && strcmp(file, path) == 0);
+  CHECK(strategy == LANGUR_STRATEGY_EXTENSION);
+
+  CHECK(langur_breakdown_language(breakdown, 2) == -1);
+  CHECK(langur_breakdown_file_count(breakdown, 2) == 0);
+  CHECK(langur_breakdown_file(breakdown, 0, 2, NULL) == NULL);
+  langur_breakdown_free(breakdown);
+
+  CHECK(langur_breakdown(NULL) == NULL);
+  langur_breakdown_free(NULL);
+
+  snprintf(path, sizeof(path), "%s/main.rs", dir);
+  unlink(path);
+  snprintf(path, sizeof(path), "%s/lib.rs", dir);
+  unlink(path);
+  snprintf(path, sizeof(path), "%s/build.py", dir);
+  unlink(path);
+  rmdir(dir);
+}
+
+int main(void) {
+  test_detect_bytes();
+  test_language_name();
+  test_languages_by_extension();
+  test_filters();
+  test_breakdown();
+  if (failures > 0) {
+    fprintf(stderr, "%d checks failed\n", failures);
+    return 1;
+  }
+  printf("All checks passed\n");
+  return 0;
+}
@@ -0,0 +1,171 @@
+//! Python bindings for Langur, built as the `langur` module with
+//! [maturin](https://github.com/PyO3/maturin).
+//!
+//! Detections are returned as dicts with the name of the language and the
+//! strategy that detected it, e.g. `{"language": "Rust", "strategy": "Heuristics"}`,
+//! and [`Language`] gives access to the rest of the metadata.
+
+// The code generated by #[pyfunction] and #[pymethods] converts PyErr into itself.
+#![allow(clippy::useless_conversion)]
+
+use pyo3::{
+    basic::CompareOp,
+    exceptions::{PyKeyError, PyValueError},
+    prelude::*,
+    types::{PyDict, PyList},
+};
+use std::{
+    collections::hash_map::DefaultHasher,
+    convert::TryFrom,
+    hash::{Hash, Hasher},
+    path::PathBuf,
+};
+
+use langur::{Detection, LanguageType};
+
+/// A language from Linguist's languages.yml file.
+#[pyclass(module = "langur", frozen)]
+#[derive(Clone, Copy)]
+struct Language {
+    inner: langur::Language,
+}
+
+#[pymethods]
+impl Language {
+    /// Looks up a language by its name, which is case-sensitive.
+    #[new]
+    fn new(name: &str) -> PyResult<Self> {
+        langur::Language::try_from(name)
+            .map(|inner| Language { inner })
+            .map_err(|()| PyKeyError::new_err(format!("unknown language: {}", name)))
+    }
+
+    /// Looks up a language by its Linguist `language_id`.
+    #[staticmethod]
+    fn from_id(id: i64) -> PyResult<Self> {
+        langur::Language::try_from(id)
+            .map(|inner| Language { inner })
+            .map_err(|()| PyKeyError::new_err(format!("unknown language id: {}", id)))
+    }
+
+    #[getter]
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    #[getter]
+    fn id(&self) -> i64 {
+        self.inner.id()
+    }
+
+    /// One of "data", "markup", "programming" or "prose".
+    #[getter]
+    fn r#type(&self) -> &'static str {
+        match self.inner.language_type() {
+            LanguageType::Data => "data",
+            LanguageType::Markup => "markup",
+            LanguageType::Programming => "programming",
+            LanguageType::Prose => "prose",
+        }
+    }
+
+    #[getter]
+    fn color(&self) -> Option<&'static str> {
+        self.inner.color()
+    }
+
+    #[getter]
+    fn group(&self) -> Option<Language> {
+        self.inner.group().map(|inner| Language { inner })
+    }
+
+    #[getter]
+    fn aliases(&self) -> Vec<&'static str> {
+        self.inner.aliases().to_vec()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Language({:?})", self.inner.name())
+    }
+
+    fn __richcmp__(&self, other: &Self, op: CompareOp, py: Python<'_>) -> PyObject {
+        match op {
+            CompareOp::Eq => (self.inner == other.inner).into_py(py),
+            CompareOp::Ne => (self.inner != other.inner).into_py(py),
+            _ => py.NotImplemented(),
+        }
+    }
+
+    fn __hash__(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.inner.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+fn detection_dict(py: Python<'_>, detection: Detection) -> PyResult<Bound<'_, PyDict>> {
+    let dict = PyDict::new_bound(py);
+    dict.set_item("language", detection.language().name())?;
+    dict.set_item("strategy", detection.variant())?;
+    Ok(dict)
+}
+
+/// Detects the language of the file at `path`, returning None if it can't
+/// be determined.
+#[pyfunction]
+fn detect(py: Python<'_>, path: PathBuf) -> PyResult<Option<Bound<'_, PyDict>>> {
+    match py.allow_threads(|| langur::detect(&path))? {
+        Some(detection) => Ok(Some(detection_dict(py, detection)?)),
+        None => Ok(None),
+    }
+}
+
+/// Same as `detect`, but for content that has already been read.
+/// `filename` is only used for its name and extension, so it doesn't need
+/// to exist. Raises ValueError if `content` isn't valid UTF-8.
+#[pyfunction]
+fn detect_bytes<'py>(
+    py: Python<'py>,
+    filename: PathBuf,
+    content: &[u8],
+) -> PyResult<Option<Bound<'py, PyDict>>> {
+    match py.allow_threads(|| langur::detect_bytes(&filename, content)) {
+        Ok(Some(detection)) => Ok(Some(detection_dict(py, detection)?)),
+        Ok(None) => Ok(None),
+        Err(e) => Err(PyValueError::new_err(e.to_string())),
+    }
+}
+
+/// Detects the languages of all the files under `root`, skipping ignored,
+/// vendored and documentation files like the `langur` CLI does.
+///
+/// Returns a dict from language names to lists of dicts with the `path`
+/// and `strategy` of each file, sorted by path.
+#[pyfunction]
+fn breakdown(py: Python<'_>, root: PathBuf) -> PyResult<Bound<'_, PyDict>> {
+    let breakdown = py.allow_threads(|| langur::get_language_breakdown(&root, &Default::default()));
+    let result = PyDict::new_bound(py);
+    for (language, mut detections) in breakdown {
+        detections.sort_by(|(_, a), (_, b)| a.cmp(b));
+        let files = PyList::empty_bound(py);
+        for (detection, path) in detections {
+            let file = PyDict::new_bound(py);
+            file.set_item("path", path)?;
+            file.set_item("strategy", detection.variant())?;
+            files.append(file)?;
+        }
+        result.set_item(language.name(), files)?;
+    }
+    Ok(result)
+}
+
+/// Fast programming language detection based on GitHub's Linguist data.
+#[pymodule]
+#[pyo3(name = "langur")]
+fn langur_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Language>()?;
+    m.add_function(wrap_pyfunction!(detect, m)?)?;
+    m.add_function(wrap_pyfunction!(detect_bytes, m)?)?;
+    m.add_function(wrap_pyfunction!(breakdown, m)?)?;
+    Ok(())
+}
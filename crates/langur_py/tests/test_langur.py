@@ -0,0 +1,90 @@
+import os
+import pathlib
+import tempfile
+import unittest
+
+import langur
+
+
+class DetectTest(unittest.TestCase):
+    def test_detect(self):
+        with tempfile.TemporaryDirectory() as root:
+            path = pathlib.Path(root, "run")
+            path.write_text("#!/usr/bin/env python\nprint('hi')\n")
+            self.assertEqual(
+                langur.detect(path), {"language": "Python", "strategy": "Shebang"}
+            )
+            self.assertEqual(
+                langur.detect(str(path)), {"language": "Python", "strategy": "Shebang"}
+            )
+            path.write_text("???")
+            self.assertIsNone(langur.detect(path))
+
+    def test_detect_missing_file(self):
+        # The extension is enough, so the file isn't read.
+        self.assertEqual(
+            langur.detect("missing/Main.purs"),
+            {"language": "PureScript", "strategy": "Extension"},
+        )
+        with self.assertRaises(FileNotFoundError):
+            langur.detect("missing/run")
+
+    def test_detect_bytes(self):
+        self.assertEqual(
+            langur.detect_bytes("a.es", b"'use strict'"),
+            {"language": "JavaScript", "strategy": "Heuristics"},
+        )
+        self.assertIsNone(langur.detect_bytes("unknown", b"???"))
+        with self.assertRaises(ValueError):
+            langur.detect_bytes("a.es", b"\xff\xfe")
+
+
+class LanguageTest(unittest.TestCase):
+    def test_metadata(self):
+        rust = langur.Language("Rust")
+        self.assertEqual(rust.name, "Rust")
+        self.assertEqual(rust.type, "programming")
+        self.assertEqual(rust.color, "#dea584")
+        self.assertIsNone(rust.group)
+        self.assertIn("rust", rust.aliases)
+        self.assertEqual(langur.Language.from_id(rust.id), rust)
+        self.assertEqual(repr(rust), 'Language("Rust")')
+
+        tsx = langur.Language("TSX")
+        self.assertEqual(tsx.group, langur.Language("TypeScript"))
+        self.assertEqual(len({rust, langur.Language("Rust"), tsx}), 2)
+
+    def test_unknown(self):
+        with self.assertRaises(KeyError):
+            langur.Language("rust")
+        with self.assertRaises(KeyError):
+            langur.Language.from_id(-1)
+
+
+class BreakdownTest(unittest.TestCase):
+    def test_breakdown(self):
+        with tempfile.TemporaryDirectory() as root:
+            os.mkdir(os.path.join(root, "src"))
+            for name, content in [
+                ("src/main.rs", "fn main() {}\n"),
+                ("src/lib.rs", "pub fn lib() {}\n"),
+                ("build.py", "print('hi')\n"),
+            ]:
+                pathlib.Path(root, name).write_text(content)
+
+            self.assertEqual(
+                langur.breakdown(root),
+                {
+                    "Rust": [
+                        {"path": os.path.join(root, "src/lib.rs"), "strategy": "Heuristics"},
+                        {"path": os.path.join(root, "src/main.rs"), "strategy": "Heuristics"},
+                    ],
+                    "Python": [
+                        {"path": os.path.join(root, "build.py"), "strategy": "Extension"},
+                    ],
+                },
+            )
+
+
+if __name__ == "__main__":
+    unittest.main()
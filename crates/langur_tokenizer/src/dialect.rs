@@ -0,0 +1,194 @@
+/// The comment and string syntax of a family of languages, which replaces the
+/// generic rules of the tokenizer when passed to [`Tokenizer::with_dialect`].
+///
+/// Without a dialect, `#` and `%` always start line comments and `--` is
+/// always a comment, so e.g. C preprocessor directives and Python decorators
+/// are dropped as comments. With one, only the delimiters of the dialect are
+/// comments and strings, and everything else is identifiers, numbers and
+/// symbols.
+///
+/// [`Tokenizer::with_dialect`]: crate::Tokenizer::with_dialect
+///
+/// # Examples
+/// ```
+/// use langur_tokenizer::{Dialect, Token, Tokenizer};
+///
+/// let content = "#include <stdio.h> // Header";
+/// let dialect = Dialect::for_language("C").unwrap();
+/// let tokens: Vec<Token> = Tokenizer::with_dialect(content, dialect).tokens().collect();
+/// assert_eq!(tokens[1], Token::Ident("include"));
+/// assert_eq!(tokens.last(), Some(&Token::LineComment("//", "Header")));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dialect {
+    /// Markers that start comments running to the end of the line, like
+    /// `//`. Repeats of a marker made of a single char, like `///` or `##`,
+    /// are part of the marker.
+    pub line_comments: &'static [&'static str],
+    /// Start and end markers of block comments, like `/*` and `*/`.
+    pub block_comments: &'static [(&'static str, &'static str)],
+    /// Whether block comments nest, like in Rust and Haskell.
+    pub nested_comments: bool,
+    /// Quotes of strings that end at the end of the line if they're not
+    /// closed before it. Backslashes escape quotes.
+    pub quotes: &'static [char],
+    /// Quotes of strings that can span lines, like template literals in
+    /// JavaScript and raw strings in Go.
+    pub multiline_quotes: &'static [char],
+    /// Whether three quotes start a string that can span lines and ends with
+    /// the same three quotes, like docstrings in Python.
+    pub triple_quotes: bool,
+    /// Whether `r"..."`, `r#"..."#` and so on are raw strings, like in Rust.
+    pub raw_strings: bool,
+}
+
+impl Dialect {
+    /// Returns the dialect of a language, by its name in Linguist.
+    pub fn for_language(language: &str) -> Option<&'static Dialect> {
+        let dialect = match language {
+            "Apex" | "C" | "C#" | "C++" | "D" | "Dart" | "Groovy" | "Java" | "Kotlin"
+            | "Objective-C" | "Objective-C++" | "Protocol Buffer" | "Scala" | "Solidity"
+            | "Swift" | "SystemVerilog" | "Thrift" | "Verilog" | "Zig" => &C,
+            "Gradle" | "JSON with Comments" | "JavaScript" | "Jsonnet" | "TSX" | "TypeScript" => {
+                &JAVASCRIPT
+            }
+            "Go" => &GO,
+            "Rust" => &RUST,
+            "CSS" => &CSS,
+            "Less" | "SCSS" => &SCSS,
+            "CMake" | "CoffeeScript" | "Crystal" | "Dockerfile" | "Elixir" | "GraphQL"
+            | "Julia" | "Makefile" | "Perl" | "PowerShell" | "R" | "Raku" | "Ruby" | "Shell"
+            | "TOML" | "Tcl" | "YAML" => &HASH,
+            "Python" | "Starlark" => &PYTHON,
+            "HCL" | "Hack" | "Nix" | "PHP" => &PHP,
+            "PLSQL" | "PLpgSQL" | "SQL" | "TSQL" => &SQL,
+            "Lua" => &LUA,
+            "Elm" | "Haskell" => &HASKELL,
+            "Ada" | "VHDL" => &ADA,
+            "Erlang" | "MATLAB" | "TeX" => &PERCENT,
+            "Coq" | "OCaml" | "Standard ML" => &ML,
+            "HTML" | "SVG" | "Svelte" | "Vue" | "XML" => &MARKUP,
+            "Markdown" => &MARKDOWN,
+            _ => return None,
+        };
+        Some(dialect)
+    }
+
+    /// Whether `content` is the start of a comment marker or of triple
+    /// quotes, but too short to be the whole marker.
+    pub(crate) fn is_marker_prefix(&self, content: &str) -> bool {
+        let is_prefix = |marker: &str| marker.len() > content.len() && marker.starts_with(content);
+        self.line_comments.iter().any(|marker| is_prefix(marker))
+            || self.block_comments.iter().any(|(open, _)| is_prefix(open))
+            || (self.triple_quotes
+                && self
+                    .quotes
+                    .iter()
+                    .any(|quote| is_prefix(&quote.to_string().repeat(3))))
+    }
+}
+
+const NONE: Dialect = Dialect {
+    line_comments: &[],
+    block_comments: &[],
+    nested_comments: false,
+    quotes: &[],
+    multiline_quotes: &[],
+    triple_quotes: false,
+    raw_strings: false,
+};
+
+const C_STYLE_COMMENTS: &[(&str, &str)] = &[("/*", "*/")];
+
+const C: Dialect = Dialect {
+    line_comments: &["//"],
+    block_comments: C_STYLE_COMMENTS,
+    quotes: &['"', '\''],
+    ..NONE
+};
+const JAVASCRIPT: Dialect = Dialect {
+    multiline_quotes: &['`'],
+    ..C
+};
+const GO: Dialect = Dialect {
+    multiline_quotes: &['`'],
+    ..C
+};
+// Single quotes are also lifetimes, so only double quotes are strings.
+const RUST: Dialect = Dialect {
+    line_comments: &["//"],
+    block_comments: C_STYLE_COMMENTS,
+    nested_comments: true,
+    quotes: &['"'],
+    raw_strings: true,
+    ..NONE
+};
+const CSS: Dialect = Dialect {
+    block_comments: C_STYLE_COMMENTS,
+    quotes: &['"', '\''],
+    ..NONE
+};
+const SCSS: Dialect = Dialect {
+    line_comments: &["//"],
+    ..CSS
+};
+const HASH: Dialect = Dialect {
+    line_comments: &["#"],
+    quotes: &['"', '\''],
+    ..NONE
+};
+const PYTHON: Dialect = Dialect {
+    triple_quotes: true,
+    ..HASH
+};
+const PHP: Dialect = Dialect {
+    line_comments: &["#", "//"],
+    block_comments: C_STYLE_COMMENTS,
+    quotes: &['"', '\''],
+    ..NONE
+};
+const SQL: Dialect = Dialect {
+    line_comments: &["--"],
+    block_comments: C_STYLE_COMMENTS,
+    quotes: &['\'', '"'],
+    ..NONE
+};
+const LUA: Dialect = Dialect {
+    line_comments: &["--"],
+    block_comments: &[("--[[", "]]")],
+    quotes: &['"', '\''],
+    ..NONE
+};
+const HASKELL: Dialect = Dialect {
+    line_comments: &["--"],
+    block_comments: &[("{-", "-}")],
+    nested_comments: true,
+    quotes: &['"'],
+    ..NONE
+};
+const ADA: Dialect = Dialect {
+    line_comments: &["--"],
+    quotes: &['"'],
+    ..NONE
+};
+const PERCENT: Dialect = Dialect {
+    line_comments: &["%"],
+    quotes: &['"'],
+    ..NONE
+};
+const ML: Dialect = Dialect {
+    block_comments: &[("(*", "*)")],
+    nested_comments: true,
+    quotes: &['"'],
+    ..NONE
+};
+const MARKUP: Dialect = Dialect {
+    block_comments: &[("<!--", "-->")],
+    quotes: &['"', '\''],
+    ..NONE
+};
+// Apostrophes in prose aren't quotes.
+const MARKDOWN: Dialect = Dialect {
+    block_comments: &[("<!--", "-->")],
+    ..NONE
+};
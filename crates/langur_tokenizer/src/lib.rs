@@ -1,5 +1,9 @@
+mod dialect;
+mod stream;
 pub mod tokenizer;
-pub use tokenizer::{Token, Tokenizer};
+pub use dialect::Dialect;
+pub use stream::StreamTokenizer;
+pub use tokenizer::{LineIndex, Position, Token, Tokenizer, TokensWithSpans};
 
 /// Tokenize the content and return only the identifiers and symbols from the langauge
 ///
@@ -12,8 +16,25 @@ pub use tokenizer::{Token, Tokenizer};
 /// ```
 #[doc(hidden)]
 pub fn get_key_tokens(content: &str) -> impl Iterator<Item = &str> {
-    Tokenizer::new(content).tokens().filter_map(|t| match t {
+    Tokenizer::new(content).tokens().filter_map(key_token)
+}
+
+/// Same as [`get_key_tokens`], but with the comments and strings of `dialect`.
+#[doc(hidden)]
+pub fn get_key_tokens_with_dialect<'a>(
+    content: &'a str,
+    dialect: &'a Dialect,
+) -> impl Iterator<Item = &'a str> {
+    Tokenizer::with_dialect(content, dialect)
+        .tokens()
+        .filter_map(key_token)
+}
+
+/// The identifier or symbol of a token, see [`get_key_tokens`].
+#[doc(hidden)]
+pub fn key_token(token: Token<'_>) -> Option<&str> {
+    match token {
         Token::Ident(t) | Token::Symbol(t) => Some(t),
         _ => None,
-    })
+    }
 }
@@ -0,0 +1,311 @@
+use std::{
+    io::{self, BufRead},
+    ops::Range,
+    str,
+};
+
+use crate::{Dialect, Token, Tokenizer};
+
+/// Tokenizes a reader incrementally, so that callers can stop reading once
+/// they've seen enough tokens, instead of reading a whole file up front.
+///
+/// Tokens that could continue past the content read so far, like an
+/// identifier or a block comment at the end of a chunk, are held back until
+/// more content is read, so the tokens are the same as with [`Tokenizer`]
+/// on the whole content. Invalid UTF-8 is replaced with U+FFFD, like
+/// [`String::from_utf8_lossy`].
+///
+/// # Examples
+/// ```
+/// use std::io::BufReader;
+/// use langur_tokenizer::{StreamTokenizer, Token};
+///
+/// let content = "/* a */ let x = 5;";
+/// // Read 4 bytes at a time.
+/// let mut stream = StreamTokenizer::new(BufReader::with_capacity(4, content.as_bytes()));
+/// let mut idents = Vec::new();
+/// while let Some(tokens) = stream.next_tokens().unwrap() {
+///     for (token, _) in tokens {
+///         if let Token::Ident(ident) = token {
+///             idents.push(ident.to_owned());
+///         }
+///     }
+/// }
+/// assert_eq!(idents, vec!["let", "x"]);
+/// ```
+pub struct StreamTokenizer<R> {
+    reader: R,
+    dialect: Option<&'static Dialect>,
+    /// Content that hasn't been tokenized for good yet.
+    buffer: String,
+    /// The offset of `buffer` in the whole content.
+    offset: usize,
+    /// The number of bytes at the start of `buffer` whose tokens were
+    /// returned by the last call to `next_tokens`.
+    returned: usize,
+    /// The length of `buffer` when its first token was last found to be
+    /// still open, or 0 if it wasn't.
+    pending_len: usize,
+    /// The bytes of a char split across reads.
+    partial_char: Vec<u8>,
+    invalid_utf8: bool,
+    bytes_read: usize,
+    eof: bool,
+}
+
+impl<R: BufRead> StreamTokenizer<R> {
+    pub fn new(reader: R) -> Self {
+        StreamTokenizer {
+            reader,
+            dialect: None,
+            buffer: String::new(),
+            offset: 0,
+            returned: 0,
+            pending_len: 0,
+            partial_char: Vec::new(),
+            invalid_utf8: false,
+            bytes_read: 0,
+            eof: false,
+        }
+    }
+
+    /// Creates a stream tokenizer that only recognizes the comments and
+    /// strings of `dialect`, see [`Tokenizer::with_dialect`].
+    pub fn with_dialect(reader: R, dialect: &'static Dialect) -> Self {
+        StreamTokenizer {
+            dialect: Some(dialect),
+            ..StreamTokenizer::new(reader)
+        }
+    }
+
+    /// The number of bytes read from the reader so far.
+    pub fn bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+
+    /// Whether any invalid UTF-8 was replaced so far. A char cut off at the
+    /// end of the content doesn't count, since a limit on the reader can cut
+    /// it.
+    pub fn invalid_utf8(&self) -> bool {
+        self.invalid_utf8
+    }
+
+    /// Reads at least one more chunk from the reader, and returns the tokens
+    /// that can't change anymore, with their byte ranges in the whole
+    /// content. The tokens are empty if the only token read so far could
+    /// still continue, like a long block comment. Returns `None` once all
+    /// the content has been tokenized.
+    ///
+    /// The tokens borrow from the tokenizer, so they need to be processed or
+    /// copied before the next call.
+    #[allow(clippy::type_complexity)]
+    pub fn next_tokens(&mut self) -> io::Result<Option<Vec<(Token<'_>, Range<usize>)>>> {
+        self.buffer.drain(..self.returned);
+        self.offset += self.returned;
+        self.returned = 0;
+        if self.eof && self.buffer.is_empty() {
+            return Ok(None);
+        }
+
+        // A token that's still open is only tokenized again once the
+        // buffer has doubled, which keeps a long one linear to tokenize.
+        while !self.eof {
+            self.read_chunk()?;
+            if self.buffer.len() >= 2 * self.pending_len {
+                break;
+            }
+        }
+
+        let offset = self.offset;
+        let mut spans = match self.dialect {
+            Some(dialect) => Tokenizer::with_dialect(&self.buffer, dialect),
+            None => Tokenizer::new(&self.buffer),
+        }
+        .tokens_with_spans();
+        let mut tokens: Vec<_> = spans
+            .by_ref()
+            .map(|(token, span)| (token, offset + span.start..offset + span.end))
+            .collect();
+        let end = match spans.end_reached_at() {
+            Some(start) if !self.eof && !tokens.is_empty() => start,
+            // Only whitespace, which can't be part of a later token.
+            _ => self.buffer.len(),
+        };
+        tokens.retain(|(_, span)| span.start < offset + end);
+
+        self.returned = end;
+        self.pending_len = if end == 0 { self.buffer.len() } else { 0 };
+        Ok(Some(tokens))
+    }
+
+    fn read_chunk(&mut self) -> io::Result<()> {
+        let chunk = loop {
+            match self.reader.fill_buf() {
+                Ok(chunk) => break chunk,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        };
+        let len = chunk.len();
+        self.partial_char.extend_from_slice(chunk);
+        self.reader.consume(len);
+        self.bytes_read += len;
+        self.eof = len == 0;
+
+        let partial_char = std::mem::take(&mut self.partial_char);
+        let mut bytes = &partial_char[..];
+        loop {
+            match str::from_utf8(bytes) {
+                Ok(valid) => {
+                    self.buffer.push_str(valid);
+                    bytes = &[];
+                    break;
+                }
+                Err(e) => {
+                    let (valid, rest) = bytes.split_at(e.valid_up_to());
+                    // Valid by construction.
+                    self.buffer
+                        .push_str(str::from_utf8(valid).unwrap_or_default());
+                    match e.error_len() {
+                        Some(len) => {
+                            self.invalid_utf8 = true;
+                            self.buffer.push(char::REPLACEMENT_CHARACTER);
+                            bytes = &rest[len..];
+                        }
+                        // The rest of the char is in the next chunk.
+                        None if !self.eof => {
+                            bytes = rest;
+                            break;
+                        }
+                        None => {
+                            self.buffer.push(char::REPLACEMENT_CHARACTER);
+                            bytes = &[];
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        self.partial_char = bytes.to_vec();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufReader;
+
+    /// The tokens and spans, formatted since the stream's tokens don't
+    /// outlive each call.
+    fn stream_tokens(
+        content: &[u8],
+        capacity: usize,
+        dialect: Option<&'static Dialect>,
+    ) -> Vec<String> {
+        let reader = BufReader::with_capacity(capacity, content);
+        let mut stream = match dialect {
+            Some(dialect) => StreamTokenizer::with_dialect(reader, dialect),
+            None => StreamTokenizer::new(reader),
+        };
+        let mut tokens = Vec::new();
+        while let Some(chunk) = stream.next_tokens().unwrap() {
+            tokens.extend(chunk.iter().map(|token| format!("{:?}", token)));
+        }
+        assert_eq!(stream.bytes_read(), content.len());
+        tokens
+    }
+
+    fn tokens(content: &str, dialect: Option<&'static Dialect>) -> Vec<String> {
+        let tokenizer = match dialect {
+            Some(dialect) => Tokenizer::with_dialect(content, dialect),
+            None => Tokenizer::new(content),
+        };
+        tokenizer
+            .tokens_with_spans()
+            .map(|token| format!("{:?}", token))
+            .collect()
+    }
+
+    const SAMPLES: &[&str] = &[
+        "fn main() {\n    let x_1 = 0x1F + -2.5; // done\n}\n",
+        "/* block\n comment */ x /* unterminated",
+        "s = \"a \\\" b\" + 'c' + \"unterminated\nnext\"",
+        "\"\"\"doc\nstring\"\"\" ``` ` <!-- html --> <!- (* ml *) {- hs -}",
+        "# hash\n%% percent\n-- dash\n--[[ lua ]]",
+        "京y = \"héllo\" // ünïcode",
+        "r#\"raw \"# string\"# '#' @decorator",
+        "   \n\t  ",
+        "",
+    ];
+
+    #[test]
+    fn matches_whole_content() {
+        let dialects = [
+            None,
+            Dialect::for_language("C"),
+            Dialect::for_language("Python"),
+            Dialect::for_language("Rust"),
+            Dialect::for_language("Lua"),
+        ];
+        for sample in SAMPLES {
+            for dialect in dialects {
+                let expected = tokens(sample, dialect);
+                for capacity in [1, 2, 3, 5, 8, 64] {
+                    assert_eq!(
+                        stream_tokens(sample.as_bytes(), capacity, dialect),
+                        expected,
+                        "{:?} read {} bytes at a time with {:?}",
+                        sample,
+                        capacity,
+                        dialect
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reads_incrementally() {
+        let content = "let x = 5;\n".repeat(100);
+        let mut stream = StreamTokenizer::new(BufReader::with_capacity(16, content.as_bytes()));
+        let tokens = stream.next_tokens().unwrap().unwrap();
+        assert_eq!(tokens[0], (Token::Ident("let"), 0..3));
+        assert!(stream.bytes_read() < content.len());
+    }
+
+    #[test]
+    fn long_open_token() {
+        // The comment is only tokenized again each time the content read
+        // doubles, rather than after every chunk.
+        let content = format!("/*{}*/ x", " comment".repeat(8192));
+        let mut stream = StreamTokenizer::new(BufReader::with_capacity(16, content.as_bytes()));
+        let mut calls = 0;
+        let mut tokens = Vec::new();
+        while let Some(chunk) = stream.next_tokens().unwrap() {
+            calls += 1;
+            tokens.extend(chunk.into_iter().map(|(_, span)| span));
+        }
+        assert_eq!(
+            tokens,
+            vec![0..content.len() - 2, content.len() - 1..content.len()]
+        );
+        assert!(calls < 20, "{} calls", calls);
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let content = b"ab\xffcd \xe4\xba";
+        let expected = tokens(&String::from_utf8_lossy(content), None);
+        for capacity in [1, 2, 64] {
+            assert_eq!(stream_tokens(content, capacity, None), expected);
+        }
+
+        let mut stream = StreamTokenizer::new(BufReader::new(&b"ab \xe4\xba"[..]));
+        while stream.next_tokens().unwrap().is_some() {}
+        assert!(!stream.invalid_utf8());
+        let mut stream = StreamTokenizer::new(BufReader::new(&content[..]));
+        while stream.next_tokens().unwrap().is_some() {}
+        assert!(stream.invalid_utf8());
+    }
+}
@@ -1,7 +1,10 @@
 use circular_queue::CircularQueue;
+
+use crate::Dialect;
 use std::{
     collections::VecDeque,
     iter::{DoubleEndedIterator, Peekable},
+    ops::Range,
     str::CharIndices,
 };
 
@@ -58,12 +61,25 @@ pub enum Token<'a> {
 #[doc(hidden)]
 pub struct Tokenizer<'a> {
     content: &'a str,
+    dialect: Option<&'a Dialect>,
 }
 
 impl<'a> Tokenizer<'a> {
     #[doc(hidden)]
     pub fn new(content: &'a str) -> Self {
-        Tokenizer { content }
+        Tokenizer {
+            content,
+            dialect: None,
+        }
+    }
+
+    /// Creates a tokenizer that only recognizes the comments and strings
+    /// of `dialect`, see [`Dialect`].
+    pub fn with_dialect(content: &'a str, dialect: &'a Dialect) -> Self {
+        Tokenizer {
+            content,
+            dialect: Some(dialect),
+        }
     }
 
     #[doc(hidden)]
@@ -73,6 +89,99 @@ impl<'a> Tokenizer<'a> {
             chars: self.content.char_indices().peekable(),
             content: self.content,
             current_token_idx: 0,
+            dialect: self.dialect,
+            end_reached_at: None,
+        }
+    }
+
+    /// Same as [`tokens`](Tokenizer::tokens), but with the byte range of
+    /// each token in the content, including the delimiters of comments and
+    /// strings. Use a [`LineIndex`] to turn offsets into lines and columns.
+    ///
+    /// # Examples
+    /// ```
+    /// use langur_tokenizer::{Token, Tokenizer};
+    ///
+    /// let content = "x = 5 // five";
+    /// let spans: Vec<_> = Tokenizer::new(content).tokens_with_spans().collect();
+    /// assert_eq!(spans[2], (Token::Number("5"), 4..5));
+    /// assert_eq!(spans[3], (Token::LineComment("//", "five"), 6..13));
+    /// ```
+    pub fn tokens_with_spans(&self) -> TokensWithSpans<'a> {
+        TokensWithSpans {
+            tokens: self.tokens(),
+        }
+    }
+}
+
+/// An iterator over tokens and their byte ranges, see
+/// [`Tokenizer::tokens_with_spans`].
+pub struct TokensWithSpans<'a> {
+    tokens: Tokens<'a>,
+}
+
+impl<'a> Iterator for TokensWithSpans<'a> {
+    type Item = (Token<'a>, Range<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = self.tokens.next()?;
+        // Tokens end where the next char to tokenize starts, which is
+        // also the case when chars were pushed back to the backlog.
+        let end = match self.tokens.peek() {
+            Some((idx, _)) => idx,
+            None => self.tokens.content.len(),
+        };
+        Some((token, self.tokens.token_start()..end))
+    }
+}
+
+impl<'a> TokensWithSpans<'a> {
+    /// The start of the first token so far that looked at the end of the
+    /// content, and could be a different token if there were more content.
+    pub(crate) fn end_reached_at(&self) -> Option<usize> {
+        self.tokens.end_reached_at
+    }
+}
+
+/// A zero-based line and column. The column is a byte offset in the line.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Maps byte offsets in some content to lines and columns.
+///
+/// # Examples
+/// ```
+/// use langur_tokenizer::{LineIndex, Position};
+///
+/// let index = LineIndex::new("fn main() {\n    x\n}");
+/// assert_eq!(index.position(16), Position { line: 1, column: 4 });
+/// ```
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(content: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(content.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        LineIndex { line_starts }
+    }
+
+    /// Returns the line and column of a byte offset. Offsets past the end
+    /// of the content are on the last line.
+    pub fn position(&self, offset: usize) -> Position {
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        };
+        Position {
+            line,
+            column: offset - self.line_starts[line],
         }
     }
 }
@@ -83,6 +192,8 @@ pub struct Tokens<'a> {
     chars: Peekable<CharIndices<'a>>,
     content: &'a str,
     current_token_idx: usize,
+    dialect: Option<&'a Dialect>,
+    end_reached_at: Option<usize>,
 }
 
 impl<'a> Tokens<'a> {
@@ -97,7 +208,15 @@ impl<'a> Tokens<'a> {
     }
 
     fn peek(&mut self) -> Option<(usize, char)> {
-        self.peek_backlog().or(self.chars.peek().copied())
+        let next = self.peek_backlog().or(self.chars.peek().copied());
+        if next.is_none() {
+            self.mark_end_reached();
+        }
+        next
+    }
+
+    fn mark_end_reached(&mut self) {
+        self.end_reached_at.get_or_insert(self.current_token_idx);
     }
 
     fn next_backlog(&mut self) -> Option<(usize, char)> {
@@ -215,10 +334,11 @@ impl<'a> Tokens<'a> {
         content_idx: usize,
         end_sequence: &[char],
     ) -> Result<(&'a str, &'a str), Token<'a>> {
-        // start with a random char '@' that won't match the closure check
+        // Take chars until the last ones taken are the end sequence. The
+        // queue iterates from the most recent char.
         let mut prev_chars = CircularQueue::with_capacity(end_sequence.len());
         let mut take_if = |ch| {
-            let should_take = prev_chars.iter().eq(end_sequence.iter());
+            let should_take = !prev_chars.iter().eq(end_sequence.iter().rev());
             if should_take {
                 prev_chars.push(ch);
             }
@@ -226,7 +346,7 @@ impl<'a> Tokens<'a> {
         };
 
         let end = self.take_if(&mut take_if);
-        if prev_chars.iter().eq(end_sequence.iter()) {
+        if prev_chars.iter().eq(end_sequence.iter().rev()) {
             let end_sequence_start = end - end_sequence.len();
             let content = self.slice(content_idx, end_sequence_start);
             let end_sequence = self.slice(end_sequence_start, end);
@@ -246,30 +366,12 @@ impl<'a> Tokens<'a> {
 impl<'a> Iterator for Tokens<'a> {
     type Item = Token<'a>;
     fn next(&mut self) -> Option<Self::Item> {
+        if let Some(dialect) = self.dialect {
+            return self.next_in_dialect(dialect);
+        }
         self.eat_whitespace();
         match self.start_new_token() {
-            Some(ch) if ch.is_alphabetic() || ch == '_' => Some(Token::Ident(
-                self.take_if_slice(&mut |ch| ch.is_alphanumeric() || ch == '_'),
-            )),
-            Some('0') => match self.peek() {
-                Some((_, 'b')) => {
-                    self.advance();
-                    Some(Token::Number(self.take_if_slice(&mut |ch| {
-                        ch == '1' || ch == '0' || ch == '_'
-                    })))
-                }
-                Some((_, 'o')) => {
-                    self.advance();
-                    Some(Token::Number(self.take_if_slice(&mut |ch| matches!(ch, '0'..='7' | '_'))))
-                }
-                Some((_, 'x')) => {
-                    self.advance();
-                    Some(Token::Number(self.take_if_slice(&mut |ch| {
-                        ch.is_ascii_hexdigit() || ch == '_'
-                    })))
-                }
-                _ => Some(Token::Number(self.take_if_slice(&mut numeric_closure()))),
-            },
+            Some(ch) if ch.is_alphanumeric() || ch == '_' => Some(self.ident_or_number(ch)),
             Some(ch) if ch == '-' || ch == '+' => match self.peek() {
                 Some((_, ch)) if ch.is_numeric() => {
                     Some(Token::Number(self.take_if_slice(&mut numeric_closure())))
@@ -285,9 +387,6 @@ impl<'a> Iterator for Tokens<'a> {
                     &self.content[self.token_start()..self.token_start() + 1],
                 )),
             },
-            Some(ch) if ch.is_numeric() => {
-                Some(Token::Number(self.take_if_slice(&mut numeric_closure())))
-            }
             Some('/') => match self.peek() {
                 Some((_, '/')) => {
                     let symbol = self.take_if_slice(&mut |ch| ch == '/');
@@ -397,6 +496,217 @@ impl<'a> Iterator for Tokens<'a> {
     }
 }
 
+impl<'a> Tokens<'a> {
+    /// Pre-condition: `ch` is alphanumeric or `_` and was just taken.
+    fn ident_or_number(&mut self, ch: char) -> Token<'a> {
+        match ch {
+            ch if ch.is_alphabetic() || ch == '_' => {
+                Token::Ident(self.take_if_slice(&mut |ch| ch.is_alphanumeric() || ch == '_'))
+            }
+            '0' => match self.peek() {
+                Some((_, 'b')) => {
+                    self.advance();
+                    Token::Number(self.take_if_slice(&mut |ch| ch == '1' || ch == '0' || ch == '_'))
+                }
+                Some((_, 'o')) => {
+                    self.advance();
+                    Token::Number(self.take_if_slice(&mut |ch| matches!(ch, '0'..='7' | '_')))
+                }
+                Some((_, 'x')) => {
+                    self.advance();
+                    Token::Number(self.take_if_slice(&mut |ch| ch.is_ascii_hexdigit() || ch == '_'))
+                }
+                _ => Token::Number(self.take_if_slice(&mut numeric_closure())),
+            },
+            _ => Token::Number(self.take_if_slice(&mut numeric_closure())),
+        }
+    }
+
+    /// Advances until the next char to tokenize is at `end`.
+    fn skip_to(&mut self, end: usize) {
+        while let Some((idx, _)) = self.peek() {
+            if idx >= end {
+                break;
+            }
+            self.advance();
+        }
+    }
+
+    fn next_in_dialect(&mut self, dialect: &Dialect) -> Option<Token<'a>> {
+        self.eat_whitespace();
+        let ch = self.start_new_token()?;
+        let rest = &self.content[self.token_start()..];
+        if dialect.is_marker_prefix(rest) {
+            self.mark_end_reached();
+        }
+
+        // Block comments go first, since e.g. Lua's `--[[` starts with `--`.
+        if let Some((open, close)) = dialect
+            .block_comments
+            .iter()
+            .find(|(open, _)| rest.starts_with(open))
+        {
+            return Some(self.dialect_block_comment(open, close, dialect.nested_comments));
+        }
+        if let Some(marker) = dialect
+            .line_comments
+            .iter()
+            .filter(|marker| rest.starts_with(**marker))
+            .max_by_key(|marker| marker.len())
+        {
+            return Some(self.dialect_line_comment(marker));
+        }
+        if dialect.raw_strings && ch == 'r' {
+            if let Some(token) = self.raw_string() {
+                return Some(token);
+            }
+        }
+        let multiline = dialect.multiline_quotes.contains(&ch);
+        if multiline || dialect.quotes.contains(&ch) {
+            return Some(self.dialect_string(ch, multiline, dialect.triple_quotes));
+        }
+        match ch {
+            ch if ch.is_alphanumeric() || ch == '_' => Some(self.ident_or_number(ch)),
+            '-' | '+' if matches!(self.peek(), Some((_, next)) if next.is_numeric()) => {
+                Some(Token::Number(self.take_if_slice(&mut numeric_closure())))
+            }
+            ch => Some(Token::Symbol(
+                self.slice_from_token_start(self.token_start() + ch.len_utf8()),
+            )),
+        }
+    }
+
+    fn dialect_line_comment(&mut self, marker: &str) -> Token<'a> {
+        let start = self.token_start();
+        let mut symbol_end = start + marker.len();
+        let first = marker.chars().next().unwrap_or_default();
+        if marker.chars().all(|ch| ch == first) {
+            symbol_end += self.content[symbol_end..]
+                .chars()
+                .take_while(|&ch| ch == first)
+                .map(char::len_utf8)
+                .sum::<usize>();
+        }
+        self.skip_to(symbol_end);
+        let comment_start = self.eat_non_newline_whitespace();
+        let comment_end = self.take_if(&mut |ch| ch != '\r' && ch != '\n');
+        Token::LineComment(
+            self.slice(start, symbol_end),
+            self.slice(comment_start, comment_end),
+        )
+    }
+
+    /// Comments that aren't closed run to the end of the content.
+    fn dialect_block_comment(&mut self, open: &str, close: &str, nested: bool) -> Token<'a> {
+        let start = self.token_start();
+        let body_start = start + open.len();
+        let mut depth = 1;
+        let mut position = body_start;
+        let body_end = loop {
+            let rest = &self.content[position..];
+            match rest.chars().next() {
+                None => break self.content.len(),
+                Some(_) if rest.starts_with(close) => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break position;
+                    }
+                    position += close.len();
+                }
+                Some(_) if nested && rest.starts_with(open) => {
+                    depth += 1;
+                    position += open.len();
+                }
+                Some(ch) => position += ch.len_utf8(),
+            }
+        };
+        let end = (body_end + close.len()).min(self.content.len());
+        self.skip_to(end);
+        Token::BlockComment(
+            self.slice(start, body_start),
+            self.slice(body_start, body_end),
+            self.slice(body_end, end),
+        )
+    }
+
+    /// `r"..."`, `r#"..."#` and so on, or `None` if the `r` doesn't start a
+    /// raw string.
+    fn raw_string(&mut self) -> Option<Token<'a>> {
+        let start = self.token_start();
+        let after = &self.content[start + 1..];
+        let hashes = after.len() - after.trim_start_matches('#').len();
+        if !after[hashes..].starts_with('"') {
+            if after.len() == hashes {
+                self.mark_end_reached();
+            }
+            return None;
+        }
+        let body_start = start + 1 + hashes + 1;
+        let closing = format!("\"{}", "#".repeat(hashes));
+        let (body_end, end) = match self.content[body_start..].find(&closing) {
+            Some(offset) => (body_start + offset, body_start + offset + closing.len()),
+            None => (self.content.len(), self.content.len()),
+        };
+        self.skip_to(end);
+        Some(Token::String(
+            self.slice(start, body_start),
+            self.slice(body_start, body_end),
+            self.slice(body_end, end),
+        ))
+    }
+
+    /// Strings that aren't closed are a symbol for the quote.
+    fn dialect_string(&mut self, quote: char, multiline: bool, triple_quotes: bool) -> Token<'a> {
+        let start = self.token_start();
+        let triple = quote.to_string().repeat(3);
+        if triple_quotes && self.content[start..].starts_with(&triple) {
+            let body_start = start + triple.len();
+            let (body_end, end) = match self.content[body_start..].find(&triple) {
+                Some(offset) => (body_start + offset, body_start + offset + triple.len()),
+                None => (self.content.len(), self.content.len()),
+            };
+            self.skip_to(end);
+            return Token::String(
+                self.slice(start, body_start),
+                self.slice(body_start, body_end),
+                self.slice(body_end, end),
+            );
+        }
+
+        let body_start = start + quote.len_utf8();
+        let mut is_escaped = false;
+        let mut body_end = None;
+        let mut reached_end = true;
+        for (offset, ch) in self.content[body_start..].char_indices() {
+            if ch == quote && !is_escaped {
+                body_end = Some(body_start + offset);
+                reached_end = false;
+                break;
+            }
+            if ch == '\n' && !multiline {
+                reached_end = false;
+                break;
+            }
+            is_escaped = ch == '\\' && !is_escaped;
+        }
+        if reached_end {
+            self.mark_end_reached();
+        }
+        match body_end {
+            Some(body_end) => {
+                let end = body_end + quote.len_utf8();
+                self.skip_to(end);
+                Token::String(
+                    self.slice(start, body_start),
+                    self.slice(body_start, body_end),
+                    self.slice(body_end, end),
+                )
+            }
+            None => Token::Symbol(self.slice(start, body_start)),
+        }
+    }
+}
+
 fn numeric_closure() -> Box<dyn FnMut(char) -> bool> {
     let mut seen_decimal = false;
     Box::new(move |ch| match ch {
@@ -701,6 +1011,30 @@ mod tests {
         assert_eq!(tokens, expected);
     }
 
+    #[test]
+    fn block_end_sequence_order() {
+        // Parts of the end sequence, or its chars in the wrong order, don't
+        // end the block, and tokenizing resumes right after the end.
+        let sample = r#"
+        /* a * b / c */ x
+        """ say "hi" "" """ y
+        {- a - } }- -} z
+        "#;
+
+        let tokenizer = Tokenizer::new(sample);
+        let tokens: Vec<Token> = tokenizer.tokens().collect();
+        let expected = vec![
+            BlockComment("/*", " a * b / c ", "*/"),
+            Ident("x"),
+            String("\"\"\"", " say \"hi\" \"\" ", "\"\"\""),
+            Ident("y"),
+            BlockComment("{-", " a - } }- ", "-}"),
+            Ident("z"),
+        ];
+
+        assert_eq!(tokens, expected);
+    }
+
     #[test]
     fn unterminated_html_comment() {
         let sample = r#"
@@ -807,4 +1141,165 @@ mod tests {
         ];
         assert_eq!(tokens, expected);
     }
+
+    #[test]
+    fn spans() {
+        let sample = "let s = \"a\";\n/* block\n comment */ x--\n'''doc'''";
+        let spans: Vec<_> = Tokenizer::new(sample)
+            .tokens_with_spans()
+            .map(|(_, span)| &sample[span])
+            .collect();
+        let expected = vec![
+            "let",
+            "s",
+            "=",
+            "\"a\"",
+            ";",
+            "/* block\n comment */",
+            "x",
+            "--",
+            "'''doc'''",
+        ];
+        assert_eq!(spans, expected);
+    }
+
+    #[test]
+    fn spans_with_backlog() {
+        // Unterminated strings and comments are split into symbols, whose
+        // spans are still contiguous with the chars after them.
+        let sample = "\"ab /*c";
+        let spans: Vec<_> = Tokenizer::new(sample)
+            .tokens_with_spans()
+            .map(|(token, span)| (token, &sample[span]))
+            .collect();
+        let expected = vec![
+            (Symbol("\""), "\""),
+            (Ident("ab"), "ab"),
+            (Symbol("/"), "/"),
+            (Symbol("*"), "*"),
+            (Ident("c"), "c"),
+        ];
+        assert_eq!(spans, expected);
+    }
+
+    #[test]
+    fn line_index() {
+        let index = LineIndex::new("a\n\nbc\n");
+        assert_eq!(index.position(0), Position { line: 0, column: 0 });
+        assert_eq!(index.position(1), Position { line: 0, column: 1 });
+        assert_eq!(index.position(2), Position { line: 1, column: 0 });
+        assert_eq!(index.position(4), Position { line: 2, column: 1 });
+        assert_eq!(index.position(6), Position { line: 3, column: 0 });
+    }
+
+    fn dialect_tokens<'a>(sample: &'a str, language: &str) -> Vec<Token<'a>> {
+        let dialect = Dialect::for_language(language).unwrap();
+        Tokenizer::with_dialect(sample, dialect).tokens().collect()
+    }
+
+    #[test]
+    fn dialect_c() {
+        let sample = "#include <stdio.h>\n/* a /* b */ x = '#'; // done";
+        let expected = vec![
+            Symbol("#"),
+            Ident("include"),
+            Symbol("<"),
+            Ident("stdio"),
+            Symbol("."),
+            Ident("h"),
+            Symbol(">"),
+            BlockComment("/*", " a /* b ", "*/"),
+            Ident("x"),
+            Symbol("="),
+            String("'", "#", "'"),
+            Symbol(";"),
+            LineComment("//", "done"),
+        ];
+        assert_eq!(dialect_tokens(sample, "C"), expected);
+    }
+
+    #[test]
+    fn dialect_python() {
+        let sample =
+            "@property\ndef f():\n    \"\"\"Doc \"string\"\n    # here\"\"\"\n    return -1 ## end";
+        let expected = vec![
+            Symbol("@"),
+            Ident("property"),
+            Ident("def"),
+            Ident("f"),
+            Symbol("("),
+            Symbol(")"),
+            Symbol(":"),
+            String("\"\"\"", "Doc \"string\"\n    # here", "\"\"\""),
+            Ident("return"),
+            Number("-1"),
+            LineComment("##", "end"),
+        ];
+        assert_eq!(dialect_tokens(sample, "Python"), expected);
+    }
+
+    #[test]
+    fn dialect_multiline_quotes() {
+        let sample = "let s = `a\nb`; let t = \"c\nd\";";
+        let expected = vec![
+            Ident("let"),
+            Ident("s"),
+            Symbol("="),
+            String("`", "a\nb", "`"),
+            Symbol(";"),
+            Ident("let"),
+            Ident("t"),
+            Symbol("="),
+            Symbol("\""),
+            Ident("c"),
+            Ident("d"),
+            Symbol("\""),
+            Symbol(";"),
+        ];
+        assert_eq!(dialect_tokens(sample, "JavaScript"), expected);
+    }
+
+    #[test]
+    fn dialect_rust() {
+        let sample = "/* a /* b */ c */ r#\"x\"y\"# 'a r";
+        let expected = vec![
+            BlockComment("/*", " a /* b */ c ", "*/"),
+            String("r#\"", "x\"y", "\"#"),
+            Symbol("'"),
+            Ident("a"),
+            Ident("r"),
+        ];
+        assert_eq!(dialect_tokens(sample, "Rust"), expected);
+        // Unclosed comments run to the end.
+        assert_eq!(
+            dialect_tokens("/* a /* b */", "Rust"),
+            vec![BlockComment("/*", " a /* b */", "")]
+        );
+    }
+
+    #[test]
+    fn dialect_lua() {
+        let sample = "--[[ block\n]] x = 1 -- line\n# y";
+        let expected = vec![
+            BlockComment("--[[", " block\n", "]]"),
+            Ident("x"),
+            Symbol("="),
+            Number("1"),
+            LineComment("--", "line"),
+            Symbol("#"),
+            Ident("y"),
+        ];
+        assert_eq!(dialect_tokens(sample, "Lua"), expected);
+    }
+
+    #[test]
+    fn dialect_spans() {
+        let sample = "# a\r\nb";
+        let dialect = Dialect::for_language("Shell").unwrap();
+        let spans: Vec<_> = Tokenizer::with_dialect(sample, dialect)
+            .tokens_with_spans()
+            .map(|(_, span)| &sample[span])
+            .collect();
+        assert_eq!(spans, vec!["# a", "b"]);
+    }
 }
@@ -0,0 +1,123 @@
+//! WebAssembly bindings for Langur, for detecting the language of content
+//! that isn't on disk, like snippets pasted into a browser.
+//!
+//! ```js
+//! import { detect, Language } from "langur_wasm";
+//!
+//! const detection = detect("main.rs", "fn main() {}");
+//! detection.language.name; // "Rust"
+//! detection.strategy; // "Heuristics"
+//! new Language("TSX").group.name; // "TypeScript"
+//! ```
+//!
+//! Heuristics use fancy-regex instead of PCRE2, which doesn't support a
+//! handful of Linguist's patterns; those never match.
+
+use std::{convert::TryFrom, path::Path};
+use wasm_bindgen::prelude::*;
+
+use langur::LanguageType;
+
+/// The language of a snippet and the strategy that detected it.
+#[wasm_bindgen]
+pub struct Detection {
+    language: langur::Language,
+    strategy: String,
+}
+
+#[wasm_bindgen]
+impl Detection {
+    #[wasm_bindgen(getter)]
+    pub fn language(&self) -> Language {
+        Language {
+            inner: self.language,
+        }
+    }
+
+    /// One of "Filename", "Extension", "Shebang", "Heuristics" or "Classifier".
+    #[wasm_bindgen(getter)]
+    pub fn strategy(&self) -> String {
+        self.strategy.clone()
+    }
+}
+
+/// A language from Linguist's languages.yml file.
+#[wasm_bindgen]
+pub struct Language {
+    inner: langur::Language,
+}
+
+#[wasm_bindgen]
+impl Language {
+    /// Looks up a language by its name, which is case-sensitive.
+    #[wasm_bindgen(constructor)]
+    pub fn new(name: &str) -> Result<Language, JsError> {
+        langur::Language::try_from(name)
+            .map(|inner| Language { inner })
+            .map_err(|()| JsError::new(&format!("unknown language: {}", name)))
+    }
+
+    /// Looks up a language by its Linguist `language_id`.
+    #[wasm_bindgen(js_name = fromId)]
+    pub fn from_id(id: f64) -> Result<Language, JsError> {
+        langur::Language::try_from(id as i64)
+            .ok()
+            .filter(|language| language.id() as f64 == id)
+            .map(|inner| Language { inner })
+            .ok_or_else(|| JsError::new(&format!("unknown language id: {}", id)))
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.inner.name().to_owned()
+    }
+
+    /// Linguist `language_id`s fit in a JavaScript number.
+    #[wasm_bindgen(getter)]
+    pub fn id(&self) -> f64 {
+        self.inner.id() as f64
+    }
+
+    /// One of "data", "markup", "programming" or "prose".
+    #[wasm_bindgen(getter = type)]
+    pub fn language_type(&self) -> String {
+        match self.inner.language_type() {
+            LanguageType::Data => "data",
+            LanguageType::Markup => "markup",
+            LanguageType::Programming => "programming",
+            LanguageType::Prose => "prose",
+        }
+        .to_owned()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn color(&self) -> Option<String> {
+        self.inner.color().map(str::to_owned)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn group(&self) -> Option<Language> {
+        self.inner.group().map(|inner| Language { inner })
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn aliases(&self) -> Box<[JsValue]> {
+        self.inner
+            .aliases()
+            .iter()
+            .map(|alias| JsValue::from_str(alias))
+            .collect()
+    }
+}
+
+/// Detects the language of `content`, using `filename` for its name and
+/// extension. Returns undefined if the language can't be determined.
+#[wasm_bindgen]
+pub fn detect(filename: &str, content: &str) -> Option<Detection> {
+    // Detection only fails for content that isn't valid UTF-8.
+    let detection = langur::detect_bytes(Path::new(filename), content.as_bytes()).ok()??;
+    Some(Detection {
+        language: detection.language(),
+        strategy: detection.variant().to_owned(),
+    })
+}
@@ -0,0 +1,37 @@
+//! Run with wasm-bindgen-test-runner on node, see Development.md.
+#![cfg(target_arch = "wasm32")]
+
+use langur_wasm::{detect, Language};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test]
+fn test_detect() {
+    let detection = detect("main.rs", "fn main() {}").unwrap();
+    assert_eq!(detection.language().name(), "Rust");
+    assert_eq!(detection.strategy(), "Heuristics");
+
+    let detection = detect("script", "#!/usr/bin/env python\nprint('hi')").unwrap();
+    assert_eq!(detection.language().name(), "Python");
+    assert_eq!(detection.strategy(), "Shebang");
+
+    assert!(detect("unknown", "???").is_none());
+}
+
+#[wasm_bindgen_test]
+fn test_detect_heuristics_crlf() {
+    let detection = detect("lyxclient.1in", ".TH LYXCLIENT 1\r\n.SH NAME\r\n").unwrap();
+    assert_eq!(detection.language().name(), "Roff Manpage");
+}
+
+#[wasm_bindgen_test]
+fn test_language() {
+    let rust = Language::new("Rust").ok().unwrap();
+    assert_eq!(rust.language_type(), "programming");
+    assert_eq!(rust.color().as_deref(), Some("#dea584"));
+    assert!(rust.group().is_none());
+    assert_eq!(Language::from_id(rust.id()).ok().unwrap().name(), "Rust");
+    assert!(Language::from_id(0.5).is_err());
+
+    let tsx = Language::new("TSX").ok().unwrap();
+    assert_eq!(tsx.group().unwrap().name(), "TypeScript");
+}
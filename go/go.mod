@@ -0,0 +1,3 @@
+module github.com/sourcegraph/langur/go
+
+go 1.20
@@ -0,0 +1,103 @@
+// Package langur provides Go bindings for Langur, with the same API as
+// the commonly used functions of go-enry (https://github.com/go-enry/go-enry),
+// so that it can be used as a drop-in replacement.
+//
+// The package links against the static library built from crates/langur_ffi,
+// so build it before building this package:
+//
+//	cargo build --release -p langur_ffi
+package langur
+
+/*
+#cgo CFLAGS: -I${SRCDIR}/../crates/langur_ffi/include
+#cgo LDFLAGS: ${SRCDIR}/../target/release/liblangur_ffi.a -ldl -lm -lpthread
+#include <stdlib.h>
+#include "langur.h"
+*/
+import "C"
+
+import "unsafe"
+
+// OtherLanguage is returned when the language of a file can't be determined.
+const OtherLanguage = ""
+
+// GetLanguage returns the language of a file from its name and content,
+// or OtherLanguage if it can't be determined. Only the name and extension
+// of filename are used, so it doesn't need to exist.
+func GetLanguage(filename string, content []byte) string {
+	cFilename := C.CString(filename)
+	defer C.free(unsafe.Pointer(cFilename))
+
+	var detection C.LangurDetection
+	status := C.langur_detect_bytes(cFilename, bytesPointer(content), C.size_t(len(content)), &detection)
+	if status != C.LANGUR_STATUS_OK {
+		return OtherLanguage
+	}
+	return languageName(detection.language)
+}
+
+// GetLanguagesByExtension returns the languages that use the extension of
+// filename, without looking at the content. The content and candidates
+// arguments are only there for compatibility with go-enry, which ignores
+// them too.
+func GetLanguagesByExtension(filename string, _ []byte, _ []string) []string {
+	cFilename := C.CString(filename)
+	defer C.free(unsafe.Pointer(cFilename))
+
+	ids := make([]C.int64_t, 8)
+	count := int(C.langur_languages_by_extension(cFilename, &ids[0], C.size_t(len(ids))))
+	if count > len(ids) {
+		ids = make([]C.int64_t, count)
+		C.langur_languages_by_extension(cFilename, &ids[0], C.size_t(len(ids)))
+	}
+	if count == 0 {
+		return nil
+	}
+
+	languages := make([]string, 0, count)
+	for _, id := range ids[:count] {
+		languages = append(languages, languageName(id))
+	}
+	return languages
+}
+
+// IsVendor returns true if path is vendored, like node_modules/.
+// Directories should end with a slash.
+func IsVendor(path string) bool {
+	cPath := C.CString(path)
+	defer C.free(unsafe.Pointer(cPath))
+	return bool(C.langur_is_vendor(cPath))
+}
+
+// IsDocumentation returns true if path is documentation, like docs/ or a
+// README. Directories should end with a slash.
+func IsDocumentation(path string) bool {
+	cPath := C.CString(path)
+	defer C.free(unsafe.Pointer(cPath))
+	return bool(C.langur_is_documentation(cPath))
+}
+
+// IsGenerated returns true if the file at path with the given content was
+// generated, like a lock file or a file with a "Code generated by" header.
+func IsGenerated(path string, content []byte) bool {
+	cPath := C.CString(path)
+	defer C.free(unsafe.Pointer(cPath))
+	return bool(C.langur_is_generated(cPath, bytesPointer(content), C.size_t(len(content))))
+}
+
+func languageName(id C.int64_t) string {
+	name := C.langur_language_name(id)
+	if name == nil {
+		return OtherLanguage
+	}
+	return C.GoString(name)
+}
+
+// bytesPointer returns a pointer to the first byte of b, or nil if b is
+// empty, since Langur accepts NULL for empty content.
+func bytesPointer(b []byte) *C.uint8_t {
+	if len(b) == 0 {
+		return nil
+	}
+	return (*C.uint8_t)(unsafe.Pointer(&b[0]))
+}
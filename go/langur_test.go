@@ -0,0 +1,67 @@
+package langur
+
+import (
+	"reflect"
+	"sort"
+	"testing"
+)
+
+func TestGetLanguage(t *testing.T) {
+	tests := []struct {
+		filename string
+		content  string
+		want     string
+	}{
+		{"src/Main.purs", "", "PureScript"},
+		{"run", "#!/usr/bin/env python\nprint('hi')\n", "Python"},
+		{"Dockerfile", "", "Dockerfile"},
+		{"unknown", "???", OtherLanguage},
+		{"a.es", "\xff\xfe", OtherLanguage},
+	}
+	for _, test := range tests {
+		if got := GetLanguage(test.filename, []byte(test.content)); got != test.want {
+			t.Errorf("GetLanguage(%q, %q) = %q, want %q", test.filename, test.content, got, test.want)
+		}
+	}
+}
+
+func TestGetLanguagesByExtension(t *testing.T) {
+	if got, want := GetLanguagesByExtension("Main.purs", nil, nil), []string{"PureScript"}; !reflect.DeepEqual(got, want) {
+		t.Errorf("GetLanguagesByExtension(\"Main.purs\") = %q, want %q", got, want)
+	}
+	if got := GetLanguagesByExtension("unknown", nil, nil); got != nil {
+		t.Errorf("GetLanguagesByExtension(\"unknown\") = %q, want nil", got)
+	}
+
+	// .h is shared by more languages than fit in the initial buffer.
+	languages := GetLanguagesByExtension("main.h", nil, nil)
+	sort.Strings(languages)
+	for _, want := range []string{"C", "C++", "Objective-C"} {
+		if i := sort.SearchStrings(languages, want); i == len(languages) || languages[i] != want {
+			t.Errorf("GetLanguagesByExtension(\"main.h\") = %q, missing %q", languages, want)
+		}
+	}
+}
+
+func TestFilters(t *testing.T) {
+	tests := []struct {
+		name string
+		got  bool
+		want bool
+	}{
+		{"IsVendor(node_modules/)", IsVendor("node_modules/"), true},
+		{"IsVendor(static/jquery.min.js)", IsVendor("static/jquery.min.js"), true},
+		{"IsVendor(src/main.go)", IsVendor("src/main.go"), false},
+		{"IsDocumentation(docs/)", IsDocumentation("docs/"), true},
+		{"IsDocumentation(README.md)", IsDocumentation("README.md"), true},
+		{"IsDocumentation(src/main.go)", IsDocumentation("src/main.go"), false},
+		{"IsGenerated(Cargo.lock)", IsGenerated("Cargo.lock", nil), true},
+		{"IsGenerated(kind_string.go)", IsGenerated("kind_string.go", []byte("// Code generated by \"stringer\"; DO NOT EDIT.\n")), true},
+		{"IsGenerated(kind.go)", IsGenerated("kind.go", []byte("package kind\n")), false},
+	}
+	for _, test := range tests {
+		if test.got != test.want {
+			t.Errorf("%s = %v, want %v", test.name, test.got, test.want)
+		}
+	}
+}
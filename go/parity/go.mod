@@ -0,0 +1,10 @@
+module github.com/sourcegraph/langur/go/parity
+
+go 1.20
+
+require (
+	github.com/go-enry/go-enry/v2 v2.8.8
+	github.com/sourcegraph/langur/go v0.0.0
+)
+
+replace github.com/sourcegraph/langur/go => ../
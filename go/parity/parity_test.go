@@ -0,0 +1,178 @@
+// Package parity checks that the Go bindings agree with go-enry on
+// Linguist's samples. It's a separate module so that the bindings don't
+// depend on go-enry.
+//
+// go-enry embeds its own version of Linguist's data and its own classifier,
+// so the results aren't expected to be identical; the tests fail if the
+// fraction of samples on which they agree drops below -min-agreement,
+// and log the samples on which they disagree with -v.
+package parity
+
+import (
+	"flag"
+	"io/fs"
+	"os"
+	"path/filepath"
+	"reflect"
+	"sort"
+	"strconv"
+	"strings"
+	"testing"
+
+	"github.com/go-enry/go-enry/v2"
+	langur "github.com/sourcegraph/langur/go"
+)
+
+var minAgreement = flag.Float64("min-agreement", 0.95, "minimum fraction of samples on which langur and go-enry must agree")
+
+type sample struct {
+	// path is relative to Linguist's samples directory, e.g. "Python/filenames/SConstruct".
+	path    string
+	content []byte
+}
+
+// linguistSamples reads the samples from the Linguist checkout in
+// $LINGUIST_ROOT, skipping the test if it isn't set.
+func linguistSamples(t *testing.T) []sample {
+	root := os.Getenv("LINGUIST_ROOT")
+	if root == "" {
+		t.Skip("LINGUIST_ROOT isn't set")
+	}
+	samplesDir := filepath.Join(root, "samples")
+
+	var samples []sample
+	err := filepath.WalkDir(samplesDir, func(path string, entry fs.DirEntry, err error) error {
+		if err != nil || entry.IsDir() {
+			return err
+		}
+		content, err := os.ReadFile(path)
+		if err != nil {
+			return err
+		}
+		relative, err := filepath.Rel(samplesDir, path)
+		if err != nil {
+			return err
+		}
+		samples = append(samples, sample{filepath.ToSlash(relative), content})
+		return nil
+	})
+	if err != nil {
+		t.Fatal(err)
+	}
+	if len(samples) == 0 {
+		t.Fatalf("no samples in %s", samplesDir)
+	}
+	return samples
+}
+
+// checkAgreement runs same on every sample, logging the ones on which
+// langur and go-enry disagree and failing if there are too many.
+func checkAgreement(t *testing.T, samples []sample, same func(sample) (bool, string)) {
+	agreed := 0
+	for _, s := range samples {
+		ok, disagreement := same(s)
+		if ok {
+			agreed++
+		} else {
+			t.Logf("%s: %s", s.path, disagreement)
+		}
+	}
+	agreement := float64(agreed) / float64(len(samples))
+	t.Logf("agreed on %d/%d samples (%.2f%%)", agreed, len(samples), 100*agreement)
+	if agreement < *minAgreement {
+		t.Errorf("agreement %.4f is below %.4f", agreement, *minAgreement)
+	}
+}
+
+func TestGetLanguageParity(t *testing.T) {
+	checkAgreement(t, linguistSamples(t), func(s sample) (bool, string) {
+		filename := filepath.Base(s.path)
+		got, want := langur.GetLanguage(filename, s.content), enry.GetLanguage(filename, s.content)
+		return got == want, "langur: " + got + ", go-enry: " + want
+	})
+}
+
+func TestGetLanguagesByExtensionParity(t *testing.T) {
+	checkAgreement(t, linguistSamples(t), func(s sample) (bool, string) {
+		filename := filepath.Base(s.path)
+		got := langur.GetLanguagesByExtension(filename, s.content, nil)
+		want := enry.GetLanguagesByExtension(filename, s.content, nil)
+		sort.Strings(got)
+		sort.Strings(want)
+		// go-enry returns an empty slice where langur returns nil.
+		if len(got) == 0 && len(want) == 0 {
+			return true, ""
+		}
+		return reflect.DeepEqual(got, want), "langur: " + join(got) + ", go-enry: " + join(want)
+	})
+}
+
+func TestFiltersParity(t *testing.T) {
+	samples := linguistSamples(t)
+	checks := []struct {
+		name         string
+		langur, enry func(sample) bool
+	}{
+		{"IsVendor", func(s sample) bool { return langur.IsVendor(s.path) }, func(s sample) bool { return enry.IsVendor(s.path) }},
+		{"IsDocumentation", func(s sample) bool { return langur.IsDocumentation(s.path) }, func(s sample) bool { return enry.IsDocumentation(s.path) }},
+		{"IsGenerated", func(s sample) bool { return langur.IsGenerated(s.path, s.content) }, func(s sample) bool { return enry.IsGenerated(s.path, s.content) }},
+	}
+	for _, check := range checks {
+		check := check
+		t.Run(check.name, func(t *testing.T) {
+			checkAgreement(t, samples, func(s sample) (bool, string) {
+				got, want := check.langur(s), check.enry(s)
+				return got == want, "langur: " + strconv.FormatBool(got) + ", go-enry: " + strconv.FormatBool(want)
+			})
+		})
+	}
+}
+
+// TestFiltersPaths checks paths that are vendored, documentation or
+// generated, which the samples aren't. Both langur and go-enry must get
+// every one of them right.
+func TestFiltersPaths(t *testing.T) {
+	tests := []struct {
+		path                             string
+		vendor, documentation, generated bool
+	}{
+		{"node_modules/left-pad/index.js", true, false, true},
+		{"vendor/github.com/pkg/errors/errors.go", true, false, false},
+		{"third_party/zlib/zlib.h", true, false, false},
+		{"static/js/jquery-3.6.0.min.js", true, false, false},
+		{"bower_components/angular/angular.js", true, false, false},
+		{"Godeps/_workspace/src/foo.go", true, false, true},
+		{"docs/index.md", false, true, false},
+		{"Documentation/git.txt", false, true, false},
+		{"README.md", false, true, false},
+		{"CHANGELOG", false, true, false},
+		{"LICENSE", false, true, false},
+		{"examples/hello.rs", false, true, false},
+		{"Cargo.lock", false, false, true},
+		{"package-lock.json", false, false, true},
+		{"app/Form1.Designer.cs", false, false, true},
+		{"dist/app.js.map", true, false, true},
+	}
+	for _, test := range tests {
+		for _, check := range []struct {
+			name         string
+			langur, enry bool
+			want         bool
+		}{
+			{"IsVendor", langur.IsVendor(test.path), enry.IsVendor(test.path), test.vendor},
+			{"IsDocumentation", langur.IsDocumentation(test.path), enry.IsDocumentation(test.path), test.documentation},
+			{"IsGenerated", langur.IsGenerated(test.path, nil), enry.IsGenerated(test.path, nil), test.generated},
+		} {
+			if check.langur != check.want {
+				t.Errorf("langur.%s(%q) = %v, want %v", check.name, test.path, check.langur, check.want)
+			}
+			if check.enry != check.want {
+				t.Errorf("enry.%s(%q) = %v, want %v", check.name, test.path, check.enry, check.want)
+			}
+		}
+	}
+}
+
+func join(languages []string) string {
+	return "[" + strings.Join(languages, ", ") + "]"
+}
@@ -0,0 +1,517 @@
+use ignore::{overrides::OverrideBuilder, WalkBuilder};
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+
+use crate::{
+    detectors::{self, ClassifierTokens, DetectOptions, Detection, ScoredDetection},
+    filters, ids,
+    lines::{self, LineStats},
+    Language,
+};
+
+/// Walks the path provided and tallies the programming languages detected in the given path
+///
+/// Returns a map from the programming languages to a Vec of the files that were detected and the
+/// strategy used
+///
+/// # Examples
+/// ```
+/// use langur::get_language_breakdown;
+/// let breakdown = get_language_breakdown("src/", &Default::default());
+/// let total_detections = breakdown.iter().fold(0, |sum, (language, detections)| sum + detections.len());
+/// println!("Total files detected: {}", total_detections);
+/// ```
+pub fn get_language_breakdown<P: AsRef<Path>>(
+    path: P,
+    options: &DetectOptions,
+) -> HashMap<Language, Vec<(Detection, PathBuf)>> {
+    get_language_breakdown_with_details(path, options)
+        .into_iter()
+        .map(|(language, files)| {
+            let files = files
+                .into_iter()
+                .map(|file| (file.detection, file.path))
+                .collect();
+            (language, files)
+        })
+        .collect()
+}
+
+/// A file in the breakdown returned by [`get_language_breakdown_with_details`].
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileDetection {
+    pub detection: Detection,
+    pub path: PathBuf,
+    /// For Jupyter notebooks, the language of their code, see
+    /// [`detect_notebook_language`](crate::detect_notebook_language).
+    pub notebook_language: Option<Language>,
+    /// The number of lines of code, comments and blank lines, if
+    /// [`DetectOptions::count_lines`] is set.
+    pub lines: Option<LineStats>,
+    /// The number of tokens scored by the classifier, if it was used, see
+    /// [`DetectOptions::classifier_margin`].
+    pub classifier_tokens: Option<ClassifierTokens>,
+}
+
+/// A file that the breakdown skipped because it's larger than
+/// [`DetectOptions::max_file_size`].
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedFile {
+    pub path: PathBuf,
+    /// The size of the file in bytes.
+    pub size: u64,
+}
+
+/// Same as [`get_language_breakdown`], but with details about each file
+/// beyond the strategy used, like the language of the code in notebooks.
+pub fn get_language_breakdown_with_details<P: AsRef<Path>>(
+    path: P,
+    options: &DetectOptions,
+) -> HashMap<Language, Vec<FileDetection>> {
+    get_language_breakdown_with_skipped(path, options).0
+}
+
+/// Same as [`get_language_breakdown_with_details`], but also returns the
+/// files that were skipped because of [`DetectOptions::max_file_size`].
+pub fn get_language_breakdown_with_skipped<P: AsRef<Path>>(
+    path: P,
+    options: &DetectOptions,
+) -> (HashMap<Language, Vec<FileDetection>>, Vec<SkippedFile>) {
+    let override_builder = OverrideBuilder::new(&path);
+    let override_builder = filters::add_documentation_override(override_builder);
+    let override_builder = filters::add_vendor_override(override_builder);
+
+    let num_threads = env::var_os("LANGUR_THREADS")
+        .and_then(|threads| threads.into_string().ok())
+        .and_then(|threads| threads.parse().ok())
+        .unwrap_or_else(num_cpus::get);
+
+    let (tx, rx) = mpsc::channel::<(ScoredDetection, PathBuf, Option<(Language, LineStats)>)>();
+    let (skipped_tx, skipped_rx) = mpsc::channel::<SkippedFile>();
+    let walker = WalkBuilder::new(path)
+        .threads(num_threads)
+        .overrides(override_builder.build().unwrap())
+        .build_parallel();
+
+    walker.run(|| {
+        let tx = tx.clone();
+        let skipped_tx = skipped_tx.clone();
+        Box::new(move |result| {
+            use ignore::WalkState::*;
+
+            if let Ok(path) = result {
+                let path = path.into_path();
+                if let Some(max_file_size) = options.max_file_size {
+                    match fs::metadata(&path) {
+                        Ok(metadata) if metadata.is_file() && metadata.len() > max_file_size => {
+                            let size = metadata.len();
+                            skipped_tx.send(SkippedFile { path, size }).unwrap();
+                            return Continue;
+                        }
+                        _ => {}
+                    }
+                }
+                if !path.is_dir() {
+                    let detection =
+                        detectors::detect_scored(&path, options, options.directory_context);
+                    if let Ok(Some(detection)) = detection {
+                        // Counted here to read the files in parallel, and
+                        // counted again below if the language changes.
+                        let lines = if options.count_lines {
+                            let language = detection.detection.language();
+                            count_file_lines(&path, language, options)
+                                .map(|lines| (language, lines))
+                        } else {
+                            None
+                        };
+                        tx.send((detection, path, lines)).unwrap();
+                    }
+                }
+            }
+            Continue
+        })
+    });
+    drop(tx);
+    drop(skipped_tx);
+    let mut skipped: Vec<SkippedFile> = skipped_rx.into_iter().collect();
+    skipped.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut line_stats = HashMap::new();
+    let detections: Vec<_> = rx
+        .into_iter()
+        .map(|(detection, path, lines)| {
+            if let Some(lines) = lines {
+                line_stats.insert(path.clone(), lines);
+            }
+            (detection, path)
+        })
+        .collect();
+    let detections = if options.directory_context {
+        apply_directory_context(detections)
+    } else {
+        detections
+    };
+
+    let mut language_breakdown = HashMap::new();
+    for (scored, file) in detections {
+        let detection = scored.detection;
+        let notebook_language = if detection.language() == ids::Jupyter_Notebook {
+            read_file(&file, options)
+                .and_then(|content| String::from_utf8(content).ok())
+                .and_then(|content| {
+                    detectors::detect_notebook_language_with_options(&content, options)
+                })
+        } else {
+            None
+        };
+        let lines = match line_stats.remove(&file) {
+            Some((language, lines)) if language == detection.language() => Some(lines),
+            _ if options.count_lines => count_file_lines(&file, detection.language(), options),
+            _ => None,
+        };
+        let files = language_breakdown
+            .entry(detection.language())
+            .or_insert_with(Vec::new);
+        files.push(FileDetection {
+            detection,
+            path: file,
+            notebook_language,
+            lines,
+            classifier_tokens: scored.classifier_tokens,
+        });
+    }
+
+    (language_breakdown, skipped)
+}
+
+fn count_file_lines(path: &Path, language: Language, options: &DetectOptions) -> Option<LineStats> {
+    let content = read_file(path, options)?;
+    Some(lines::count_lines(
+        &String::from_utf8_lossy(&content),
+        language,
+    ))
+}
+
+/// Reads a whole file, or None if it's larger than
+/// [`DetectOptions::max_file_size`], which it can have grown to since it was
+/// walked.
+fn read_file(path: &Path, options: &DetectOptions) -> Option<Vec<u8>> {
+    // One byte more than the limit tells whether the file is over it.
+    let limit = options
+        .max_file_size
+        .map_or(u64::MAX, |size| size.saturating_add(1));
+    let mut content = Vec::new();
+    File::open(path)
+        .ok()?
+        .take(limit)
+        .read_to_end(&mut content)
+        .ok()?;
+    match options.max_file_size {
+        Some(size) if content.len() as u64 > size => None,
+        _ => Some(content),
+    }
+}
+
+/// Re-scores fallback decisions with priors from the languages of the
+/// files that were detected with certainty, see
+/// [`DetectOptions::directory_context`].
+///
+/// Each candidate's classifier log-probability of the whole content is
+/// added to the log of its Laplace-smoothed share of the files in the same
+/// directory, or of all files if none of the candidates were detected in
+/// that directory, so the context only decides when the content is about
+/// as likely for several candidates. Decisions without any context for
+/// their candidates, or whose language the context doesn't change, are
+/// kept.
+fn apply_directory_context(
+    detections: Vec<(ScoredDetection, PathBuf)>,
+) -> Vec<(ScoredDetection, PathBuf)> {
+    let mut directory_counts: HashMap<PathBuf, HashMap<Language, usize>> = HashMap::new();
+    let mut tree_counts: HashMap<Language, usize> = HashMap::new();
+    for (scored, path) in detections.iter() {
+        if scored.fallback_scores.is_none() {
+            let language = scored.detection.language();
+            let directory = path.parent().map(Path::to_path_buf).unwrap_or_default();
+            *directory_counts
+                .entry(directory)
+                .or_default()
+                .entry(language)
+                .or_insert(0) += 1;
+            *tree_counts.entry(language).or_insert(0) += 1;
+        }
+    }
+
+    detections
+        .into_iter()
+        .map(|(mut scored, path)| {
+            let scores = match scored.fallback_scores.take() {
+                Some(scores) => scores,
+                None => return (scored, path),
+            };
+            let count_candidates = |counts: &HashMap<Language, usize>| -> Vec<usize> {
+                scores
+                    .iter()
+                    .map(|(language, _)| counts.get(language).copied().unwrap_or(0))
+                    .collect()
+            };
+            let directory_counts = path
+                .parent()
+                .and_then(|directory| directory_counts.get(directory))
+                .map(count_candidates)
+                .filter(|counts| counts.iter().sum::<usize>() > 0);
+            let counts = match directory_counts {
+                Some(counts) => counts,
+                None => count_candidates(&tree_counts),
+            };
+            let total: usize = counts.iter().sum();
+            if total == 0 {
+                return (scored, path);
+            }
+
+            // Without a classifier score for any candidate, only the context decides.
+            let has_scores = scores.iter().any(|(_, score)| score.is_finite());
+            let (language, _) = scores
+                .iter()
+                .zip(counts)
+                .map(|(&(language, score), count)| {
+                    let score = if has_scores { score } else { 0.0 };
+                    let prior = (count + 1) as f64 / (total + scores.len()) as f64;
+                    (language, score + prior.ln())
+                })
+                .reduce(|best, candidate| {
+                    if candidate.1 > best.1 {
+                        candidate
+                    } else {
+                        best
+                    }
+                })
+                .expect("fallback decisions have candidates");
+            if language == scored.detection.language() {
+                return (scored, path);
+            }
+            let scored = ScoredDetection {
+                detection: Detection::Context(language),
+                ..scored
+            };
+            (scored, path)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ModelMode;
+    use std::sync::Arc;
+
+    fn breakdown_with_context(dir: &str) -> HashMap<Language, Vec<(Detection, PathBuf)>> {
+        let options = DetectOptions {
+            directory_context: true,
+            ..Default::default()
+        };
+        get_language_breakdown(dir, &options)
+    }
+
+    #[test]
+    fn test_directory_context() {
+        let dir = Path::new("temp-context-dir");
+        fs::create_dir_all(dir.join("cpp")).unwrap();
+        fs::create_dir_all(dir.join("c")).unwrap();
+        fs::create_dir_all(dir.join("include")).unwrap();
+        for name in ["a.cpp", "b.cpp", "c.cpp", "header.h"] {
+            fs::write(dir.join("cpp").join(name), "int x;\n").unwrap();
+        }
+        fs::write(dir.join("c/main.c"), "int x;\n").unwrap();
+        fs::write(dir.join("c/header.h"), "int x;\n").unwrap();
+        fs::write(dir.join("include/other.h"), "int x;\n").unwrap();
+
+        let without_context = get_language_breakdown(dir, &DetectOptions::default());
+        let with_context = breakdown_with_context("temp-context-dir");
+        fs::remove_dir_all(dir).unwrap();
+
+        let detection = |breakdown: &HashMap<Language, Vec<(Detection, PathBuf)>>, name: &str| {
+            breakdown
+                .values()
+                .flatten()
+                .find(|(_, path)| path == &dir.join(name))
+                .map(|(detection, _)| *detection)
+        };
+        assert_eq!(
+            detection(&without_context, "cpp/header.h"),
+            Some(Detection::Heuristics(ids::C))
+        );
+        assert_eq!(
+            detection(&with_context, "cpp/header.h"),
+            Some(Detection::Context(ids::Cpp))
+        );
+        // The context agrees with the fallback to C.
+        assert_eq!(
+            detection(&with_context, "c/header.h"),
+            Some(Detection::Heuristics(ids::C))
+        );
+        // Without siblings, the whole tree has more C++ than C.
+        assert_eq!(
+            detection(&with_context, "include/other.h"),
+            Some(Detection::Context(ids::Cpp))
+        );
+        assert_eq!(
+            detection(&with_context, "cpp/a.cpp"),
+            Some(Detection::Extension(ids::Cpp))
+        );
+    }
+
+    #[test]
+    fn test_directory_context_content_evidence() {
+        let dir = Path::new("temp-context-dir3");
+        fs::create_dir_all(dir.join("cpp")).unwrap();
+        fs::create_dir_all(dir.join("c")).unwrap();
+        for name in ["a.cpp", "b.cpp", "c.cpp"] {
+            fs::write(dir.join("cpp").join(name), "int x;\n").unwrap();
+        }
+        for name in ["a.c", "b.c", "c.c"] {
+            fs::write(dir.join("c").join(name), "int x;\n").unwrap();
+        }
+        // Every token is 3 times as likely in one language as in the other,
+        // which is weak per token but strong over the whole file.
+        fs::write(dir.join("cpp/header.h"), "x ".repeat(200)).unwrap();
+        fs::write(dir.join("c/header.h"), "y ".repeat(200)).unwrap();
+        let mut corpus = langur_classifier::Corpus::new();
+        corpus.add_sample("C", "x x x y");
+        corpus.add_sample("C++", "x y y y");
+        let options = DetectOptions {
+            directory_context: true,
+            classifier_model: Some(Arc::new(corpus.train(&Default::default()))),
+            classifier_model_mode: ModelMode::Override,
+            ..Default::default()
+        };
+        let breakdown = get_language_breakdown(dir, &options);
+        fs::remove_dir_all(dir).unwrap();
+
+        let detection = |name: &str| {
+            breakdown
+                .values()
+                .flatten()
+                .find(|(_, path)| path == &dir.join(name))
+                .map(|(detection, _)| *detection)
+        };
+        // The content beats the majority of the directory either way, and
+        // the fallback to C is kept as is when the context doesn't change it.
+        assert_eq!(
+            detection("cpp/header.h"),
+            Some(Detection::Heuristics(ids::C))
+        );
+        assert_eq!(detection("c/header.h"), Some(Detection::Context(ids::Cpp)));
+    }
+
+    #[test]
+    fn test_directory_context_without_context() {
+        let dir = Path::new("temp-context-dir2");
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join("header.h"), "int x;\n").unwrap();
+        let breakdown = breakdown_with_context("temp-context-dir2");
+        fs::remove_dir_all(dir).unwrap();
+
+        assert_eq!(
+            breakdown[&ids::C],
+            vec![(Detection::Heuristics(ids::C), dir.join("header.h"))]
+        );
+    }
+
+    #[test]
+    fn test_notebook_language() {
+        let dir = Path::new("temp-notebook-dir");
+        fs::create_dir_all(dir).unwrap();
+        fs::write(
+            dir.join("analysis.ipynb"),
+            r#"{"cells": [], "metadata": {"kernelspec": {"language": "python"}}}"#,
+        )
+        .unwrap();
+        fs::write(dir.join("main.rs"), "fn main() {}\n").unwrap();
+        let breakdown = get_language_breakdown_with_details(dir, &DetectOptions::default());
+        fs::remove_dir_all(dir).unwrap();
+
+        assert_eq!(
+            breakdown[&ids::Jupyter_Notebook],
+            vec![FileDetection {
+                detection: Detection::Extension(ids::Jupyter_Notebook),
+                path: dir.join("analysis.ipynb"),
+                notebook_language: Some(ids::Python),
+                lines: None,
+                classifier_tokens: None,
+            }]
+        );
+        assert_eq!(breakdown[&ids::Rust][0].notebook_language, None);
+    }
+
+    #[test]
+    fn test_count_lines() {
+        let dir = Path::new("temp-lines-dir");
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join("main.rs"), "// Entry point\nfn main() {}\n\n").unwrap();
+        let options = DetectOptions {
+            count_lines: true,
+            ..Default::default()
+        };
+        let breakdown = get_language_breakdown_with_details(dir, &options);
+        fs::remove_dir_all(dir).unwrap();
+
+        assert_eq!(
+            breakdown[&ids::Rust][0].lines,
+            Some(LineStats {
+                code: 1,
+                comment: 1,
+                blank: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_max_file_size() {
+        let dir = Path::new("temp-max-size-dir");
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join("small.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.join("large.rs"), "fn main() {}\n".repeat(100)).unwrap();
+        let options = DetectOptions {
+            max_file_size: Some(1000),
+            ..Default::default()
+        };
+        let (breakdown, skipped) = get_language_breakdown_with_skipped(dir, &options);
+        fs::remove_dir_all(dir).unwrap();
+
+        assert_eq!(breakdown[&ids::Rust].len(), 1);
+        assert_eq!(breakdown[&ids::Rust][0].path, dir.join("small.rs"));
+        assert_eq!(
+            skipped,
+            vec![SkippedFile {
+                path: dir.join("large.rs"),
+                size: 1300
+            }]
+        );
+    }
+
+    #[test]
+    fn test_read_file_max_file_size() {
+        let path = Path::new("temp-read-file.rs");
+        fs::write(path, "fn main() {}\n").unwrap();
+        let options = |max_file_size| DetectOptions {
+            max_file_size,
+            ..Default::default()
+        };
+        let unlimited = read_file(path, &options(None));
+        let at_limit = read_file(path, &options(Some(13)));
+        let over_limit = read_file(path, &options(Some(12)));
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(unlimited.as_deref(), Some(&b"fn main() {}\n"[..]));
+        assert_eq!(at_limit, unlimited);
+        assert_eq!(over_limit, None);
+    }
+}
@@ -1,20 +1,27 @@
 #![allow(clippy::type_complexity)]
 
-use clap::{App, Arg};
-use ignore::{overrides::OverrideBuilder, WalkBuilder};
+use clap::{App, Arg, ArgMatches, SubCommand};
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::{
     cmp::Reverse,
     collections::{BinaryHeap, HashMap},
-    env,
     io::{self, Write},
     path::{Path, PathBuf},
-    sync::mpsc,
+    sync::Arc,
 };
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 
-use crate::{detectors::Detection, filters, Language, LanguageType, LANGUAGE_DATA_MAP};
+use crate::{
+    classifier::{Corpus, Model, Precision, TrainingOptions},
+    detect_explain,
+    detectors::{ClassifierTokens, DetectOptions, ModelMode, ReadLimit},
+    get_language_breakdown_with_skipped, Explanation, FileDetection, Language, LanguageRegistry,
+    LanguageType, LineStats, RuleOutcome, SkippedFile, LANGUAGE_DATA_MAP,
+};
+
+/// The number of classifier scores printed by `langur explain`.
+const EXPLAIN_SCORES: usize = 10;
 
 struct CLIOptions {
     color: bool,
@@ -43,17 +50,26 @@ impl CLIOptions {
 #[doc(hidden)]
 pub fn main() {
     let matches = get_cli().get_matches();
+    if let Some(matches) = matches.subcommand_matches("train") {
+        train(matches);
+        return;
+    }
+
+    let detect_options = detect_options(&matches);
+    if let Some(matches) = matches.subcommand_matches("explain") {
+        explain(matches.value_of("FILE").unwrap(), &detect_options);
+        return;
+    }
+
     let path = matches.value_of("PATH").unwrap();
-    let breakdown = get_language_breakdown(path);
+    let (breakdown, skipped) = get_language_breakdown_with_skipped(path, &detect_options);
 
-    let mut language_count: Vec<(Language, Vec<(Detection, PathBuf)>)> = breakdown
+    let mut language_count: Vec<(Language, Vec<FileDetection>)> = breakdown
         .into_iter()
         .filter(|(language, _)| {
             matches!(
-                crate::LANGUAGE_DATA_MAP
-                    .get(language)
-                    .map(|l| l.language_type),
-                Some(LanguageType::Markup) | Some(LanguageType::Programming)
+                crate::language_data(language).language_type,
+                LanguageType::Markup | LanguageType::Programming
             )
         })
         .collect();
@@ -62,6 +78,21 @@ pub fn main() {
         std::process::exit(1);
     }
 
+    if matches.is_present("margin") && print_classifier_tokens(&language_count).is_err() {
+        std::process::exit(1);
+    }
+
+    if !skipped.is_empty() && print_skipped_files(&skipped).is_err() {
+        std::process::exit(1);
+    }
+
+    if matches.is_present("lines") {
+        writeln!(io::stdout()).unwrap_or_else(|_| std::process::exit(1));
+        if print_line_counts(&language_count).is_err() {
+            std::process::exit(1);
+        }
+    }
+
     let cli_options = CLIOptions {
         color: !matches.is_present("no-color"),
         condensed_output: matches.is_present("condensed"),
@@ -88,6 +119,50 @@ pub fn main() {
     }
 }
 
+/// Builds the detection options from the arguments of the main command,
+/// which `langur explain` shares.
+fn detect_options(matches: &ArgMatches) -> DetectOptions {
+    let mut detect_options = DetectOptions::default();
+    if let Some(model_path) = matches.value_of("model") {
+        let model = Model::load(model_path).unwrap_or_else(|e| {
+            eprintln!("Failed to load classifier model {}: {}", model_path, e);
+            std::process::exit(1);
+        });
+        detect_options.classifier_model = Some(Arc::new(model));
+    }
+    if matches.value_of("model-mode") == Some("override") {
+        detect_options.classifier_model_mode = ModelMode::Override;
+    }
+    if matches.is_present("languages") || matches.is_present("heuristics") {
+        detect_options.registry = Some(Arc::new(load_registry(matches)));
+    }
+    let registry = detect_options.registry.clone().unwrap_or_default();
+    detect_options.allowed_languages = find_languages(matches, "allow", &registry);
+    detect_options.denied_languages = find_languages(matches, "deny", &registry);
+    detect_options.directory_context = matches.is_present("context");
+    detect_options.count_lines = matches.is_present("lines");
+    detect_options.tokenizer_dialects = matches.is_present("dialects");
+    detect_options.classifier_margin = matches
+        .value_of("margin")
+        .map(|margin| margin.parse().unwrap());
+    let head = matches
+        .value_of("read-limit")
+        .map_or(detect_options.read_limit.head(), |head| {
+            head.parse().unwrap()
+        });
+    detect_options.read_limit = match matches.value_of("read-tail") {
+        Some(tail) => ReadLimit::HeadAndTail {
+            head,
+            tail: tail.parse().unwrap(),
+        },
+        None => ReadLimit::Head(head),
+    };
+    detect_options.max_file_size = matches
+        .value_of("max-file-size")
+        .map(|size| size.parse().unwrap());
+    detect_options
+}
+
 fn get_cli<'a, 'b>() -> App<'a, 'b> {
     App::new("Langur")
         .version("0.1.0")
@@ -118,96 +193,460 @@ fn get_cli<'a, 'b>() -> App<'a, 'b> {
                 "A regex that is used to filter by header which sections get printed for the file and strategy breakdown.",
             ).takes_value(true).multiple(true),
         )
+        .arg(
+            Arg::with_name("model")
+                .short("m")
+                .long("model")
+                .value_name("FILE")
+                .takes_value(true)
+                .help("A classifier model created with `langur train`, used when the other strategies can't decide between languages"),
+        )
+        .arg(
+            Arg::with_name("languages")
+                .long("languages")
+                .value_name("FILE")
+                .takes_value(true)
+                .help("Detects the languages in FILE in addition to the built-in ones. FILE has the same format as Linguist's languages.yml"),
+        )
+        .arg(
+            Arg::with_name("heuristics")
+                .long("heuristics")
+                .value_name("FILE")
+                .takes_value(true)
+                .help("Heuristics that are checked before the built-in ones. FILE has the same format as Linguist's heuristics.yml, and can refer to the languages from --languages"),
+        )
+        .arg(
+            Arg::with_name("model-mode")
+                .long("model-mode")
+                .takes_value(true)
+                .possible_values(&["extend", "override"])
+                .requires("model")
+                .help("Whether the model extends the built-in one (the default), or replaces it so that only the languages in the model can be picked by the classifier"),
+        )
+        .arg(
+            Arg::with_name("dialects")
+                .long("dialects")
+                .requires("model")
+                .help("Tokenizes files with the comment and string syntax of each candidate language, for models trained with `langur train --dialects`"),
+        )
+        .arg(
+            Arg::with_name("margin")
+                .long("margin")
+                .value_name("NATS")
+                .takes_value(true)
+                .validator(|margin| match margin.parse::<f64>() {
+                    Ok(margin) if margin > 0.0 => Ok(()),
+                    _ => Err(String::from("the margin must be a positive number")),
+                })
+                .help("Stops classifying a file once the best candidate's log-probability leads the runner-up by this much, and prints how many tokens were scored"),
+        )
+        .arg(
+            Arg::with_name("read-limit")
+                .long("read-limit")
+                .value_name("BYTES")
+                .takes_value(true)
+                .validator(validate_bytes)
+                .help("Reads at most this many bytes from the start of each file to detect its language, 51200 by default"),
+        )
+        .arg(
+            Arg::with_name("read-tail")
+                .long("read-tail")
+                .value_name("BYTES")
+                .takes_value(true)
+                .validator(validate_bytes)
+                .help("Also reads this many bytes from the end of each file"),
+        )
+        .arg(
+            Arg::with_name("max-file-size")
+                .long("max-file-size")
+                .value_name("BYTES")
+                .takes_value(true)
+                .validator(validate_bytes)
+                .help("Skips files larger than this, and prints the files skipped"),
+        )
+        .arg(
+            Arg::with_name("allow")
+                .long("allow")
+                .value_name("LANGUAGE")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Only detect LANGUAGE, which can be repeated to allow several languages. Files that could only be other languages aren't detected"),
+        )
+        .arg(
+            Arg::with_name("deny")
+                .long("deny")
+                .value_name("LANGUAGE")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Never detect LANGUAGE, e.g. --deny Objective-C for a repository whose .h files are all C. Can be repeated"),
+        )
+        .arg(
+            Arg::with_name("context")
+                .long("context")
+                .help("Re-scores files that the classifier or a fallback heuristic decided on, using the languages of the other files in the same directory, e.g. .h files next to .cpp files are detected as C++. These files are reported with the Context strategy"),
+        )
+        .arg(
+            Arg::with_name("lines")
+                .short("l")
+                .long("lines")
+                .help("Prints the number of lines of code, comments and blank lines for each language"),
+        )
         .arg(
             Arg::with_name("no-color").short("n").long("no-color").help(
                 "Don't color code the output of the breakdowns. This is useful when piping/redirecting the output.",
             ),
         )
+        .subcommand(
+            SubCommand::with_name("train")
+                .about("Trains a classifier model that can be passed to --model")
+                .arg(
+                    Arg::with_name("CORPUS")
+                        .required(true)
+                        .help("A directory with one sub-directory of samples per language, named after the language, e.g. CORPUS/Rust/main.rs"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Where to write the trained model"),
+                )
+                .arg(
+                    Arg::with_name("precision")
+                        .long("precision")
+                        .takes_value(true)
+                        .possible_values(&["u16", "f32"])
+                        .default_value("u16")
+                        .help("How to store log probabilities, u16 makes the model about half the size"),
+                )
+                .arg(
+                    Arg::with_name("dialects")
+                        .long("dialects")
+                        .help("Tokenizes samples with the comment and string syntax of their language. Pass --dialects along with --model when using the model"),
+                )
+                .arg(
+                    Arg::with_name("ngrams")
+                        .long("ngrams")
+                        .takes_value(true)
+                        .possible_values(&["1", "2", "3"])
+                        .default_value("1")
+                        .help("Also uses runs of up to this many consecutive tokens as features, which helps telling close relatives apart at the cost of a bigger model"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("explain")
+                .about("Prints how the language of a file is detected: the candidates after each strategy, the heuristic rules checked, the shebang interpreter, and the classifier's scores and most influential tokens. Options like --model and --heuristics go before `explain`")
+                .arg(
+                    Arg::with_name("FILE")
+                        .required(true)
+                        .help("The file to explain the detection of"),
+                ),
+        )
 }
 
-/// Walks the path provided and tallies the programming languages detected in the given path
-///
-/// Returns a map from the programming languages to a Vec of the files that were detected and the
-/// strategy used
-///
-/// # Examples
-/// ```
-/// use langur::get_language_breakdown;
-/// let breakdown = get_language_breakdown("src/");
-/// let total_detections = breakdown.iter().fold(0, |sum, (language, detections)| sum + detections.len());
-/// println!("Total files detected: {}", total_detections);
-/// ```
-fn get_language_breakdown<P: AsRef<Path>>(path: P) -> HashMap<Language, Vec<(Detection, PathBuf)>> {
-    let override_builder = OverrideBuilder::new(&path);
-    let override_builder = filters::add_documentation_override(override_builder);
-    let override_builder = filters::add_vendor_override(override_builder);
-
-    let num_threads = env::var_os("LANGUR_THREADS")
-        .and_then(|threads| threads.into_string().ok())
-        .and_then(|threads| threads.parse().ok())
-        .unwrap_or_else(num_cpus::get);
-
-    let (tx, rx) = mpsc::channel::<(Detection, PathBuf)>();
-    let walker = WalkBuilder::new(path)
-        .threads(num_threads)
-        .overrides(override_builder.build().unwrap())
-        .build_parallel();
-
-    walker.run(|| {
-        let tx = tx.clone();
-        Box::new(move |result| {
-            use ignore::WalkState::*;
-
-            if let Ok(path) = result {
-                let path = path.into_path();
-                if !path.is_dir() {
-                    if let Ok(Some(detection)) = crate::detectors::detect(&path) {
-                        tx.send((detection, path)).unwrap();
-                    }
-                }
-            }
-            Continue
+fn load_registry(matches: &ArgMatches) -> LanguageRegistry {
+    let mut registry = LanguageRegistry::new();
+    if let Some(path) = matches.value_of("languages") {
+        if let Err(e) = registry.load_languages_yml(path) {
+            eprintln!("Failed to load languages from {}: {}", path, e);
+            std::process::exit(1);
+        }
+    }
+    if let Some(path) = matches.value_of("heuristics") {
+        if let Err(e) = registry.load_heuristics_yml(path) {
+            eprintln!("Failed to load heuristics from {}: {}", path, e);
+            std::process::exit(1);
+        }
+    }
+    registry
+}
+
+/// Looks up the languages passed to `arg` by name, exiting on unknown names.
+fn find_languages(matches: &ArgMatches, arg: &str, registry: &LanguageRegistry) -> Vec<Language> {
+    matches
+        .values_of(arg)
+        .into_iter()
+        .flatten()
+        .map(|name| {
+            registry.find(name).unwrap_or_else(|| {
+                eprintln!("Unknown language for --{}: {}", arg, name);
+                std::process::exit(1);
+            })
         })
+        .collect()
+}
+
+/// Trains a classifier model on a directory with one sub-directory of samples
+/// per language, named after the language, and writes it to the output file.
+fn train(matches: &ArgMatches) {
+    let corpus_dir = matches.value_of("CORPUS").unwrap();
+    let output = matches.value_of("output").unwrap();
+
+    let corpus = if matches.is_present("dialects") {
+        Corpus::with_dialects()
+    } else {
+        Corpus::new()
+    };
+    let ngram_order = matches.value_of("ngrams").unwrap().parse().unwrap();
+    let mut corpus = corpus.with_ngram_order(ngram_order);
+    if let Err(e) = corpus.add_directory(corpus_dir) {
+        eprintln!("Failed to read training corpus {}: {}", corpus_dir, e);
+        std::process::exit(1);
+    }
+
+    let mut samples = 0;
+    for (language, counts) in corpus.languages() {
+        samples += counts.samples;
+        if !LANGUAGE_DATA_MAP.values().any(|data| data.name == language) {
+            eprintln!(
+                "Warning: {} is not a built-in language, pass it with --languages when using the model",
+                language
+            );
+        }
+    }
+    if samples == 0 {
+        eprintln!("No samples found in {}", corpus_dir);
+        std::process::exit(1);
+    }
+
+    let precision = match matches.value_of("precision") {
+        Some("f32") => Precision::F32,
+        _ => Precision::U16,
+    };
+    let model = corpus.train(&TrainingOptions::default());
+    if let Err(e) = model.save(output, precision) {
+        eprintln!("Failed to write classifier model {}: {}", output, e);
+        std::process::exit(1);
+    }
+    println!(
+        "Trained a model for {} languages from {} samples, written to {}",
+        model.languages.len(),
+        samples,
+        output
+    );
+}
+
+/// Prints how the language of a single file is detected, stage by stage.
+fn explain(path: &str, options: &DetectOptions) {
+    let explanation = detect_explain(Path::new(path), options).unwrap_or_else(|e| {
+        eprintln!("Failed to detect the language of {}: {}", path, e);
+        std::process::exit(1);
     });
-    drop(tx);
+    if print_explanation(path, &explanation).is_err() {
+        std::process::exit(1);
+    }
+}
+
+fn print_explanation(path: &str, explanation: &Explanation) -> Result<(), io::Error> {
+    let mut stdout = io::stdout();
+    match explanation.detection {
+        Some(detection) => writeln!(
+            stdout,
+            "{}: {} ({})",
+            path,
+            detection.language().name(),
+            detection.variant()
+        )?,
+        None => writeln!(stdout, "{}: no language detected", path)?,
+    }
+    if let Some(extension) = &explanation.extension {
+        writeln!(stdout, "Extension: {}", extension)?;
+    }
+    if let Some(interpreter) = &explanation.interpreter {
+        writeln!(stdout, "Interpreter: {}", interpreter)?;
+    }
+
+    writeln!(stdout, "\nStages:")?;
+    for stage in explanation.stages.iter() {
+        writeln!(
+            stdout,
+            "  {:<10} found {}, leaving {}",
+            stage.stage.name(),
+            language_names(&stage.languages),
+            language_names(&stage.candidates)
+        )?;
+    }
+
+    if !explanation.heuristic_rules.is_empty() {
+        writeln!(stdout, "\nHeuristic rules:")?;
+        for rule in explanation.heuristic_rules.iter() {
+            let outcome = match rule.outcome {
+                RuleOutcome::Matched => "matched",
+                RuleOutcome::NotMatched => "no match",
+                RuleOutcome::Skipped => "skipped",
+            };
+            writeln!(
+                stdout,
+                "  {:<8} {}{} -> {}: {}",
+                outcome,
+                rule.key,
+                if rule.custom { " (custom)" } else { "" },
+                language_names(&rule.languages),
+                rule.pattern.as_deref().unwrap_or("always")
+            )?;
+        }
+    }
 
-    let mut language_breakdown = HashMap::new();
-    for (detection, file) in rx {
-        let files = language_breakdown
-            .entry(detection.language())
-            .or_insert_with(Vec::new);
-        files.push((detection, file));
+    if !explanation.classifier_scores.is_empty() {
+        writeln!(stdout, "\nClassifier scores:")?;
+        for (language, score) in explanation.classifier_scores.iter().take(EXPLAIN_SCORES) {
+            writeln!(stdout, "  {:>12.2} {}", score, language.name())?;
+        }
     }
 
-    language_breakdown
+    if let [(best, _), (runner_up, _), ..] = explanation.classifier_scores.as_slice() {
+        if !explanation.influential_tokens.is_empty() {
+            writeln!(
+                stdout,
+                "\nTokens favoring {} over {}:",
+                best.name(),
+                runner_up.name()
+            )?;
+            for token in explanation.influential_tokens.iter() {
+                writeln!(
+                    stdout,
+                    "  {:>+10.2} {:?} x{}",
+                    token.influence, token.token, token.count
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn language_names(languages: &[Language]) -> String {
+    if languages.is_empty() {
+        return String::from("none");
+    }
+    let names: Vec<&str> = languages.iter().map(|language| language.name()).collect();
+    names.join(", ")
 }
 
 fn print_language_split(
-    language_counts: &[(Language, Vec<(Detection, PathBuf)>)],
+    language_counts: &[(Language, Vec<FileDetection>)],
 ) -> Result<(), io::Error> {
     let total = language_counts
         .iter()
         .fold(0, |acc, (_, files)| acc + files.len()) as f64;
     for (language, files) in language_counts.iter() {
         let percentage = ((files.len() * 100) as f64) / total;
+        write!(io::stdout(), "{:.2}% {}", percentage, language.name())?;
+        let notebook_languages = count_notebook_languages(files);
+        if !notebook_languages.is_empty() {
+            let counts: Vec<String> = notebook_languages
+                .iter()
+                .map(|(language, count)| format!("{} {}", language.name(), count))
+                .collect();
+            write!(io::stdout(), " ({})", counts.join(", "))?;
+        }
+        writeln!(io::stdout())?;
+    }
+
+    Ok(())
+}
+
+fn print_classifier_tokens(
+    language_counts: &[(Language, Vec<FileDetection>)],
+) -> Result<(), io::Error> {
+    let counts: Vec<ClassifierTokens> = language_counts
+        .iter()
+        .flat_map(|(_, files)| files.iter().filter_map(|file| file.classifier_tokens))
+        .collect();
+    let stopped_early = counts
+        .iter()
+        .filter(|tokens| tokens.total.is_none())
+        .count();
+    let scored: usize = counts.iter().map(|tokens| tokens.scored).sum();
+    writeln!(
+        io::stdout(),
+        "\nClassified {} files, {} of them early, scoring {} tokens",
+        counts.len(),
+        stopped_early,
+        scored
+    )
+}
+
+fn print_skipped_files(skipped: &[SkippedFile]) -> Result<(), io::Error> {
+    writeln!(io::stdout(), "\nSkipped {} files:", skipped.len())?;
+    for file in skipped {
         writeln!(
             io::stdout(),
-            "{:.2}% {}",
-            percentage,
-            LANGUAGE_DATA_MAP.get(language).unwrap().name
+            "  {} ({} bytes)",
+            strip_relative_parts(&file.path).display(),
+            file.size
         )?;
     }
+    Ok(())
+}
 
+fn validate_bytes(bytes: String) -> Result<(), String> {
+    bytes
+        .parse::<u64>()
+        .map(|_| ())
+        .map_err(|_| String::from("expected a number of bytes"))
+}
+
+fn print_line_counts(language_counts: &[(Language, Vec<FileDetection>)]) -> Result<(), io::Error> {
+    let rows: Vec<(&str, usize, LineStats)> = language_counts
+        .iter()
+        .map(|(language, files)| {
+            let lines = files
+                .iter()
+                .filter_map(|file| file.lines)
+                .fold(LineStats::default(), |total, lines| total + lines);
+            (language.name(), files.len(), lines)
+        })
+        .collect();
+    let total = rows.iter().fold(
+        (0, LineStats::default()),
+        |(files, total), (_, count, lines)| (files + count, total + *lines),
+    );
+    let width = rows
+        .iter()
+        .map(|(name, _, _)| name.len())
+        .max()
+        .unwrap_or_default()
+        .max("Language".len());
+
+    writeln!(
+        io::stdout(),
+        "{:<width$} {:>8} {:>10} {:>10} {:>10}",
+        "Language",
+        "Files",
+        "Code",
+        "Comment",
+        "Blank",
+        width = width
+    )?;
+    for (name, files, lines) in rows
+        .iter()
+        .chain(std::iter::once(&("Total", total.0, total.1)))
+    {
+        writeln!(
+            io::stdout(),
+            "{:<width$} {:>8} {:>10} {:>10} {:>10}",
+            name,
+            files,
+            lines.code,
+            lines.comment,
+            lines.blank,
+            width = width
+        )?;
+    }
     Ok(())
 }
 
 fn print_file_breakdown(
-    language_counts: &[(Language, Vec<(Detection, PathBuf)>)],
+    language_counts: &[(Language, Vec<FileDetection>)],
     options: &CLIOptions,
 ) -> Result<(), io::Error> {
     let mut stdout = StandardStream::stdout(options.color_option());
     for (language, breakdowns) in language_counts.iter() {
-        let language_name = LANGUAGE_DATA_MAP.get(language).unwrap().name;
+        let language_name = language.name();
         if options.matches_filter(language_name) {
             stdout.set_color(&TITLE_COLOR)?;
             write!(stdout, "{}", language_name)?;
@@ -215,9 +654,15 @@ fn print_file_breakdown(
             stdout.set_color(&DEFAULT_COLOR)?;
             writeln!(stdout, " ({})", breakdowns.len())?;
             if !options.condensed_output {
-                for (_, file) in breakdowns.iter() {
-                    let path = strip_relative_parts(file);
-                    writeln!(stdout, "{}", path.display())?;
+                for file in breakdowns.iter() {
+                    let path = strip_relative_parts(&file.path);
+                    write!(stdout, "{}", path.display())?;
+                    if let Some(notebook_language) = file.notebook_language {
+                        stdout.set_color(&LANGUAGE_COLOR)?;
+                        write!(stdout, " ({})", notebook_language.name())?;
+                        stdout.set_color(&DEFAULT_COLOR)?;
+                    }
+                    writeln!(stdout)?;
                 }
                 writeln!(stdout)?;
             }
@@ -227,17 +672,17 @@ fn print_file_breakdown(
 }
 
 fn print_strategy_breakdown(
-    language_counts: &[(Language, Vec<(Detection, PathBuf)>)],
+    language_counts: &[(Language, Vec<FileDetection>)],
     options: &CLIOptions,
 ) -> Result<(), io::Error> {
     let mut strategy_breakdown = HashMap::new();
     for (language, files) in language_counts.iter() {
-        let language_name = LANGUAGE_DATA_MAP.get(language).unwrap().name;
-        for (detection, file) in files.iter() {
+        let language_name = language.name();
+        for file in files.iter() {
             let files = strategy_breakdown
-                .entry(detection.variant())
+                .entry(file.detection.variant())
                 .or_insert(BinaryHeap::new());
-            files.push(Reverse((language_name, file)));
+            files.push(Reverse((language_name, &file.path)));
         }
     }
 
@@ -269,6 +714,17 @@ fn print_strategy_breakdown(
     Ok(())
 }
 
+/// The languages of the notebooks among `files`, from most to least common.
+fn count_notebook_languages(files: &[FileDetection]) -> Vec<(Language, usize)> {
+    let mut counts: HashMap<Language, usize> = HashMap::new();
+    for language in files.iter().filter_map(|file| file.notebook_language) {
+        *counts.entry(language).or_insert(0) += 1;
+    }
+    let mut counts: Vec<(Language, usize)> = counts.into_iter().collect();
+    counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.name().cmp(b.name())));
+    counts
+}
+
 fn strip_relative_parts(path: &Path) -> &Path {
     path.strip_prefix("./").unwrap_or(path)
 }
@@ -289,14 +745,15 @@ lazy_static! {
 
 #[cfg(test)]
 mod tests {
-    use super::get_language_breakdown;
+    use super::DetectOptions;
+    use crate::{get_language_breakdown, ids};
     use std::fs;
 
     #[test]
     fn test_get_language_breakdown_ignores_overrides_documentation() {
         fs::create_dir_all("temp-testing-dir").unwrap();
         fs::File::create("temp-testing-dir/README.md").unwrap();
-        assert!(get_language_breakdown("temp-testing-dir").is_empty());
+        assert!(get_language_breakdown("temp-testing-dir", &DetectOptions::default()).is_empty());
 
         fs::remove_dir_all("temp-testing-dir").unwrap();
     }
@@ -305,8 +762,26 @@ mod tests {
     fn test_get_language_breakdown_ignores_overrides_vendor() {
         fs::create_dir_all("temp-testing-dir2/node_modules").unwrap();
         fs::File::create("temp-testing-dir2/node_modules/hello.go").unwrap();
-        assert!(get_language_breakdown("temp-testing-dir2").is_empty());
+        assert!(get_language_breakdown("temp-testing-dir2", &DetectOptions::default()).is_empty());
 
         fs::remove_dir_all("temp-testing-dir2").unwrap();
     }
+
+    #[test]
+    fn test_get_language_breakdown_denied_languages() {
+        fs::create_dir_all("temp-testing-dir3").unwrap();
+        fs::write(
+            "temp-testing-dir3/foo.h",
+            "@interface Foo : NSObject\n@end\n",
+        )
+        .unwrap();
+        let options = DetectOptions {
+            denied_languages: vec![ids::Objective_C],
+            ..Default::default()
+        };
+        let breakdown = get_language_breakdown("temp-testing-dir3", &options);
+        fs::remove_dir_all("temp-testing-dir3").unwrap();
+
+        assert_eq!(breakdown.keys().collect::<Vec<_>>(), vec![&ids::C]);
+    }
 }
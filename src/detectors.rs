@@ -1,52 +1,225 @@
 mod classifier;
+mod explain;
 mod extensions;
 mod filenames;
-mod heuristics;
+pub(crate) mod heuristics;
 mod interpreters;
+mod modelines;
 
 use std::{
+    borrow::Cow,
     fs::File,
-    io::{BufReader, Read, Seek, SeekFrom},
+    io::{self, BufRead, BufReader, Cursor, Read, Seek, SeekFrom},
     path::Path,
+    sync::Arc,
 };
 
-use crate::Language;
+use crate::{segments::notebook, Language, LanguageRegistry, LanguageType};
+pub use explain::{
+    Explanation, HeuristicRule, RuleOutcome, Stage, StageExplanation, TokenInfluence,
+};
+use heuristics::HeuristicsKey;
 
 /// An enum where the variant is the strategy that detected the language and the value is the name
 /// of the language
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
-pub(crate) enum Detection {
+pub enum Detection {
     Filename(Language),
     Extension(Language),
     Shebang(Language),
     Heuristics(Language),
     Classifier(Language),
+    /// Only returned by [`detect_snippet`], for an Emacs or Vim modeline.
+    Modeline(Language),
+    /// Only returned by the breakdown with `DetectOptions::directory_context`,
+    /// for files whose language changed when they were re-scored using the
+    /// languages of other files.
+    Context(Language),
 }
 
 impl Detection {
     /// Returns the language detected
-    pub(crate) fn language(&self) -> Language {
+    pub fn language(&self) -> Language {
         match self {
             Detection::Filename(language)
             | Detection::Extension(language)
             | Detection::Shebang(language)
             | Detection::Heuristics(language)
-            | Detection::Classifier(language) => *language,
+            | Detection::Classifier(language)
+            | Detection::Modeline(language)
+            | Detection::Context(language) => *language,
         }
     }
 
     /// Returns the strategy used to detect the langauge
-    pub(crate) fn variant(&self) -> &str {
+    pub fn variant(&self) -> &str {
         match self {
             Detection::Filename(_) => "Filename",
             Detection::Extension(_) => "Extension",
             Detection::Shebang(_) => "Shebang",
             Detection::Heuristics(_) => "Heuristics",
             Detection::Classifier(_) => "Classifier",
+            Detection::Modeline(_) => "Modeline",
+            Detection::Context(_) => "Context",
         }
     }
 }
 
+/// How many tokens of the content the classifier scored, see
+/// [`DetectOptions::classifier_margin`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct ClassifierTokens {
+    pub scored: usize,
+    /// The number of tokens in the content, or None if the classifier
+    /// stopped early, before the rest of the content was tokenized.
+    pub total: Option<usize>,
+}
+
+/// Options for customizing [`detect_with_options`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Default)]
+pub struct DetectOptions {
+    /// Classifier model to use alongside or instead of the built-in one,
+    /// for example a model trained with `langur train`.
+    pub classifier_model: Option<Arc<langur_classifier::Model>>,
+    /// How `classifier_model` is combined with the built-in model.
+    pub classifier_model_mode: ModelMode,
+    /// Languages to detect in addition to the built-in ones.
+    pub registry: Option<Arc<LanguageRegistry>>,
+    /// If not empty, only these languages can be detected, e.g. a list of
+    /// SQL dialects for a repository of queries. Files that could only be
+    /// other languages aren't detected.
+    pub allowed_languages: Vec<Language>,
+    /// Languages that are never detected, e.g. Objective-C for a repository
+    /// whose `.h` files are all C. Takes precedence over `allowed_languages`.
+    pub denied_languages: Vec<Language>,
+    /// Only used by `get_language_breakdown`. Files whose language was picked
+    /// by the classifier or by a fallback heuristic, like `.h` files
+    /// defaulting to C, are re-scored with priors from the files detected
+    /// with certainty in the same directory, or in the whole tree if there
+    /// are none. The priors only tip the balance when the classifier is
+    /// unsure, and files whose language changes are reported as
+    /// [`Detection::Context`]. For example, a `.h` file next to `.cpp` files
+    /// is detected as C++.
+    pub directory_context: bool,
+    /// Only used by `get_language_breakdown_with_details`, which then counts
+    /// the lines of code, comments and blank lines in each file, see
+    /// [`count_lines`](crate::count_lines).
+    pub count_lines: bool,
+    /// Tokenize the content with the comment and string syntax of each
+    /// candidate language, see [`langur_tokenizer::Dialect`], instead of the
+    /// generic rules, so that e.g. `#include` isn't dropped as a comment.
+    /// Only useful with a `classifier_model` trained with the same dialects,
+    /// like `langur train --dialects`, since the built-in model isn't.
+    pub tokenizer_dialects: bool,
+    /// Stop scoring the content once the classifier's best candidate leads
+    /// the runner-up by this many nats of log-probability, instead of
+    /// scoring every token. The tokens are scored in batches, and the
+    /// number of tokens scored is reported in
+    /// [`FileDetection::classifier_tokens`](crate::FileDetection::classifier_tokens).
+    /// The larger the margin, the less likely the result differs from
+    /// scoring every token. If no heuristic applies to the candidates and
+    /// `read_limit` is a [`ReadLimit::Head`], the content is read as it's
+    /// scored, so the rest of the file isn't read either.
+    pub classifier_margin: Option<f64>,
+    /// How much of a file's content is read when its filename and extension
+    /// aren't enough. The rest of the file is never read.
+    pub read_limit: ReadLimit,
+    /// Only used by `get_language_breakdown`. Files larger than this many
+    /// bytes are skipped, and reported by
+    /// [`get_language_breakdown_with_skipped`](crate::get_language_breakdown_with_skipped).
+    /// Files that are read in full, to count their lines or find the kernel
+    /// of a notebook, are only read up to this size.
+    pub max_file_size: Option<u64>,
+}
+
+/// How much of the content is read to detect a language, see
+/// [`DetectOptions::read_limit`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReadLimit {
+    /// The first bytes of the content.
+    Head(usize),
+    /// The first `head` bytes and the last `tail` bytes of the content,
+    /// which helps with files that start with a long license or data.
+    HeadAndTail { head: usize, tail: usize },
+}
+
+impl Default for ReadLimit {
+    fn default() -> Self {
+        ReadLimit::Head(MAX_CONTENT_SIZE_BYTES)
+    }
+}
+
+impl ReadLimit {
+    /// The number of bytes read from the start of the content.
+    pub fn head(self) -> usize {
+        self.head_and_tail().0
+    }
+
+    fn head_and_tail(self) -> (usize, usize) {
+        match self {
+            ReadLimit::Head(head) => (head, 0),
+            ReadLimit::HeadAndTail { head, tail } => (head, tail),
+        }
+    }
+}
+
+impl DetectOptions {
+    fn allows(&self, language: Language) -> bool {
+        (self.allowed_languages.is_empty() || self.allowed_languages.contains(&language))
+            && !self.denied_languages.contains(&language)
+    }
+
+    /// Removes the languages that can't be detected from the candidates of a strategy.
+    fn retain_allowed(&self, mut languages: Vec<Language>) -> Vec<Language> {
+        languages.retain(|&language| self.allows(language));
+        languages
+    }
+}
+
+/// How a custom classifier model is combined with the built-in one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ModelMode {
+    /// Languages in the custom model are scored with it, all other languages
+    /// with the built-in model.
+    ///
+    /// Scores from models trained on different corpora aren't strictly
+    /// comparable, so this works best when the custom model covers all the
+    /// languages that are commonly confused with each other.
+    #[default]
+    Extend,
+    /// Only the custom model is used; languages it doesn't know can't be
+    /// picked by the classifier.
+    Override,
+}
+
+/// Options for customizing [`detect_snippet_with_options`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Default)]
+pub struct SnippetOptions {
+    /// Languages to consider. If empty, all built-in languages and the
+    /// languages in `detect_options.registry` are considered.
+    pub candidates: Vec<Language>,
+    /// Only consider languages of these types, e.g. [`LanguageType::Programming`]
+    /// to leave out data and prose. If empty, all types are considered.
+    pub language_types: Vec<LanguageType>,
+    /// The classifier model, custom languages and allowed or denied
+    /// languages to use.
+    pub detect_options: DetectOptions,
+}
+
+/// A possible language of a snippet, see [`detect_snippet`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Guess {
+    /// The language, and [`Detection::Modeline`] or [`Detection::Shebang`]
+    /// if a hint in the snippet narrowed it down to a single language,
+    /// or [`Detection::Classifier`] otherwise.
+    pub detection: Detection,
+    /// The probability of the language relative to the other guesses,
+    /// which add up to 1.
+    pub probability: f64,
+}
+
 fn filter_candidates<L: PartialEq + Copy>(
     previous_candidates: Vec<L>,
     new_candidates: Vec<L>,
@@ -79,71 +252,572 @@ fn filter_candidates<L: PartialEq + Copy>(
 ///
 /// # Examples
 /// ```
-/// let path = Path::new("src/bin/main.rs");
+/// use langur::{detect, ids, Detection};
+/// use std::path::Path;
+///
+/// let path = Path::new("src/main.rs");
 /// let language = detect(path).unwrap().unwrap();
 /// assert_eq!(Detection::Heuristics(ids::Rust), language);
 /// ```
-pub(crate) fn detect(path: &Path) -> Result<Option<Detection>, std::io::Error> {
+pub fn detect(path: &Path) -> Result<Option<Detection>, std::io::Error> {
+    detect_with_options(path, &DetectOptions::default())
+}
+
+/// Same as [`detect`], but with customized behavior.
+pub fn detect_with_options(
+    path: &Path,
+    options: &DetectOptions,
+) -> Result<Option<Detection>, std::io::Error> {
+    let detection = detect_from(path, options, false, None, || {
+        Ok(BufReader::new(File::open(path)?))
+    })?;
+    Ok(detection.map(|scored| scored.detection))
+}
+
+/// Same as [`detect_with_options`], but also reports what each stage of
+/// the detection found: the candidates left after the filename, extension
+/// and shebang, the heuristic rules checked, and the classifier's scores
+/// and most influential tokens.
+///
+/// # Examples
+/// ```
+/// use langur::{detect_explain, ids, DetectOptions, Detection, Stage};
+/// use std::path::Path;
+///
+/// let explanation = detect_explain(Path::new("src/main.rs"), &DetectOptions::default()).unwrap();
+/// assert_eq!(explanation.stages[0].stage, Stage::Extension);
+/// assert_eq!(explanation.detection, Some(Detection::Heuristics(ids::Rust)));
+/// ```
+pub fn detect_explain(path: &Path, options: &DetectOptions) -> Result<Explanation, std::io::Error> {
+    let mut explanation = Explanation::default();
+    let detection = detect_from(path, options, false, Some(&mut explanation), || {
+        Ok(BufReader::new(File::open(path)?))
+    })?;
+    if let Some(scored) = detection {
+        explanation.detection = Some(scored.detection);
+        explanation.classifier_tokens = scored.classifier_tokens;
+    }
+    Ok(explanation)
+}
+
+/// A detection, along with the classifier's log-probabilities of the
+/// candidates it was picked from if it was a fallback decision, i.e. by
+/// the classifier or by a heuristic rule without a pattern, rather than a
+/// definite match, and the number of tokens the classifier scored if it
+/// was used.
+#[cfg_attr(not(feature = "breakdown"), allow(dead_code))]
+pub(crate) struct ScoredDetection {
+    pub(crate) detection: Detection,
+    pub(crate) fallback_scores: Option<Vec<(Language, f64)>>,
+    pub(crate) classifier_tokens: Option<ClassifierTokens>,
+}
+
+impl From<Detection> for ScoredDetection {
+    fn from(detection: Detection) -> Self {
+        ScoredDetection {
+            detection,
+            fallback_scores: None,
+            classifier_tokens: None,
+        }
+    }
+}
+
+/// Same as [`detect_with_options`], but keeps the number of tokens scored
+/// by the classifier and, if `keep_scores` is set, the scores of fallback
+/// decisions so that the breakdown can re-score them with context.
+#[cfg(feature = "breakdown")]
+pub(crate) fn detect_scored(
+    path: &Path,
+    options: &DetectOptions,
+    keep_scores: bool,
+) -> Result<Option<ScoredDetection>, std::io::Error> {
+    detect_from(path, options, keep_scores, None, || {
+        Ok(BufReader::new(File::open(path)?))
+    })
+}
+
+/// Same as [`detect`], but for content that has already been read.
+/// `path` is only used for its filename and extension, so it doesn't
+/// need to exist.
+///
+/// # Examples
+/// ```
+/// use langur::{detect_bytes, ids, Detection};
+/// use std::path::Path;
+///
+/// let language = detect_bytes(Path::new("script"), b"#!/usr/bin/env python\n").unwrap();
+/// assert_eq!(Some(Detection::Shebang(ids::Python)), language);
+/// ```
+///
+/// # Errors
+/// Same as [`detect`], which for content in memory means that it isn't valid UTF-8
+pub fn detect_bytes(path: &Path, content: &[u8]) -> Result<Option<Detection>, std::io::Error> {
+    detect_bytes_with_options(path, content, &DetectOptions::default())
+}
+
+/// Same as [`detect_bytes`], but with customized behavior.
+pub fn detect_bytes_with_options(
+    path: &Path,
+    content: &[u8],
+    options: &DetectOptions,
+) -> Result<Option<Detection>, std::io::Error> {
+    let detection = detect_from(path, options, false, None, || Ok(Cursor::new(content)))?;
+    Ok(detection.map(|scored| scored.detection))
+}
+
+/// Returns the languages that use the extension of `path`, which are the
+/// candidates that [`detect`] narrows down for an ambiguous extension.
+/// The file isn't read, and the result is empty for unknown extensions.
+///
+/// # Examples
+/// ```
+/// use langur::{ids, languages_by_extension};
+/// use std::path::Path;
+///
+/// assert_eq!(languages_by_extension(Path::new("main.purs")), vec![ids::PureScript]);
+/// assert!(languages_by_extension(Path::new("main.h")).contains(&ids::C));
+/// ```
+pub fn languages_by_extension(path: &Path) -> Vec<Language> {
+    let registry = LanguageRegistry::new();
+    path.file_name()
+        .and_then(|filename| filename.to_str())
+        .and_then(|filename| extensions::get_extension(filename, &registry))
+        .map(|extension| extensions::get_languages_from_extension(extension, &registry))
+        .unwrap_or_default()
+}
+
+/// Guesses the language of a snippet of code without a filename, like one
+/// pasted into a chat, and returns the guesses ranked from most to least
+/// likely.
+///
+/// An Emacs or Vim modeline and then a shebang narrow down the languages
+/// if present, and the classifier ranks the rest. Returns an empty list
+/// if `content` is blank.
+///
+/// # Examples
+/// ```
+/// use langur::{detect_snippet, ids, Detection};
+///
+/// let guesses = detect_snippet("#!/usr/bin/env python\nprint('hi')\n");
+/// assert_eq!(guesses[0].detection, Detection::Shebang(ids::Python));
+/// ```
+pub fn detect_snippet(content: &str) -> Vec<Guess> {
+    detect_snippet_with_options(content, &SnippetOptions::default())
+}
+
+/// Same as [`detect_snippet`], but with customized behavior.
+pub fn detect_snippet_with_options(content: &str, options: &SnippetOptions) -> Vec<Guess> {
+    if content.trim().is_empty() {
+        return vec![];
+    }
+
+    let built_in_registry;
+    let registry = match options.detect_options.registry.as_deref() {
+        Some(registry) => registry,
+        None => {
+            built_in_registry = LanguageRegistry::new();
+            &built_in_registry
+        }
+    };
+
+    let candidates: Vec<Language> = if options.candidates.is_empty() {
+        Language::VARIANTS
+            .iter()
+            .chain(registry.custom_languages())
+            .copied()
+            .collect()
+    } else {
+        options.candidates.clone()
+    };
+    let candidates: Vec<Language> = candidates
+        .into_iter()
+        .filter(|&language| {
+            (options.language_types.is_empty()
+                || options.language_types.contains(&language.language_type()))
+                && options.detect_options.allows(language)
+        })
+        .collect();
+    if candidates.is_empty() {
+        return vec![];
+    }
+
+    let content = sample_content(content, options.detect_options.read_limit);
+    let content = content.as_ref();
+
+    let candidates = filter_candidates(
+        candidates,
+        modelines::get_languages_from_modeline(content, registry),
+    );
+    if candidates.len() == 1 {
+        return vec![Guess {
+            detection: Detection::Modeline(candidates[0]),
+            probability: 1.0,
+        }];
+    }
+
+    // Reading from memory can't fail.
+    let shebang_languages =
+        interpreters::get_languages_from_shebang(Cursor::new(content), registry)
+            .unwrap_or_default();
+    let candidates = filter_candidates(candidates, shebang_languages);
+    if candidates.len() == 1 {
+        return vec![Guess {
+            detection: Detection::Shebang(candidates[0]),
+            probability: 1.0,
+        }];
+    }
+
+    let (ranked, _) = classifier::rank(content, &candidates, &options.detect_options);
+    // Softmax over the log-probabilities, relative to the best score to
+    // avoid underflow. If no model knows any of the candidates, they're
+    // all equally likely.
+    let best = ranked[0].1;
+    let weights: Vec<f64> = ranked
+        .iter()
+        .map(|&(_, score)| {
+            if best == f64::NEG_INFINITY {
+                1.0
+            } else {
+                (score - best).exp()
+            }
+        })
+        .collect();
+    let total: f64 = weights.iter().sum();
+    ranked
+        .into_iter()
+        .zip(weights)
+        .map(|((language, _), weight)| Guess {
+            detection: Detection::Classifier(language),
+            probability: weight / total,
+        })
+        .collect()
+}
+
+/// Returns the language of the code in a Jupyter notebook, which is
+/// detected as [`ids::Jupyter_Notebook`](crate::ids::Jupyter_Notebook)
+/// regardless of it. The language of the notebook's kernel is read from its
+/// metadata, and otherwise its code cells are classified as a snippet.
+/// Returns `None` if `content` isn't a valid notebook or the classifier
+/// can't tell.
+///
+/// # Examples
+/// ```
+/// use langur::{detect_notebook_language, ids};
+///
+/// let notebook = r#"{"cells": [], "metadata": {"kernelspec": {"language": "R"}}}"#;
+/// assert_eq!(detect_notebook_language(notebook), Some(ids::R));
+/// ```
+pub fn detect_notebook_language(content: &str) -> Option<Language> {
+    detect_notebook_language_with_options(content, &DetectOptions::default())
+}
+
+/// Same as [`detect_notebook_language`], but with customized behavior.
+pub fn detect_notebook_language_with_options(
+    content: &str,
+    options: &DetectOptions,
+) -> Option<Language> {
+    let built_in_registry;
+    let registry = match options.registry.as_deref() {
+        Some(registry) => registry,
+        None => {
+            built_in_registry = LanguageRegistry::new();
+            &built_in_registry
+        }
+    };
+    let kernel =
+        notebook::kernel_language(content, registry).filter(|&language| options.allows(language));
+    if kernel.is_some() {
+        return kernel;
+    }
+
+    let snippet_options = SnippetOptions {
+        language_types: vec![LanguageType::Programming],
+        detect_options: options.clone(),
+        ..Default::default()
+    };
+    let guesses = detect_snippet_with_options(&notebook::code(content)?, &snippet_options);
+    match guesses.as_slice() {
+        [guess] => Some(guess.detection.language()),
+        [first, second, ..] if first.probability > second.probability => {
+            Some(first.detection.language())
+        }
+        _ => None,
+    }
+}
+
+/// `open` is only called if the filename and extension aren't enough to
+/// pick a language. Scores of fallback decisions are only computed if
+/// `keep_scores` is set, and each stage is recorded in `explanation` if
+/// given.
+fn detect_from<R, F>(
+    path: &Path,
+    options: &DetectOptions,
+    keep_scores: bool,
+    mut explanation: Option<&mut Explanation>,
+    open: F,
+) -> Result<Option<ScoredDetection>, std::io::Error>
+where
+    R: BufRead + Seek,
+    F: FnOnce() -> Result<R, std::io::Error>,
+{
+    let built_in_registry;
+    let registry = match options.registry.as_deref() {
+        Some(registry) => registry,
+        None => {
+            built_in_registry = LanguageRegistry::new();
+            &built_in_registry
+        }
+    };
+
     let filename = match path.file_name() {
         Some(filename) => filename.to_str(),
         None => return Ok(None),
     };
 
-    let candidate = filename.and_then(filenames::get_language_from_filename);
-    if let Some(candidate) = candidate {
-        return Ok(Some(Detection::Filename(candidate)));
+    // A filename with several languages, like HOSTS, narrows down the
+    // candidates for the other strategies.
+    let filename_candidates = filename
+        .map(|filename| filenames::get_languages_from_path(path, filename, registry))
+        .unwrap_or_default();
+    let filename_candidates = options.retain_allowed(filename_candidates);
+    if let Some(explanation) = explanation.as_deref_mut() {
+        if !filename_candidates.is_empty() {
+            explanation.push_stage(Stage::Filename, &filename_candidates, &filename_candidates);
+        }
+    }
+    if filename_candidates.len() == 1 {
+        return Ok(Some(Detection::Filename(filename_candidates[0]).into()));
     };
 
-    let extension = filename.and_then(extensions::get_extension);
+    let extension = filename.and_then(|filename| extensions::get_extension(filename, registry));
 
-    let candidates = extension
-        .map(extensions::get_languages_from_extension)
+    let extension_languages = extension
+        .map(|extension| extensions::get_languages_from_extension(extension, registry))
         .unwrap_or_else(Vec::new);
+    let extension_languages = options.retain_allowed(extension_languages);
+    let candidates = filter_candidates(filename_candidates, extension_languages.clone());
+    if let Some(explanation) = explanation.as_deref_mut() {
+        explanation.extension = extension.map(String::from);
+        if extension.is_some() {
+            explanation.push_stage(Stage::Extension, &extension_languages, &candidates);
+        }
+    }
 
     if candidates.len() == 1 {
-        return Ok(Some(Detection::Extension(candidates[0])));
+        return Ok(Some(Detection::Extension(candidates[0]).into()));
     };
 
-    let file = File::open(path)?;
-    let mut reader = BufReader::new(file);
+    let mut reader = open()?;
 
-    let candidates = filter_candidates(
-        candidates,
-        interpreters::get_languages_from_shebang(&mut reader)?,
-    );
+    let interpreter = interpreters::get_interpreter_from_shebang(&mut reader)?;
+    let (interpreter, shebang_languages) = match &interpreter {
+        Some(interpreter) => {
+            let (name, languages) =
+                interpreters::get_languages_from_interpreter(interpreter, registry);
+            (Some(name), languages)
+        }
+        None => (None, vec![]),
+    };
+    let shebang_languages = options.retain_allowed(shebang_languages);
+    let candidates = filter_candidates(candidates, shebang_languages.clone());
+    if let Some(explanation) = explanation.as_deref_mut() {
+        explanation.interpreter = interpreter.map(String::from);
+        if interpreter.is_some() {
+            explanation.push_stage(Stage::Shebang, &shebang_languages, &candidates);
+        }
+    }
     if candidates.len() == 1 {
-        return Ok(Some(Detection::Shebang(candidates[0])));
+        return Ok(Some(Detection::Shebang(candidates[0]).into()));
     };
-    reader.seek(SeekFrom::Start(0))?;
 
-    let mut content = String::new();
-    reader.read_to_string(&mut content)?;
-    const MAX_CONTENT_SIZE_BYTES: usize = 51200;
+    // rules for the extension come first, then the ones for the filename and the
+    // interpreter, which cover files like HOSTS or parrot scripts without one
+    let keys: Vec<HeuristicsKey> = extension
+        .map(HeuristicsKey::Extension)
+        .into_iter()
+        .chain(filename.map(HeuristicsKey::Filename))
+        .chain(interpreter.map(HeuristicsKey::Interpreter))
+        .collect();
 
-    let content = truncate_to_char_boundary(&content, MAX_CONTENT_SIZE_BYTES);
+    // The classifier can stop before the end with a margin, so if it's up
+    // next, the content is only read as far as it gets.
+    if let (Some(_), ReadLimit::Head(head)) = (options.classifier_margin, options.read_limit) {
+        if candidates.len() > 1
+            && explanation.is_none()
+            && !heuristics::any_rule_applies(&keys, &candidates, registry)
+            && classifier::can_read_incrementally(&candidates, options)
+        {
+            reader.seek(SeekFrom::Start(0))?;
+            let reader = reader.take(head as u64);
+            let detection = if keep_scores {
+                let (scores, tokens) = classifier::rank_reader(reader, &candidates, options)?;
+                ScoredDetection {
+                    detection: Detection::Classifier(scores[0].0),
+                    fallback_scores: Some(scores),
+                    classifier_tokens: Some(tokens),
+                }
+            } else {
+                let (language, tokens) = classifier::classify_reader(reader, &candidates, options)?;
+                ScoredDetection {
+                    detection: Detection::Classifier(language),
+                    fallback_scores: None,
+                    classifier_tokens: Some(tokens),
+                }
+            };
+            return Ok(Some(detection));
+        }
+    }
+
+    let content = read_content(&mut reader, options.read_limit)?;
+    let content = content.as_str();
 
     // using heuristics is only going to be useful if we have more than one candidate
-    // if the extension didn't result in candidate languages then the heuristics won't either
+    let mut heuristics_fallback = false;
+    let candidates_before_heuristics = if keep_scores {
+        candidates.clone()
+    } else {
+        vec![]
+    };
     let candidates: Vec<Language> = if candidates.len() > 1 {
-        if let Some(extension) = extension {
-            let languages =
-                heuristics::get_languages_from_heuristics(extension, &candidates, content);
-            filter_candidates(candidates, languages)
-        } else {
-            candidates
+        let (languages, fallback) = heuristics::get_languages_from_heuristics_with_fallback(
+            &keys,
+            &candidates,
+            content,
+            registry,
+            explanation
+                .as_deref_mut()
+                .map(|explanation| &mut explanation.heuristic_rules),
+        );
+        heuristics_fallback = fallback;
+        let languages = options.retain_allowed(languages);
+        let candidates = filter_candidates(candidates, languages.clone());
+        if let Some(explanation) = explanation.as_deref_mut() {
+            explanation.push_stage(Stage::Heuristics, &languages, &candidates);
         }
+        candidates
     } else {
         candidates
     };
 
     match candidates.len() {
         0 => Ok(None),
-        1 => Ok(Some(Detection::Heuristics(candidates[0]))),
-        _ => Ok(Some(Detection::Classifier(classifier::classify(
-            content,
-            &candidates,
-        )))),
+        1 => {
+            let (fallback_scores, classifier_tokens) = if keep_scores && heuristics_fallback {
+                let (scores, tokens) =
+                    classifier::rank(content, &candidates_before_heuristics, options);
+                (Some(scores), Some(tokens))
+            } else {
+                (None, None)
+            };
+            Ok(Some(ScoredDetection {
+                detection: Detection::Heuristics(candidates[0]),
+                fallback_scores,
+                classifier_tokens,
+            }))
+        }
+        _ if keep_scores => {
+            let (scores, tokens) = classifier::rank(content, &candidates, options);
+            Ok(Some(ScoredDetection {
+                detection: Detection::Classifier(scores[0].0),
+                fallback_scores: Some(scores),
+                classifier_tokens: Some(tokens),
+            }))
+        }
+        _ if explanation.is_some() => {
+            let (scores, tokens, influential_tokens) =
+                classifier::rank_explained(content, &candidates, options);
+            let language = scores[0].0;
+            if let Some(explanation) = explanation {
+                explanation.classifier_scores = scores;
+                explanation.influential_tokens = influential_tokens;
+            }
+            Ok(Some(ScoredDetection {
+                detection: Detection::Classifier(language),
+                fallback_scores: None,
+                classifier_tokens: Some(tokens),
+            }))
+        }
+        _ => {
+            let (language, tokens) = classifier::classify(content, &candidates, options);
+            Ok(Some(ScoredDetection {
+                detection: Detection::Classifier(language),
+                fallback_scores: None,
+                classifier_tokens: Some(tokens),
+            }))
+        }
+    }
+}
+
+const MAX_CONTENT_SIZE_BYTES: usize = 51200;
+
+/// Reads the part of the content allowed by `limit`, from the start. The head
+/// and the tail are joined with a newline, so that no token spans both.
+///
+/// # Errors
+/// If the content read isn't valid UTF-8, except for chars cut by the limit.
+fn read_content<R: Read + Seek>(reader: &mut R, limit: ReadLimit) -> io::Result<String> {
+    let (head, tail) = limit.head_and_tail();
+    let len = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    let limit = head.saturating_add(tail) as u64;
+    let mut bytes = Vec::new();
+    if tail == 0 || len <= limit {
+        reader.by_ref().take(limit).read_to_end(&mut bytes)?;
+        let cut = len > bytes.len() as u64;
+        return decode_utf8(bytes, false, cut);
+    }
+    reader.by_ref().take(head as u64).read_to_end(&mut bytes)?;
+    let mut content = decode_utf8(bytes, false, true)?;
+
+    let mut bytes = Vec::new();
+    reader.seek(SeekFrom::Start(len - tail as u64))?;
+    reader.read_to_end(&mut bytes)?;
+    content.push('\n');
+    content.push_str(&decode_utf8(bytes, true, false)?);
+    Ok(content)
+}
+
+/// Decodes bytes cut out of UTF-8 content, dropping the partial chars at
+/// the start if `cut_start` and at the end if `cut_end`.
+fn decode_utf8(mut bytes: Vec<u8>, cut_start: bool, cut_end: bool) -> io::Result<String> {
+    if cut_start {
+        let continuation_bytes = bytes
+            .iter()
+            .take(3)
+            .take_while(|&&byte| byte & 0xC0 == 0x80)
+            .count();
+        bytes.drain(..continuation_bytes);
+    }
+    match String::from_utf8(bytes) {
+        Ok(content) => Ok(content),
+        Err(e) if cut_end && e.utf8_error().error_len().is_none() => {
+            let valid_up_to = e.utf8_error().valid_up_to();
+            let mut bytes = e.into_bytes();
+            bytes.truncate(valid_up_to);
+            // Valid by construction.
+            Ok(String::from_utf8(bytes).unwrap_or_default())
+        }
+        Err(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+    }
+}
+
+/// Same as [`read_content`], for content that has already been read.
+fn sample_content(content: &str, limit: ReadLimit) -> Cow<'_, str> {
+    let (head, tail) = limit.head_and_tail();
+    let limit = head.saturating_add(tail);
+    if tail == 0 || content.len() <= limit {
+        return Cow::Borrowed(truncate_to_char_boundary(content, limit));
+    }
+    let mut tail_start = content.len() - tail;
+    while !content.is_char_boundary(tail_start) {
+        tail_start += 1;
     }
+    Cow::Owned(format!(
+        "{}\n{}",
+        truncate_to_char_boundary(content, head),
+        &content[tail_start..]
+    ))
 }
 
 // function stolen from from https://doc.rust-lang.org/nightly/src/core/str/mod.rs.html
@@ -180,6 +854,63 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_detect_filename_patterns() {
+        assert_eq!(
+            detect(Path::new("docker/Dockerfile.prod")).unwrap(),
+            Some(Detection::Filename(ids::Dockerfile))
+        );
+
+        // HOSTS is both INI and a hosts file, so the classifier decides.
+        let detection = detect_bytes(Path::new("HOSTS"), b"127.0.0.1 localhost\n")
+            .unwrap()
+            .unwrap();
+        assert_eq!(detection.variant(), "Classifier");
+        assert!([ids::INI, ids::Hosts_File].contains(&detection.language()));
+    }
+
+    #[test]
+    fn test_detect_filename_and_interpreter_heuristics() {
+        let mut registry = LanguageRegistry::new();
+        registry
+            .add_filename_heuristic("HOSTS", &[ids::Hosts_File], Some(r"^\d+\.\d+\.\d+"))
+            .unwrap();
+        registry
+            .add_interpreter_heuristic(
+                "parrot",
+                &[ids::Parrot_Internal_Representation],
+                Some(r"^\.sub\b"),
+            )
+            .unwrap();
+        let options = DetectOptions {
+            registry: Some(Arc::new(registry)),
+            ..Default::default()
+        };
+        let detect = |path: &str, content: &str| {
+            detect_bytes_with_options(Path::new(path), content.as_bytes(), &options)
+                .unwrap()
+                .unwrap()
+        };
+
+        assert_eq!(
+            detect("etc/HOSTS", "127.0.0.1 localhost\n"),
+            Detection::Heuristics(ids::Hosts_File)
+        );
+        assert_eq!(
+            detect("run", "#!/usr/bin/parrot\n.sub main\n.end\n"),
+            Detection::Heuristics(ids::Parrot_Internal_Representation)
+        );
+        // The version is stripped from the interpreter like for the shebang.
+        assert_eq!(
+            detect("run", "#!/usr/bin/env parrot2\n.sub main\n.end\n"),
+            Detection::Heuristics(ids::Parrot_Internal_Representation)
+        );
+        assert_eq!(
+            detect("run", "#!/usr/bin/parrot\nset I1, 10\n").variant(),
+            "Classifier"
+        );
+    }
+
     #[test]
     fn test_detect_extension() {
         let path = Path::new("pizza.purs");
@@ -205,6 +936,19 @@ mod tests {
         assert_eq!(detected_language, Detection::Shebang(ids::Python));
     }
 
+    #[test]
+    fn test_detect_bytes() {
+        // The file doesn't exist, so the content has to come from memory.
+        let path = Path::new("does-not-exist/a.es");
+        let detected_language = detect_bytes(path, b"'use strict'").unwrap().unwrap();
+        assert_eq!(detected_language, Detection::Heuristics(ids::JavaScript));
+
+        let detected_language = detect_bytes(Path::new("a"), b"#!/usr/bin/python").unwrap();
+        assert_eq!(detected_language, Some(Detection::Shebang(ids::Python)));
+
+        assert!(detect_bytes(Path::new("a.es"), b"\xff\xfe").is_err());
+    }
+
     #[test]
     fn test_detect_heuristics() {
         let path = Path::new("a.es");
@@ -243,6 +987,236 @@ mod tests {
         assert_eq!(detected_language, Detection::Classifier(ids::Rust));
     }
 
+    #[test]
+    fn test_detect_with_classifier_model() {
+        let path = Path::new("model.rs");
+        let mut file = File::create(path).unwrap();
+        file.write_all(b"rs_allocation alloc;").unwrap();
+        file.flush().unwrap();
+
+        let mut corpus = langur_classifier::Corpus::new();
+        corpus.add_sample("Rust", "let x: Vec<u8> = vec![];");
+        corpus.add_sample("RenderScript", "rs_allocation alloc; rs_script script;");
+        let options = DetectOptions {
+            classifier_model: Some(Arc::new(corpus.train(&Default::default()))),
+            classifier_model_mode: ModelMode::Override,
+            ..Default::default()
+        };
+        let detected_language = detect_with_options(path, &options).unwrap().unwrap();
+
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(
+            detected_language,
+            Detection::Classifier(ids::RenderScript)
+        );
+    }
+
+    #[test]
+    fn test_detect_with_margin_reads_incrementally() {
+        /// Counts the bytes read from the content.
+        struct CountingReader<'a> {
+            content: Cursor<&'a [u8]>,
+            bytes_read: &'a std::cell::Cell<usize>,
+        }
+
+        impl Read for CountingReader<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let len = self.content.read(buf)?;
+                self.bytes_read.set(self.bytes_read.get() + len);
+                Ok(len)
+            }
+        }
+
+        impl Seek for CountingReader<'_> {
+            fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+                self.content.seek(pos)
+            }
+        }
+
+        let mut registry = LanguageRegistry::new();
+        registry
+            .add_languages_yml(
+                "Sprocket Script:
+  type: programming
+  extensions: ['.sprocket']
+Sprocket Data:
+  type: data
+  extensions: ['.sprocket']
+",
+            )
+            .unwrap();
+        let mut corpus = langur_classifier::Corpus::new();
+        corpus.add_sample("Sprocket Script", "spin sprocket(teeth) { turn teeth }");
+        corpus.add_sample("Sprocket Data", "teeth = 12\nsize = 3");
+        let mut options = DetectOptions {
+            classifier_model: Some(Arc::new(corpus.train(&Default::default()))),
+            classifier_model_mode: ModelMode::Override,
+            registry: Some(Arc::new(registry)),
+            classifier_margin: Some(5.0),
+            ..Default::default()
+        };
+
+        let path = Path::new("gears.sprocket");
+        let content = "spin sprocket(teeth) { turn teeth }\n".repeat(1000);
+        let detect = |options: &DetectOptions, content: &[u8]| {
+            let bytes_read = std::cell::Cell::new(0);
+            let detection = detect_from(path, options, false, None, || {
+                Ok(BufReader::with_capacity(
+                    1024,
+                    CountingReader {
+                        content: Cursor::new(content),
+                        bytes_read: &bytes_read,
+                    },
+                ))
+            });
+            (detection, bytes_read.get())
+        };
+
+        let (detection, bytes_read) = detect(&options, content.as_bytes());
+        let detection = detection.unwrap().unwrap();
+        let script = options.registry.as_ref().unwrap().find("Sprocket Script");
+        assert_eq!(Some(detection.detection.language()), script);
+        assert_eq!(detection.classifier_tokens.unwrap().total, None);
+        assert!(bytes_read < 4096, "read {} bytes", bytes_read);
+
+        // Invalid UTF-8 is still an error.
+        assert!(detect(&options, b"spin \xff\xfe").0.is_err());
+
+        options.classifier_margin = None;
+        let (detection, bytes_read) = detect(&options, content.as_bytes());
+        let tokens = detection.unwrap().unwrap().classifier_tokens.unwrap();
+        assert_eq!(tokens.total, Some(tokens.scored));
+        assert!(bytes_read >= content.len());
+    }
+
+    #[test]
+    fn test_detect_explain() {
+        let path = Path::new("explain.es");
+        fs::write(path, "'use strict';\n").unwrap();
+        let explanation = detect_explain(path, &DetectOptions::default()).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(explanation.extension.as_deref(), Some(".es"));
+        assert_eq!(
+            explanation
+                .stages
+                .iter()
+                .map(|stage| (stage.stage, stage.candidates.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (Stage::Extension, vec![ids::Erlang, ids::JavaScript]),
+                (Stage::Heuristics, vec![ids::JavaScript]),
+            ]
+        );
+        let rules = &explanation.heuristic_rules;
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].languages, vec![ids::Erlang]);
+        assert_eq!(rules[0].outcome, RuleOutcome::NotMatched);
+        assert_eq!(rules[1].languages, vec![ids::JavaScript]);
+        assert_eq!(rules[1].outcome, RuleOutcome::Matched);
+        assert!(rules[1].pattern.as_ref().unwrap().starts_with('/'));
+        assert!(!rules[1].custom);
+        assert_eq!(
+            explanation.detection,
+            Some(Detection::Heuristics(ids::JavaScript))
+        );
+        assert_eq!(explanation.classifier_tokens, None);
+    }
+
+    #[test]
+    fn test_detect_explain_shebang_and_classifier() {
+        let path = Path::new("explain-script");
+        fs::write(
+            path,
+            "#!/usr/bin/env parrot2\nrs_allocation alloc\nrs_allocation other\n",
+        )
+        .unwrap();
+        let mut corpus = langur_classifier::Corpus::new();
+        corpus.add_sample("Parrot Assembly", "set I1, 10\nprint I1\nend\n");
+        corpus.add_sample(
+            "Parrot Internal Representation",
+            "rs_allocation alloc\n.sub main\n.end\n",
+        );
+        let options = DetectOptions {
+            classifier_model: Some(Arc::new(corpus.train(&Default::default()))),
+            classifier_model_mode: ModelMode::Override,
+            ..Default::default()
+        };
+        let explanation = detect_explain(path, &options).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(explanation.extension, None);
+        assert_eq!(explanation.interpreter.as_deref(), Some("parrot"));
+        assert_eq!(explanation.stages[0].stage, Stage::Shebang);
+        assert!(explanation.heuristic_rules.is_empty());
+        assert_eq!(explanation.classifier_scores.len(), 2);
+        assert_eq!(
+            explanation.classifier_scores[0].0,
+            ids::Parrot_Internal_Representation
+        );
+        let top = &explanation.influential_tokens[0];
+        assert_eq!((top.token.as_str(), top.count), ("rs_allocation", 2));
+        assert!(top.influence > 0.0);
+        assert_eq!(
+            explanation.detection,
+            Some(Detection::Classifier(ids::Parrot_Internal_Representation))
+        );
+        let tokens = explanation.classifier_tokens.unwrap();
+        assert!(tokens.scored > 0);
+        assert_eq!(tokens.total, Some(tokens.scored));
+    }
+
+    #[test]
+    fn test_detect_with_registry() {
+        let mut registry = LanguageRegistry::new();
+        registry
+            .add_languages_yml(
+                "Widget Script:
+  type: programming
+  extensions: ['.widget', '.rs']
+  filenames: [Widgetfile]
+  interpreters: [widgetsh]
+",
+            )
+            .unwrap();
+        let widget_script = registry.find("Widget Script").unwrap();
+        registry
+            .add_heuristic(".rs", &[widget_script], Some("^widget "))
+            .unwrap();
+        let options = DetectOptions {
+            registry: Some(Arc::new(registry)),
+            ..Default::default()
+        };
+
+        let dir = Path::new("temp-registry-dir");
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join("main.widget"), "").unwrap();
+        fs::write(dir.join("Widgetfile"), "").unwrap();
+        fs::write(dir.join("run"), "#!/usr/bin/env widgetsh\n").unwrap();
+        fs::write(dir.join("shared.rs"), "widget foo\n").unwrap();
+        let detect = |name: &str| detect_with_options(&dir.join(name), &options).unwrap();
+        let detections = [
+            detect("main.widget"),
+            detect("Widgetfile"),
+            detect("run"),
+            detect("shared.rs"),
+        ];
+        let built_in = detect_with_options(&dir.join("shared.rs"), &Default::default()).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+
+        assert_eq!(
+            detections,
+            [
+                Some(Detection::Extension(widget_script)),
+                Some(Detection::Filename(widget_script)),
+                Some(Detection::Shebang(widget_script)),
+                Some(Detection::Heuristics(widget_script)),
+            ]
+        );
+        assert_ne!(built_in.unwrap().language(), widget_script);
+    }
+
     #[test]
     fn test_detect_none() {
         let path = Path::new("y");
@@ -264,6 +1238,149 @@ mod tests {
         assert_eq!(detected_language, None);
     }
 
+    #[test]
+    fn test_detect_allowed_and_denied_languages() {
+        let deny = |languages: &[Language]| DetectOptions {
+            denied_languages: languages.to_vec(),
+            ..Default::default()
+        };
+        let detect = |filename: &str, content: &str, options: &DetectOptions| {
+            detect_bytes_with_options(Path::new(filename), content.as_bytes(), options).unwrap()
+        };
+
+        let objective_c = "@interface Foo : NSObject\n@end\n";
+        assert_eq!(
+            detect("foo.h", objective_c, &Default::default()),
+            Some(Detection::Heuristics(ids::Objective_C))
+        );
+        assert_eq!(
+            detect("foo.h", objective_c, &deny(&[ids::Objective_C])),
+            Some(Detection::Heuristics(ids::C))
+        );
+        assert_eq!(
+            detect("foo.h", objective_c, &deny(&[ids::Objective_C, ids::Cpp])),
+            Some(Detection::Extension(ids::C))
+        );
+        let allow_c = DetectOptions {
+            allowed_languages: vec![ids::C, ids::Python],
+            ..Default::default()
+        };
+        assert_eq!(
+            detect("foo.h", objective_c, &allow_c),
+            Some(Detection::Extension(ids::C))
+        );
+
+        // Denied languages can't be picked by any strategy.
+        assert_eq!(
+            detect("Dockerfile", "FROM alpine\n", &deny(&[ids::Dockerfile])),
+            None
+        );
+        assert_eq!(detect("foo.purs", "", &deny(&[ids::PureScript])), None);
+        let python = "#!/usr/bin/env python\nprint('hi')\n";
+        assert_eq!(detect("run", python, &deny(&[ids::Python])), None);
+        assert_eq!(
+            detect("run", python, &allow_c),
+            Some(Detection::Shebang(ids::Python))
+        );
+        // Denying takes precedence over allowing.
+        let allow_and_deny = DetectOptions {
+            denied_languages: vec![ids::Python],
+            ..allow_c
+        };
+        assert_eq!(detect("run", python, &allow_and_deny), None);
+    }
+
+    #[test]
+    fn test_detect_snippet() {
+        let guesses = detect_snippet(
+            "use std::io;
+            fn main() {
+                println!(\"{}\", \"Hello World\");
+            }",
+        );
+        assert_eq!(guesses[0].detection, Detection::Classifier(ids::Rust));
+        assert!(guesses
+            .windows(2)
+            .all(|pair| pair[0].probability >= pair[1].probability));
+
+        assert_eq!(detect_snippet(" \n\t"), vec![]);
+    }
+
+    #[test]
+    fn test_detect_snippet_hints() {
+        assert_eq!(
+            detect_snippet("# vim: set ft=python:\nprint('hi')\n"),
+            vec![Guess {
+                detection: Detection::Modeline(ids::Python),
+                probability: 1.0,
+            }]
+        );
+        assert_eq!(
+            detect_snippet("#!/usr/bin/env ruby\nputs 'hi'\n"),
+            vec![Guess {
+                detection: Detection::Shebang(ids::Ruby),
+                probability: 1.0,
+            }]
+        );
+
+        // Hints outside of the candidates are ignored.
+        let options = SnippetOptions {
+            candidates: vec![ids::C, ids::Cpp],
+            ..Default::default()
+        };
+        let guesses = detect_snippet_with_options("#!/usr/bin/env ruby\nputs 'hi'\n", &options);
+        assert_eq!(guesses.len(), 2);
+        let options = SnippetOptions {
+            detect_options: DetectOptions {
+                denied_languages: vec![ids::Ruby],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let guesses = detect_snippet_with_options("#!/usr/bin/env ruby\nputs 'hi'\n", &options);
+        assert!(guesses
+            .iter()
+            .all(|guess| guess.detection.language() != ids::Ruby));
+    }
+
+    #[test]
+    fn test_detect_snippet_options() {
+        let options = SnippetOptions {
+            candidates: vec![ids::C, ids::Cpp, ids::Markdown],
+            language_types: vec![LanguageType::Programming],
+            ..Default::default()
+        };
+        let guesses = detect_snippet_with_options("int main() { return 0; }", &options);
+        let mut languages: Vec<Language> = guesses
+            .iter()
+            .map(|guess| guess.detection.language())
+            .collect();
+        languages.sort_by_key(|language| language.name());
+        assert_eq!(languages, vec![ids::C, ids::Cpp]);
+        let total: f64 = guesses.iter().map(|guess| guess.probability).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_detect_notebook_language() {
+        let notebook = r#"{"cells": [], "metadata": {"kernelspec": {"language": "python"}}}"#;
+        assert_eq!(detect_notebook_language(notebook), Some(ids::Python));
+        // Without kernel metadata, the code cells are detected as a snippet.
+        let notebook = r##"{"cells": [
+            {"cell_type": "code", "source": ["# vim: ft=ruby\n", "puts 1"]}
+        ], "metadata": {}}"##;
+        assert_eq!(detect_notebook_language(notebook), Some(ids::Ruby));
+        let options = DetectOptions {
+            denied_languages: vec![ids::Ruby],
+            ..Default::default()
+        };
+        assert_ne!(
+            detect_notebook_language_with_options(notebook, &options),
+            Some(ids::Ruby)
+        );
+        assert_eq!(detect_notebook_language("not a notebook"), None);
+    }
+
     fn linguist_path(s: &str) -> PathBuf {
         PathBuf::from("external/com_github_linguist").join(s)
     }
@@ -278,6 +1395,8 @@ mod tests {
         }
     }
 
+    // NOTE: The classifier is trained on these same samples, so this mostly
+    // checks for memorization. See tools/accuracy for held-out accuracy.
     #[test]
     fn test_detect_accuracy() {
         let mut total = 0;
@@ -376,4 +1495,42 @@ mod tests {
             vec!["Python"]
         );
     }
+
+    #[test]
+    fn test_read_limit() {
+        let cases = [
+            ("héllo wörld", ReadLimit::Head(100), "héllo wörld"),
+            // The limit cuts the `é`.
+            ("héllo wörld", ReadLimit::Head(2), "h"),
+            (
+                "abcdefghij",
+                ReadLimit::HeadAndTail { head: 3, tail: 3 },
+                "abc\nhij",
+            ),
+            (
+                "abcdefghij",
+                ReadLimit::HeadAndTail { head: 6, tail: 6 },
+                "abcdefghij",
+            ),
+            // The limits cut the `é` and the `ö`.
+            (
+                "héllo wörld",
+                ReadLimit::HeadAndTail { head: 2, tail: 4 },
+                "h\nrld",
+            ),
+        ];
+        for (content, limit, expected) in cases {
+            assert_eq!(
+                read_content(&mut Cursor::new(content), limit).unwrap(),
+                expected
+            );
+            assert_eq!(sample_content(content, limit), expected);
+        }
+
+        assert!(read_content(&mut Cursor::new(b"a\xffb"), ReadLimit::Head(10)).is_err());
+        assert_eq!(
+            read_content(&mut Cursor::new(b"ab\xffc"), ReadLimit::Head(2)).unwrap(),
+            "ab"
+        );
+    }
 }
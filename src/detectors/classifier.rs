@@ -1,13 +1,33 @@
-// Include the map that contains the token log probabilities
-// static TOKEN_LOG_PROBABILITIES: phf::Map<Language, f64> = ...;
-include!("../generated/token_log_probabilities.rs");
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    io::{self, BufRead},
+    iter,
+};
 
-// Include the array of all possible languages
+use langur_classifier::{CompactModel, StreamTokens};
+use langur_tokenizer::{Dialect, StreamTokenizer};
+use lazy_static::lazy_static;
 
-const MAX_TOKEN_BYTES: usize = 32;
-const DEFAULT_LOG_PROB: f64 = -19f64;
+use super::explain::TokenInfluence;
+use crate::{ClassifierTokens, DetectOptions, Language, ModelMode};
 
-use crate::Language;
+lazy_static! {
+    /// The built-in model, written by codegen in the format described in
+    /// `langur_classifier::serialization`. Decoding happens on first use
+    /// instead of at compile time, which keeps the generated code small.
+    static ref BUILT_IN_MODEL: CompactModel = CompactModel::from_bytes(include_bytes!(
+        "../generated/token_log_probabilities.bin"
+    ))
+    .expect("built-in classifier model is invalid");
+}
+
+/// The number of tokens scored between checks of
+/// [`DetectOptions::classifier_margin`].
+const MARGIN_BATCH_TOKENS: usize = 256;
+
+/// The number of features returned by [`rank_explained`].
+const INFLUENTIAL_TOKENS: usize = 10;
 
 #[derive(Debug)]
 struct LanguageScore {
@@ -15,38 +35,463 @@ struct LanguageScore {
     score: f64,
 }
 
+/// The tokens of the content under one tokenizer dialect, shared by all the
+/// candidates with that dialect. They're tokenized a batch at a time, so
+/// that the rest of the content isn't tokenized once the classifier stops.
+struct Tokenized<'a> {
+    dialect: Option<&'static Dialect>,
+    source: Box<dyn Iterator<Item = io::Result<Cow<'a, str>>> + 'a>,
+    /// The tokens read but not scored yet.
+    tokens: Vec<Cow<'a, str>>,
+    /// The number of tokens scored.
+    scored: usize,
+    exhausted: bool,
+}
+
+impl<'a> Tokenized<'a> {
+    fn new(content: &'a str, dialect: Option<&'static Dialect>) -> Self {
+        let source = content_tokens(content, dialect).map(|token| Ok(Cow::Borrowed(token)));
+        Tokenized::with_source(Box::new(source), dialect)
+    }
+
+    /// Tokenizes the content as it's read from `reader`. Invalid UTF-8 is an
+    /// error, like when the content is read up front.
+    fn from_reader<R: BufRead + 'a>(reader: R, dialect: Option<&'static Dialect>) -> Self {
+        let stream = match dialect {
+            Some(dialect) => StreamTokenizer::with_dialect(reader, dialect),
+            None => StreamTokenizer::new(reader),
+        };
+        let mut tokens = StreamTokens::new(stream);
+        let source = iter::from_fn(move || {
+            let token = tokens.next();
+            if tokens.stream().invalid_utf8() {
+                let message = "stream did not contain valid UTF-8";
+                return Some(Err(io::Error::new(io::ErrorKind::InvalidData, message)));
+            }
+            token.map(|token| token.map(Cow::Owned))
+        });
+        Tokenized::with_source(Box::new(source), dialect)
+    }
+
+    fn with_source(
+        source: Box<dyn Iterator<Item = io::Result<Cow<'a, str>>> + 'a>,
+        dialect: Option<&'static Dialect>,
+    ) -> Self {
+        Tokenized {
+            dialect,
+            source,
+            tokens: Vec::new(),
+            scored: 0,
+            exhausted: false,
+        }
+    }
+
+    /// Whether all the tokens have been scored.
+    fn is_done(&self) -> bool {
+        self.exhausted && self.tokens.is_empty()
+    }
+
+    /// Reads the next `len` tokens, plus the ones that n-grams starting in
+    /// them can end at, and passes `score_batch` the features of the custom
+    /// model, if there is one, and the features of the built-in model as
+    /// indices.
+    fn next_batch<F>(
+        &mut self,
+        len: usize,
+        options: &DetectOptions,
+        score_batch: F,
+    ) -> io::Result<()>
+    where
+        F: FnOnce(&[Cow<str>], &[Option<u32>]),
+    {
+        let custom_ngram_order = match options.classifier_model.as_deref() {
+            Some(model) => model.ngram_order,
+            None => 1,
+        };
+        // One more token than the n-grams need tells whether any are left.
+        let ngram_order = custom_ngram_order.max(BUILT_IN_MODEL.ngram_order());
+        let wanted = len.saturating_add(ngram_order);
+        while self.tokens.len() < wanted {
+            match self.source.next() {
+                Some(token) => self.tokens.push(token?),
+                None => {
+                    self.exhausted = true;
+                    break;
+                }
+            }
+        }
+
+        let tokens: Vec<&str> = self.tokens.iter().map(AsRef::as_ref).collect();
+        let range = 0..len.min(tokens.len());
+        let features = match options.classifier_model.as_deref() {
+            Some(model) => {
+                langur_classifier::features_in(&tokens, range.clone(), model.ngram_order)
+            }
+            None => Vec::new(),
+        };
+        let token_indices = BUILT_IN_MODEL.token_indices(
+            langur_classifier::features_in(&tokens, range.clone(), BUILT_IN_MODEL.ngram_order())
+                .iter()
+                .map(AsRef::as_ref),
+        );
+        score_batch(&features, &token_indices);
+
+        self.scored += range.len();
+        self.tokens.drain(range);
+        Ok(())
+    }
+}
+
+fn content_tokens<'a>(
+    content: &'a str,
+    dialect: Option<&'static Dialect>,
+) -> Box<dyn Iterator<Item = &'a str> + 'a> {
+    match dialect {
+        Some(dialect) => Box::new(langur_classifier::tokens_with_dialect(content, dialect)),
+        None => Box::new(langur_classifier::tokens(content)),
+    }
+}
+
+/// The dialect a candidate's tokens are scored with, see
+/// [`DetectOptions::tokenizer_dialects`].
+fn dialect(language: Language, options: &DetectOptions) -> Option<&'static Dialect> {
+    if options.tokenizer_dialects {
+        Dialect::for_language(language.name())
+    } else {
+        None
+    }
+}
+
+/// Picks the most likely candidate, using the custom model from `options`
+/// if there is one, see [`ModelMode`].
+///
 /// Pre-condition: !candidates.is_empty()
-pub(crate) fn classify(content: &str, candidates: &[Language]) -> Language {
+pub(crate) fn classify(
+    content: &str,
+    candidates: &[Language],
+    options: &DetectOptions,
+) -> (Language, ClassifierTokens) {
     assert!(
         !candidates.is_empty(),
         "classify requires 1 or more candidates"
     );
 
-    let tokens: Vec<_> = langur_tokenizer::get_key_tokens(content)
-        .filter(|token| token.len() <= MAX_TOKEN_BYTES)
+    let (ranked, tokens) = rank(content, candidates, options);
+    (ranked[0].0, tokens)
+}
+
+/// Whether the candidates can be classified with [`classify_reader`] or
+/// [`rank_reader`], which read the content with a single
+/// tokenizer dialect.
+pub(crate) fn can_read_incrementally(candidates: &[Language], options: &DetectOptions) -> bool {
+    match candidates.split_first() {
+        Some((first, rest)) => {
+            let first = dialect(*first, options);
+            rest.iter()
+                .all(|&language| dialect(language, options) == first)
+        }
+        None => false,
+    }
+}
+
+/// Same as [`classify`], but tokenizes the content as it's read from
+/// `reader`, so that nothing past the tokens scored is read when the
+/// classifier stops early, see [`DetectOptions::classifier_margin`].
+///
+/// Pre-condition: [`can_read_incrementally`]
+pub(crate) fn classify_reader<R: BufRead>(
+    reader: R,
+    candidates: &[Language],
+    options: &DetectOptions,
+) -> io::Result<(Language, ClassifierTokens)> {
+    let (scored_candidates, tokens) = score_candidates_from_reader(reader, candidates, options)?;
+    Ok((scored_candidates[0].language, tokens))
+}
+
+/// Same as [`rank`], but reads the content like [`classify_reader`].
+pub(crate) fn rank_reader<R: BufRead>(
+    reader: R,
+    candidates: &[Language],
+    options: &DetectOptions,
+) -> io::Result<(Vec<(Language, f64)>, ClassifierTokens)> {
+    let (scored_candidates, tokens) = score_candidates_from_reader(reader, candidates, options)?;
+    Ok((ranked(scored_candidates), tokens))
+}
+
+/// Scores every candidate like [`classify`] and returns them with their
+/// log-probabilities, most likely first. Candidates that no model knows
+/// about get a score of negative infinity.
+pub(crate) fn rank(
+    content: &str,
+    candidates: &[Language],
+    options: &DetectOptions,
+) -> (Vec<(Language, f64)>, ClassifierTokens) {
+    let (scored_candidates, tokens) = score_candidates(content, candidates, options);
+    (ranked(scored_candidates), tokens)
+}
+
+fn ranked(scored_candidates: Vec<LanguageScore>) -> Vec<(Language, f64)> {
+    scored_candidates
+        .into_iter()
+        .map(|candidate| (candidate.language, candidate.score))
+        .collect()
+}
+
+/// Same as [`rank`], but also returns the features that favored the best
+/// candidate over the runner-up the most, see
+/// [`Explanation::influential_tokens`](crate::Explanation::influential_tokens).
+pub(crate) fn rank_explained(
+    content: &str,
+    candidates: &[Language],
+    options: &DetectOptions,
+) -> (Vec<(Language, f64)>, ClassifierTokens, Vec<TokenInfluence>) {
+    let (ranked, tokens) = rank(content, candidates, options);
+    let influential_tokens = match ranked.as_slice() {
+        [(best, best_score), (runner_up, runner_up_score), ..]
+            if best_score.is_finite() && runner_up_score.is_finite() =>
+        {
+            influential_tokens(content, *best, *runner_up, tokens.scored, options)
+        }
+        _ => vec![],
+    };
+    (ranked, tokens, influential_tokens)
+}
+
+/// Sums up how much each feature in the scored tokens adds to the score of
+/// `best` compared to `runner_up`, and returns the features that add the
+/// most. The features are the ones scored for `best`, so this is only
+/// approximate if the runner-up is scored by a model with a different
+/// n-gram order or on tokens from another dialect.
+fn influential_tokens(
+    content: &str,
+    best: Language,
+    runner_up: Language,
+    scored: usize,
+    options: &DetectOptions,
+) -> Vec<TokenInfluence> {
+    let dialect = dialect(best, options);
+    let ngram_order = match options.classifier_model.as_deref() {
+        Some(model) if model.languages.contains_key(best.name()) => model.ngram_order,
+        _ => BUILT_IN_MODEL.ngram_order(),
+    };
+    let tokens: Vec<&str> = content_tokens(content, dialect)
+        .take(scored + ngram_order - 1)
         .collect();
+    let range = 0..scored.min(tokens.len());
+    let mut counts: HashMap<Cow<str>, usize> = HashMap::new();
+    for feature in langur_classifier::features_in(&tokens, range, ngram_order) {
+        *counts.entry(feature).or_insert(0) += 1;
+    }
 
-    let mut scored_candidates: Vec<LanguageScore> = candidates
+    let best_log_prior = score(best, &[], &[], options);
+    let runner_up_log_prior = score(runner_up, &[], &[], options);
+    let mut influential: Vec<TokenInfluence> = counts
+        .into_iter()
+        .map(|(feature, count)| {
+            let features = vec![feature];
+            let token_indices = BUILT_IN_MODEL.token_indices(features.iter().map(AsRef::as_ref));
+            let best_log_prob = score(best, &features, &token_indices, options) - best_log_prior;
+            let runner_up_log_prob =
+                score(runner_up, &features, &token_indices, options) - runner_up_log_prior;
+            TokenInfluence {
+                token: features[0].to_string(),
+                count,
+                influence: count as f64 * (best_log_prob - runner_up_log_prob),
+            }
+        })
+        .filter(|token| token.influence > 0.0)
+        .collect();
+    influential.sort_by(|a, b| {
+        b.influence
+            .partial_cmp(&a.influence)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.token.cmp(&b.token))
+    });
+    influential.truncate(INFLUENTIAL_TOKENS);
+    influential
+}
+
+/// Scores the candidates, most likely first. With
+/// [`DetectOptions::tokenizer_dialects`], each candidate is scored on the
+/// tokens from its own dialect.
+///
+/// With [`DetectOptions::classifier_margin`], the tokens are scored in
+/// batches, and scoring stops after the batch where the best candidate's
+/// lead over the runner-up exceeds the margin.
+fn score_candidates(
+    content: &str,
+    candidates: &[Language],
+    options: &DetectOptions,
+) -> (Vec<LanguageScore>, ClassifierTokens) {
+    let mut tokenized: Vec<Tokenized> = Vec::new();
+    let candidate_tokens: Vec<usize> = candidates
         .iter()
         .map(|&language| {
-            let score = match TOKEN_LOG_PROBABILITIES.get(&language) {
-                Some(token_map) => tokens
-                    .iter()
-                    .map(|token| token_map.get(*token).copied().unwrap_or(DEFAULT_LOG_PROB))
-                    .sum(),
-                None => std::f64::NEG_INFINITY,
-            };
-            LanguageScore { language, score }
+            let dialect = dialect(language, options);
+            match tokenized.iter().position(|t| t.dialect == dialect) {
+                Some(index) => index,
+                None => {
+                    tokenized.push(Tokenized::new(content, dialect));
+                    tokenized.len() - 1
+                }
+            }
         })
         .collect();
+    score_tokenized(tokenized, &candidate_tokens, candidates, options)
+        .expect("tokenizing content in memory doesn't fail")
+}
+
+/// Same as [`score_candidates`], for the content read from `reader`.
+///
+/// Pre-condition: [`can_read_incrementally`]
+fn score_candidates_from_reader<R: BufRead>(
+    reader: R,
+    candidates: &[Language],
+    options: &DetectOptions,
+) -> io::Result<(Vec<LanguageScore>, ClassifierTokens)> {
+    let tokenized = vec![Tokenized::from_reader(
+        reader,
+        dialect(candidates[0], options),
+    )];
+    score_tokenized(tokenized, &vec![0; candidates.len()], candidates, options)
+}
+
+/// Scores the candidates on the tokens in `tokenized`, where
+/// `candidate_tokens` has the index of each candidate's tokens.
+fn score_tokenized(
+    mut tokenized: Vec<Tokenized>,
+    candidate_tokens: &[usize],
+    candidates: &[Language],
+    options: &DetectOptions,
+) -> io::Result<(Vec<LanguageScore>, ClassifierTokens)> {
+    let log_priors: Vec<f64> = candidates
+        .iter()
+        .map(|&language| score(language, &[], &[], options))
+        .collect();
+    let mut log_likelihoods = vec![0.0; candidates.len()];
+
+    // Log-likelihoods of different numbers of tokens aren't comparable, so
+    // with dialects they're scaled to the largest number of tokens scored.
+    let scaled_scores = |tokenized: &[Tokenized], log_likelihoods: &[f64]| -> Vec<f64> {
+        let scored = tokenized.iter().map(|t| t.scored).max().unwrap_or(0);
+        (0..candidates.len())
+            .map(|candidate| {
+                let log_prior = log_priors[candidate];
+                if !log_prior.is_finite() {
+                    return log_prior;
+                }
+                let tokens = tokenized[candidate_tokens[candidate]].scored;
+                let scale = scored as f64 / tokens.max(1) as f64;
+                log_prior + log_likelihoods[candidate] * scale
+            })
+            .collect()
+    };
 
+    let batch_size = match options.classifier_margin {
+        Some(_) => MARGIN_BATCH_TOKENS,
+        None => usize::MAX,
+    };
+    while !tokenized.iter().all(Tokenized::is_done) {
+        for (index, tokenized) in tokenized.iter_mut().enumerate() {
+            if tokenized.is_done() {
+                continue;
+            }
+            tokenized.next_batch(batch_size, options, |features, token_indices| {
+                for (candidate, &language) in candidates.iter().enumerate() {
+                    if candidate_tokens[candidate] == index && log_priors[candidate].is_finite() {
+                        log_likelihoods[candidate] +=
+                            score(language, features, token_indices, options)
+                                - log_priors[candidate];
+                    }
+                }
+            })?;
+        }
+
+        if let Some(margin) = options.classifier_margin {
+            if lead(&scaled_scores(&tokenized, &log_likelihoods)) > margin {
+                break;
+            }
+        }
+    }
+
+    let scored = tokenized.iter().map(|t| t.scored).max().unwrap_or(0);
+    let mut scored_candidates: Vec<LanguageScore> = candidates
+        .iter()
+        .zip(scaled_scores(&tokenized, &log_likelihoods))
+        .map(|(&language, score)| LanguageScore { language, score })
+        .collect();
     scored_candidates.sort_by(|a, b| {
         b.score
             .partial_cmp(&a.score)
             .unwrap_or(std::cmp::Ordering::Equal)
     });
 
-    scored_candidates[0].language
+    let total = if tokenized.iter().all(Tokenized::is_done) {
+        Some(scored)
+    } else {
+        None
+    };
+    let tokens = ClassifierTokens { scored, total };
+    Ok((scored_candidates, tokens))
+}
+
+/// How far the best score is ahead of the second best, or infinity if only
+/// one candidate can be picked at all.
+fn lead(scores: &[f64]) -> f64 {
+    let mut best = f64::NEG_INFINITY;
+    let mut second = f64::NEG_INFINITY;
+    for &score in scores {
+        if score > best {
+            second = best;
+            best = score;
+        } else if score > second {
+            second = score;
+        }
+    }
+    if best.is_finite() && second == f64::NEG_INFINITY {
+        f64::INFINITY
+    } else {
+        best - second
+    }
+}
+
+/// Scores a language with the custom model from `options` if it knows the
+/// language, and with the built-in model otherwise, see [`ModelMode`].
+fn score(
+    language: Language,
+    features: &[Cow<str>],
+    token_indices: &[Option<u32>],
+    options: &DetectOptions,
+) -> f64 {
+    let custom_score = options
+        .classifier_model
+        .as_deref()
+        .map(|model| score_with_model(model, language, features));
+    match (custom_score, options.classifier_model_mode) {
+        (Some(Some(score)), _) => score,
+        (Some(None), ModelMode::Override) => f64::NEG_INFINITY,
+        (Some(None), ModelMode::Extend) | (None, _) => {
+            score_with_built_in_model(language, token_indices)
+        }
+    }
+}
+
+fn score_with_built_in_model(language: Language, token_indices: &[Option<u32>]) -> f64 {
+    BUILT_IN_MODEL
+        .score(language.name(), token_indices)
+        .unwrap_or(f64::NEG_INFINITY)
+}
+
+/// Returns None if `model` doesn't know about `language`.
+fn score_with_model(
+    model: &langur_classifier::Model,
+    language: Language,
+    features: &[Cow<str>],
+) -> Option<f64> {
+    model
+        .languages
+        .get(language.name())
+        .map(|model| model.score(features.iter().map(AsRef::as_ref)))
 }
 
 #[cfg(test)]
@@ -64,18 +509,18 @@ mod tests {
     fn test_classify() {
         let content = fs::read_to_string(linguist_path("samples/Rust/main.rs")).unwrap();
         let candidates = &[ids::C, ids::Rust];
-        let language = classify(content.as_str(), candidates);
+        let language = classify(content.as_str(), candidates, &DetectOptions::default()).0;
         assert_eq!(language, ids::Rust);
 
         let content =
             fs::read_to_string(linguist_path("samples/Erlang/170-os-daemons.es")).unwrap();
         let candidates = &[ids::Erlang, ids::JavaScript];
-        let language = classify(content.as_str(), candidates);
+        let language = classify(content.as_str(), candidates, &DetectOptions::default()).0;
         assert_eq!(language, ids::Erlang);
 
         let content = fs::read_to_string(linguist_path("samples/TypeScript/classes.ts")).unwrap();
         let candidates = &[ids::Cpp, ids::Java, ids::CSharp, ids::TypeScript];
-        let language = classify(content.as_str(), candidates);
+        let language = classify(content.as_str(), candidates, &DetectOptions::default()).0;
         assert_eq!(language, ids::TypeScript);
     }
 
@@ -90,7 +535,7 @@ mod tests {
 
     imp(args)"#;
         let candidates = &[ids::Rust, ids::RenderScript];
-        let language = classify(sample, candidates);
+        let language = classify(sample, candidates, &DetectOptions::default()).0;
         assert_eq!(language, ids::Rust);
     }
 
@@ -98,9 +543,12 @@ mod tests {
     fn test_classify_empty_and_all_candidates() {
         let content = fs::read_to_string(linguist_path("samples/Rust/main.rs")).unwrap();
         let candidates = &[];
-        assert!(std::panic::catch_unwind(|| { classify(content.as_str(), candidates) }).is_err());
+        assert!(std::panic::catch_unwind(|| {
+            classify(content.as_str(), candidates, &DetectOptions::default()).0
+        })
+        .is_err());
         let candidates = Language::VARIANTS;
-        let language = classify(content.as_str(), candidates);
+        let language = classify(content.as_str(), candidates, &DetectOptions::default()).0;
         assert_eq!(language, ids::Rust);
     }
 
@@ -108,7 +556,124 @@ mod tests {
     fn test_classify_f_star() {
         let content = fs::read_to_string(linguist_path("samples/Fstar/Hacl.HKDF.fst")).unwrap();
         let candidates = Language::VARIANTS;
-        let language = classify(content.as_str(), candidates);
+        let language = classify(content.as_str(), candidates, &DetectOptions::default()).0;
         assert_eq!(language, ids::Fstar);
     }
+
+    #[test]
+    fn test_classify_model_modes() {
+        // The custom model doesn't know any of the candidates.
+        let mut corpus = langur_classifier::Corpus::new();
+        corpus.add_sample("In-House DSL", "rule main { emit x }");
+        let content = "rule main { emit x }";
+        let candidates = &[ids::C, ids::Rust];
+
+        let mut options = DetectOptions {
+            classifier_model: Some(std::sync::Arc::new(corpus.train(&Default::default()))),
+            classifier_model_mode: ModelMode::Extend,
+            ..Default::default()
+        };
+        assert_eq!(
+            classify(content, candidates, &options).0,
+            classify(content, candidates, &DetectOptions::default()).0
+        );
+
+        // Every candidate is ruled out, so the first one wins the tie.
+        options.classifier_model_mode = ModelMode::Override;
+        assert_eq!(classify(content, candidates, &options).0, ids::C);
+    }
+
+    #[test]
+    fn test_classify_with_ngram_model() {
+        // The same tokens, in an order that only bigrams tell apart.
+        let mut corpus = langur_classifier::Corpus::new().with_ngram_order(2);
+        corpus.add_sample("C++", "std::string s;");
+        corpus.add_sample("C", "s: std; string:");
+        let options = DetectOptions {
+            classifier_model: Some(std::sync::Arc::new(corpus.train(&Default::default()))),
+            classifier_model_mode: ModelMode::Override,
+            ..Default::default()
+        };
+        assert_eq!(classify("x::y", &[ids::C, ids::Cpp], &options).0, ids::Cpp);
+    }
+
+    #[test]
+    fn test_classify_with_dialects() {
+        let mut corpus = langur_classifier::Corpus::with_dialects();
+        corpus.add_sample("C", "#include <stdio.h>\n#define N 1");
+        corpus.add_sample("Python", "import os\nprint(os.name)");
+        let content = "#include <stdio.h>\nint main;";
+        let candidates = &[ids::Python, ids::C];
+
+        let mut options = DetectOptions {
+            classifier_model: Some(std::sync::Arc::new(corpus.train(&Default::default()))),
+            classifier_model_mode: ModelMode::Override,
+            tokenizer_dialects: true,
+            ..Default::default()
+        };
+        assert_eq!(classify(content, candidates, &options).0, ids::C);
+
+        // Without dialects, the directive is dropped as a comment.
+        options.tokenizer_dialects = false;
+        assert_eq!(classify(content, candidates, &options).0, ids::Python);
+    }
+
+    #[test]
+    fn test_classify_with_margin() {
+        let mut corpus = langur_classifier::Corpus::new();
+        corpus.add_sample("C", "int main(void) { return 0; }");
+        corpus.add_sample("Python", "def main():\n    return 0");
+        let content = "def f():\n    return 0\n".repeat(200);
+        let candidates = &[ids::C, ids::Python];
+
+        let mut options = DetectOptions {
+            classifier_model: Some(std::sync::Arc::new(corpus.train(&Default::default()))),
+            classifier_model_mode: ModelMode::Override,
+            ..Default::default()
+        };
+        let (language, tokens) = classify(&content, candidates, &options);
+        assert_eq!(language, ids::Python);
+        assert_eq!(tokens.total, Some(tokens.scored));
+
+        options.classifier_margin = Some(5.0);
+        let (language, tokens) = classify(&content, candidates, &options);
+        assert_eq!(language, ids::Python);
+        assert_eq!(tokens.scored, MARGIN_BATCH_TOKENS);
+        assert_eq!(tokens.total, None);
+
+        // The content repeats, so the scores per token are about the same.
+        let (all, all_tokens) = rank(
+            &content,
+            candidates,
+            &DetectOptions {
+                classifier_margin: None,
+                ..options.clone()
+            },
+        );
+        let (early, early_tokens) = rank(&content, candidates, &options);
+        let per_token = |score: f64, tokens: ClassifierTokens| score / tokens.scored as f64;
+        assert!(
+            (per_token(all[0].1, all_tokens) - per_token(early[0].1, early_tokens)).abs() < 0.1
+        );
+
+        // Reading the content as it goes gives the same result.
+        let (language, tokens) = classify_reader(content.as_bytes(), candidates, &options).unwrap();
+        assert_eq!(language, ids::Python);
+        assert_eq!(tokens.scored, MARGIN_BATCH_TOKENS);
+        let (all_from_reader, _) = rank_reader(
+            content.as_bytes(),
+            candidates,
+            &DetectOptions {
+                classifier_margin: None,
+                ..options.clone()
+            },
+        )
+        .unwrap();
+        assert_eq!(all_from_reader, all);
+
+        // An infinite margin is never exceeded.
+        options.classifier_margin = Some(f64::INFINITY);
+        let (_, tokens) = classify(&content, candidates, &options);
+        assert_eq!(tokens.total, Some(tokens.scored));
+    }
 }
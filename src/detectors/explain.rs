@@ -0,0 +1,121 @@
+use crate::{ClassifierTokens, Detection, Language};
+
+/// How [`detect_explain`](crate::detect_explain) got to its detection, for
+/// figuring out why a file was misdetected.
+#[non_exhaustive]
+#[derive(Debug, Clone, Default)]
+pub struct Explanation {
+    /// The stages that ran, in order. Detection stops at the first stage
+    /// that leaves a single candidate.
+    pub stages: Vec<StageExplanation>,
+    /// The extension the languages were looked up by, like `.h`.
+    pub extension: Option<String>,
+    /// The interpreter parsed from the shebang, after following `env`,
+    /// `nix-shell` and trampolines, and without a version that isn't known,
+    /// like `python3` for `#!/usr/bin/python3.11`.
+    pub interpreter: Option<String>,
+    /// The heuristic rules checked, in order. Only the last one can have
+    /// matched.
+    pub heuristic_rules: Vec<HeuristicRule>,
+    /// The log-probabilities of the candidates, most likely first, if the
+    /// classifier picked the language.
+    pub classifier_scores: Vec<(Language, f64)>,
+    /// The features of the content that favored the classifier's pick over
+    /// the runner-up the most, most influential first.
+    pub influential_tokens: Vec<TokenInfluence>,
+    /// How many tokens the classifier scored, if it was used.
+    pub classifier_tokens: Option<ClassifierTokens>,
+    /// The same detection as [`detect_with_options`](crate::detect_with_options).
+    pub detection: Option<Detection>,
+}
+
+/// What a stage of the detection found, see [`Explanation::stages`].
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StageExplanation {
+    pub stage: Stage,
+    /// The languages the stage found on its own, leaving out the ones that
+    /// aren't allowed by the options.
+    pub languages: Vec<Language>,
+    /// The candidates left after the stage. Languages that contradict the
+    /// earlier stages are ignored, unless none of them agree.
+    pub candidates: Vec<Language>,
+}
+
+/// A stage of the detection that narrows down the candidates.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Stage {
+    Filename,
+    Extension,
+    Shebang,
+    Heuristics,
+}
+
+impl Stage {
+    /// The name of the stage, like [`Detection::variant`].
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::Filename => "Filename",
+            Stage::Extension => "Extension",
+            Stage::Shebang => "Shebang",
+            Stage::Heuristics => "Heuristics",
+        }
+    }
+}
+
+/// A heuristic rule that was checked, see [`Explanation::heuristic_rules`].
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeuristicRule {
+    /// What the rule is looked up by: an extension like `.h`, or
+    /// `filename:HOSTS` or `interpreter:parrot`.
+    pub key: String,
+    /// Whether the rule was added to a
+    /// [`LanguageRegistry`](crate::LanguageRegistry) rather than built in.
+    pub custom: bool,
+    pub languages: Vec<Language>,
+    /// The regexes of the rule, like `/^#include/ && !/^@interface/`, or
+    /// None if the rule always matches.
+    pub pattern: Option<String>,
+    pub outcome: RuleOutcome,
+}
+
+/// Whether a [`HeuristicRule`] matched.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RuleOutcome {
+    Matched,
+    NotMatched,
+    /// Some of the rule's languages aren't candidates, so its pattern
+    /// wasn't checked.
+    Skipped,
+}
+
+/// A feature of the content and how much it moved the classifier towards
+/// its pick, see [`Explanation::influential_tokens`].
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenInfluence {
+    /// The token, or a run of tokens joined by spaces for models with
+    /// n-grams.
+    pub token: String,
+    /// How many times the feature was scored.
+    pub count: usize,
+    /// How many nats all the occurrences added to the lead of the pick over
+    /// the runner-up.
+    pub influence: f64,
+}
+
+impl Explanation {
+    pub(crate) fn push_stage(
+        &mut self,
+        stage: Stage,
+        languages: &[Language],
+        candidates: &[Language],
+    ) {
+        self.stages.push(StageExplanation {
+            stage,
+            languages: languages.to_vec(),
+            candidates: candidates.to_vec(),
+        });
+    }
+}
@@ -2,20 +2,19 @@
 // static EXTENSIONS: phf::Map<&'static str, &[&str]> = ...;
 include!("../generated/extension_language_map.rs");
 
-use crate::Language;
+use crate::{registry::merge_languages, Language, LanguageRegistry};
 
-pub(crate) fn get_languages_from_extension(extension: &str) -> Vec<Language> {
-    let languages = EXTENSIONS
-        .get(extension)
-        .map(|languages| languages.to_vec());
-
-    match languages {
-        Some(languages) => languages,
-        None => vec![],
-    }
+pub(crate) fn get_languages_from_extension(
+    extension: &str,
+    registry: &LanguageRegistry,
+) -> Vec<Language> {
+    let built_in = EXTENSIONS.get(extension).copied().unwrap_or_default();
+    merge_languages(built_in, registry.extension_languages(extension))
 }
 
-pub(crate) fn get_extension(filename: &str) -> Option<&'static str> {
+/// Finds the longest extension of `filename` that is either built-in or
+/// in `registry`.
+pub(crate) fn get_extension<'r>(filename: &str, registry: &'r LanguageRegistry) -> Option<&'r str> {
     let filename = if let Some(filename_no_dot) = filename.strip_prefix('.') {
         filename_no_dot
     } else {
@@ -28,6 +27,9 @@ pub(crate) fn get_extension(filename: &str) -> Option<&'static str> {
             if let Some(extension) = EXTENSIONS.get_key(&filename[pos..]) {
                 return Some(extension);
             };
+            if let Some(extension) = registry.extension_key(&filename[pos..]) {
+                return Some(extension);
+            };
         };
     }
     None
@@ -40,24 +42,52 @@ mod tests {
 
     #[test]
     fn test_get_languages_from_extension() {
-        assert_eq!(get_languages_from_extension(".djs"), vec![ids::Dogescript]);
-        assert_eq!(get_languages_from_extension(".cmake.in"), vec![ids::CMake]);
+        assert_eq!(
+            get_languages_from_extension(".djs", &LanguageRegistry::default()),
+            vec![ids::Dogescript]
+        );
+        assert_eq!(
+            get_languages_from_extension(".cmake.in", &LanguageRegistry::default()),
+            vec![ids::CMake]
+        );
 
-        let mut header_file_langs = get_languages_from_extension(".h");
+        let mut header_file_langs =
+            get_languages_from_extension(".h", &LanguageRegistry::default());
         header_file_langs.sort();
         assert_eq!(header_file_langs, vec![ids::C, ids::Cpp, ids::Objective_C]);
 
-        assert_eq!(get_languages_from_extension(""), vec![]);
+        assert_eq!(
+            get_languages_from_extension("", &LanguageRegistry::default()),
+            vec![]
+        );
     }
 
     #[test]
     fn test_get_extension() {
-        assert_eq!(get_extension("index.djs"), Some(".djs"));
-        assert_eq!(get_extension("example.cmake.in"), Some(".cmake.in"));
-        assert_eq!(get_extension("nonsense.notrealextension.c"), Some(".c"));
-        assert_eq!(get_extension("uppercase.C"), Some(".c"));
-        assert_eq!(get_extension(".eslintrc.json"), Some(".json"));
-        assert_eq!(get_extension(".cs"), None);
-        assert_eq!(get_extension("noextension"), None);
+        assert_eq!(
+            get_extension("index.djs", &LanguageRegistry::default()),
+            Some(".djs")
+        );
+        assert_eq!(
+            get_extension("example.cmake.in", &LanguageRegistry::default()),
+            Some(".cmake.in")
+        );
+        assert_eq!(
+            get_extension("nonsense.notrealextension.c", &LanguageRegistry::default()),
+            Some(".c")
+        );
+        assert_eq!(
+            get_extension("uppercase.C", &LanguageRegistry::default()),
+            Some(".c")
+        );
+        assert_eq!(
+            get_extension(".eslintrc.json", &LanguageRegistry::default()),
+            Some(".json")
+        );
+        assert_eq!(get_extension(".cs", &LanguageRegistry::default()), None);
+        assert_eq!(
+            get_extension("noextension", &LanguageRegistry::default()),
+            None
+        );
     }
 }
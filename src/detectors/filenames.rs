@@ -1,30 +1,185 @@
+use std::path::Path;
+
 // Include the map from filenames to languages at compile time
 // static FILENAME_TO_LANGUAGE_MAP: phf::Map<&'static str, &'static str> = ...;
 include!("../generated/filename_language_map.rs");
 
-pub(crate) fn get_language_from_filename(filename: &str) -> Option<crate::Language> {
-    if let Some(slice) = FILENAME_TO_LANGUAGE_MAP.get(filename) {
-        if slice.len() == 1 {
-            return Some(slice[0]);
+// Include the fallbacks for filenames that don't match exactly
+// static FOLDED_FILENAME_TO_LANGUAGE_MAP: phf::Map<&'static str, &[crate::Language]> = ...;
+// static FILENAME_PATTERNS: &[(&str, &[crate::Language])] = ...;
+include!("../generated/filename_pattern_map.rs");
+
+use super::extensions;
+use crate::{registry::merge_languages, Language, LanguageRegistry};
+
+/// Returns the languages that a file's name or path says it's in, which can
+/// be several like for `HOSTS`, or none.
+///
+/// An exact filename wins, then a filename that only differs by case, like
+/// `dockerfile`, then the first path pattern that matches, like
+/// `Dockerfile.prod` or `.github/workflows/*.yml`. The registry's filenames
+/// and patterns are checked along with the built-in ones, its patterns
+/// first. A pattern ending in `*` doesn't match files with a known
+/// extension, so `Makefile.toml` is left to the `.toml` extension.
+pub(crate) fn get_languages_from_path(
+    path: &Path,
+    filename: &str,
+    registry: &LanguageRegistry,
+) -> Vec<Language> {
+    let built_in = FILENAME_TO_LANGUAGE_MAP
+        .get(filename)
+        .copied()
+        .unwrap_or_default();
+    let languages = merge_languages(built_in, registry.filename_languages(filename));
+    if !languages.is_empty() {
+        return languages;
+    }
+
+    let folded = filename.to_lowercase();
+    let built_in = FOLDED_FILENAME_TO_LANGUAGE_MAP
+        .get(folded.as_str())
+        .copied()
+        .unwrap_or_default();
+    let languages = merge_languages(built_in, registry.folded_filename_languages(&folded));
+    if !languages.is_empty() {
+        return languages;
+    }
+
+    let has_extension = extensions::get_extension(filename, registry).is_some();
+    let path = path.to_string_lossy().to_lowercase();
+    registry
+        .filename_patterns()
+        .iter()
+        .map(|(pattern, languages)| (pattern.as_str(), languages.as_slice()))
+        .chain(FILENAME_PATTERNS.iter().copied())
+        .filter(|(pattern, _)| !(has_extension && pattern.ends_with('*')))
+        .find(|(pattern, _)| matches_pattern(&path, pattern))
+        .map(|(_, languages)| languages.to_vec())
+        .unwrap_or_default()
+}
+
+/// Whether the last components of `path` match those of `pattern`, where
+/// `*` matches anything but `/`.
+fn matches_pattern(path: &str, pattern: &str) -> bool {
+    let mut components = path.rsplit(&['/', '\\'][..]);
+    pattern.rsplit('/').all(|pattern| match components.next() {
+        Some(component) => matches_glob(component, pattern),
+        None => false,
+    })
+}
+
+fn matches_glob(component: &str, pattern: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match component.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let parts: Vec<&str> = parts.collect();
+    match parts.split_last() {
+        // No `*`.
+        None => rest.is_empty(),
+        Some((last, middle)) => {
+            for part in middle {
+                match rest.find(part) {
+                    Some(start) => rest = &rest[start + part.len()..],
+                    None => return false,
+                }
+            }
+            rest.ends_with(last)
         }
     }
-    None
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::ids;
+    use crate::{ids, CustomLanguage, LanguageType};
+
+    fn languages(path: &str) -> Vec<Language> {
+        let path = Path::new(path);
+        let filename = path.file_name().unwrap().to_str().unwrap();
+        get_languages_from_path(path, filename, &LanguageRegistry::default())
+    }
+
+    #[test]
+    fn test_get_languages_from_path() {
+        assert_eq!(languages("APKBUILD"), vec![ids::Alpine_Abuild]);
+        assert_eq!(languages(".eslintrc.json"), vec![ids::JSON_with_Comments]);
+        assert_eq!(languages("src/BUILD.bazel"), vec![ids::Starlark]);
+        assert_eq!(languages("main.rs"), vec![]);
+    }
 
     #[test]
-    fn test_get_language_from_filename() {
+    fn test_get_languages_from_path_case_insensitive() {
+        assert_eq!(languages("dockerfile"), vec![ids::Dockerfile]);
+        assert_eq!(languages("cmakelists.txt"), vec![ids::CMake]);
+        // All-uppercase names are only matched exactly.
+        assert_eq!(languages("install"), vec![]);
+    }
+
+    #[test]
+    fn test_get_languages_from_path_patterns() {
+        assert_eq!(languages("Dockerfile.prod"), vec![ids::Dockerfile]);
+        assert_eq!(languages("docker/Dockerfile-dev"), vec![ids::Dockerfile]);
+        assert_eq!(languages("Jenkinsfile.release"), vec![ids::Groovy]);
+        assert_eq!(languages("third_party/zlib.BUILD"), vec![ids::Starlark]);
         assert_eq!(
-            get_language_from_filename("APKBUILD"),
-            Some(ids::Alpine_Abuild)
+            languages("python/requirements/dev.txt"),
+            vec![ids::Pip_Requirements]
         );
+        assert_eq!(languages(".github/workflows/ci.yml"), vec![ids::YAML]);
+        assert_eq!(languages("./.GitHub/Workflows/CI.yaml"), vec![ids::YAML]);
+        assert_eq!(languages("workflows/ci.yml"), vec![]);
+        assert_eq!(languages("notes/dev.txt"), vec![]);
+    }
+
+    #[test]
+    fn test_get_languages_from_path_patterns_known_extension() {
+        // The extension is more specific than the pattern.
+        assert_eq!(languages("Makefile.toml"), vec![]);
+        assert_eq!(languages("Dockerfile.md"), vec![]);
+        assert_eq!(languages("Dockerfile-dev.yml"), vec![]);
+        // Unless the pattern spells out the extension.
         assert_eq!(
-            get_language_from_filename(".eslintrc.json"),
-            Some(ids::JSON_with_Comments)
+            languages("requirements-dev.txt"),
+            vec![ids::Pip_Requirements]
         );
     }
+
+    #[test]
+    fn test_get_languages_from_path_registry() {
+        let mut registry = LanguageRegistry::new();
+        let mut language = CustomLanguage::new("Gadget Rules", LanguageType::Data);
+        language.filenames = vec!["Gadgetfile".to_owned(), "Gadgetfile.*".to_owned()];
+        let gadget_rules = registry.add_language(language).unwrap();
+        let languages = |path: &str| {
+            let path = Path::new(path);
+            let filename = path.file_name().unwrap().to_str().unwrap();
+            get_languages_from_path(path, filename, &registry)
+        };
+
+        assert_eq!(languages("Gadgetfile"), vec![gadget_rules]);
+        assert_eq!(languages("gadgetfile"), vec![gadget_rules]);
+        assert_eq!(languages("src/Gadgetfile.prod"), vec![gadget_rules]);
+        assert_eq!(languages("Gadgetfile.json"), vec![]);
+        assert_eq!(languages("Dockerfile.prod"), vec![ids::Dockerfile]);
+    }
+
+    #[test]
+    fn test_get_languages_from_path_multiple() {
+        let mut hosts = languages("HOSTS");
+        hosts.sort();
+        assert_eq!(hosts, vec![ids::INI, ids::Hosts_File]);
+    }
+
+    #[test]
+    fn test_matches_glob() {
+        assert!(matches_glob("dockerfile.prod", "dockerfile.*"));
+        assert!(!matches_glob("dockerfile", "dockerfile.*"));
+        assert!(matches_glob("a.b.c", "*.b.*"));
+        assert!(!matches_glob("ab", "a*b*b"));
+        assert!(matches_glob("x", "x"));
+        assert!(!matches_glob("xy", "x"));
+    }
 }
@@ -1,6 +1,15 @@
-use pcre2::bytes::RegexBuilder as PCRERegex;
+use std::borrow::Cow;
 
-// Include the map from interpreters to languages at compile time
+#[cfg(all(feature = "fancy-regex", not(feature = "pcre2")))]
+use fancy_regex::Regex;
+#[cfg(feature = "pcre2")]
+use pcre2::bytes::{Regex, RegexBuilder as PCRERegex};
+
+#[cfg(not(any(feature = "pcre2", feature = "fancy-regex")))]
+compile_error!("heuristics need either the pcre2 or the fancy-regex feature");
+
+// Include the map from extensions, filenames and interpreters to rules at
+// compile time, see HeuristicsKey for the format of the keys
 // static DISAMBIGUATIONS: phf::Map<&'static str, &'static [Rule]> = ...;
 include!("../generated/disambiguation_heuristics_map.rs");
 
@@ -12,7 +21,33 @@ enum Pattern {
     Positive(&'static str),
 }
 
-use crate::{ids, Language};
+use super::explain::{HeuristicRule, RuleOutcome};
+use crate::{ids, registry::RegistryError, Language, LanguageRegistry};
+
+/// What the heuristics for a file are looked up by. Rules for all three
+/// live in the same tables, so filenames and interpreters are prefixed to
+/// keep them apart from extensions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HeuristicsKey<'a> {
+    /// A lowercase extension with its leading dot, like `.h`.
+    Extension(&'a str),
+    /// An exact filename, like `HOSTS`.
+    Filename(&'a str),
+    /// The interpreter in a shebang, like `parrot`.
+    Interpreter(&'a str),
+}
+
+impl<'a> HeuristicsKey<'a> {
+    pub(crate) fn table_key(&self) -> Cow<'a, str> {
+        match *self {
+            HeuristicsKey::Extension(extension) => Cow::Borrowed(extension),
+            HeuristicsKey::Filename(filename) => Cow::Owned(format!("filename:{}", filename)),
+            HeuristicsKey::Interpreter(interpreter) => {
+                Cow::Owned(format!("interpreter:{}", interpreter))
+            }
+        }
+    }
+}
 
 #[derive(Debug)]
 struct Rule {
@@ -21,56 +56,288 @@ struct Rule {
 }
 
 impl Pattern {
+    /// Patterns that the regex engine doesn't support never match,
+    /// see test_heuristics_built_in_patterns_compile.
     fn matches(&self, content: &str) -> bool {
         match self {
-            Pattern::Positive(pattern) => {
-                let regex = PCRERegex::new()
-                    .crlf(true)
-                    .multi_line(true)
-                    .build(pattern)
-                    .unwrap();
-                regex.is_match(content.as_bytes()).unwrap_or(false)
-            }
-            Pattern::Negative(pattern) => {
-                let regex = PCRERegex::new()
-                    .crlf(true)
-                    .multi_line(true)
-                    .build(pattern)
-                    .unwrap();
-                !regex.is_match(content.as_bytes()).unwrap_or(true)
-            }
+            Pattern::Positive(pattern) => build_regex(pattern)
+                .ok()
+                .and_then(|regex| is_match(&regex, content))
+                .unwrap_or(false),
+            Pattern::Negative(pattern) => !build_regex(pattern)
+                .ok()
+                .and_then(|regex| is_match(&regex, content))
+                .unwrap_or(true),
             Pattern::Or(patterns) => patterns.iter().any(|pattern| pattern.matches(content)),
             Pattern::And(patterns) => patterns.iter().all(|pattern| pattern.matches(content)),
         }
     }
+
+    /// Describes the pattern for explanations, like `/a/ && !/b/`, with an
+    /// `and` or an `or` in parentheses if it's `nested` in another one.
+    fn describe(&self, nested: bool) -> String {
+        match self {
+            Pattern::Positive(pattern) => format!("/{}/", pattern),
+            Pattern::Negative(pattern) => format!("!/{}/", pattern),
+            Pattern::Or(patterns) => {
+                describe_all(patterns.iter().map(|p| p.describe(true)), " || ", nested)
+            }
+            Pattern::And(patterns) => {
+                describe_all(patterns.iter().map(|p| p.describe(true)), " && ", nested)
+            }
+        }
+    }
+}
+
+/// Joins the descriptions of the patterns of an `and` or an `or`, in
+/// parentheses if it's part of another one.
+fn describe_all<I: Iterator<Item = String>>(patterns: I, separator: &str, nested: bool) -> String {
+    let joined = patterns.collect::<Vec<_>>().join(separator);
+    if nested {
+        format!("({})", joined)
+    } else {
+        joined
+    }
+}
+
+/// A heuristic added to a [`LanguageRegistry`] at runtime. Unlike the
+/// built-in patterns, the regexes are compiled when the rule is added.
+#[derive(Debug, Clone)]
+pub(crate) struct CustomRule {
+    pub(crate) languages: Vec<Language>,
+    pub(crate) pattern: Option<CustomPattern>,
+}
+
+impl CustomRule {
+    pub(crate) fn matches(&self, content: &str) -> bool {
+        match &self.pattern {
+            Some(pattern) => pattern.matches(content),
+            None => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum CustomPattern {
+    And(Vec<CustomPattern>),
+    Negative(Regex),
+    Or(Vec<CustomPattern>),
+    Positive(Regex),
+}
+
+impl CustomPattern {
+    pub(crate) fn positive(pattern: &str) -> Result<CustomPattern, RegistryError> {
+        Ok(CustomPattern::Positive(build_custom_regex(pattern)?))
+    }
+
+    pub(crate) fn negative(pattern: &str) -> Result<CustomPattern, RegistryError> {
+        Ok(CustomPattern::Negative(build_custom_regex(pattern)?))
+    }
+
+    fn matches(&self, content: &str) -> bool {
+        match self {
+            CustomPattern::Positive(regex) => is_match(regex, content).unwrap_or(false),
+            CustomPattern::Negative(regex) => !is_match(regex, content).unwrap_or(true),
+            CustomPattern::Or(patterns) => patterns.iter().any(|pattern| pattern.matches(content)),
+            CustomPattern::And(patterns) => patterns.iter().all(|pattern| pattern.matches(content)),
+        }
+    }
+
+    /// Same as [`Pattern::describe`].
+    fn describe(&self, nested: bool) -> String {
+        match self {
+            CustomPattern::Positive(regex) => format!("/{}/", regex_source(regex)),
+            CustomPattern::Negative(regex) => format!("!/{}/", regex_source(regex)),
+            CustomPattern::Or(patterns) => {
+                describe_all(patterns.iter().map(|p| p.describe(true)), " || ", nested)
+            }
+            CustomPattern::And(patterns) => {
+                describe_all(patterns.iter().map(|p| p.describe(true)), " && ", nested)
+            }
+        }
+    }
+}
+
+fn build_custom_regex(pattern: &str) -> Result<Regex, RegistryError> {
+    build_regex(pattern).map_err(|message| RegistryError::InvalidPattern {
+        pattern: pattern.to_owned(),
+        message,
+    })
+}
+
+/// Compiles a pattern from heuristics.yml, where `^` and `$` match at line
+/// breaks like in Ruby.
+#[cfg(feature = "pcre2")]
+fn build_regex(pattern: &str) -> Result<Regex, String> {
+    PCRERegex::new()
+        .crlf(true)
+        .multi_line(true)
+        .build(pattern)
+        .map_err(|e| e.to_string())
+}
+
+/// Returns None if matching fails, e.g. by hitting the backtracking limit.
+#[cfg(feature = "pcre2")]
+fn is_match(regex: &Regex, content: &str) -> Option<bool> {
+    regex.is_match(content.as_bytes()).ok()
+}
+
+#[cfg(feature = "pcre2")]
+fn regex_source(regex: &Regex) -> &str {
+    regex.as_str()
+}
+
+/// Nothing to do, since `crlf(true)` makes PCRE2 treat `\r\n` as a line break.
+#[cfg(feature = "pcre2")]
+fn normalize_line_breaks(content: &str) -> Cow<'_, str> {
+    Cow::Borrowed(content)
+}
+
+#[cfg(all(feature = "fancy-regex", not(feature = "pcre2")))]
+fn build_regex(pattern: &str) -> Result<Regex, String> {
+    Regex::new(&format!("(?m){}", pattern)).map_err(|e| e.to_string())
 }
 
-pub(crate) fn get_languages_from_heuristics(
-    extension: &str,
+#[cfg(all(feature = "fancy-regex", not(feature = "pcre2")))]
+fn is_match(regex: &Regex, content: &str) -> Option<bool> {
+    regex.is_match(content).ok()
+}
+
+/// Leaves out the flags added by [`build_regex`].
+#[cfg(all(feature = "fancy-regex", not(feature = "pcre2")))]
+fn regex_source(regex: &Regex) -> &str {
+    regex.as_str().trim_start_matches("(?m)")
+}
+
+/// `$` only matches before `\n` in multi-line mode, so line breaks are
+/// normalized once before matching instead.
+#[cfg(all(feature = "fancy-regex", not(feature = "pcre2")))]
+fn normalize_line_breaks(content: &str) -> Cow<'_, str> {
+    if content.contains("\r\n") {
+        Cow::Owned(content.replace("\r\n", "\n"))
+    } else {
+        Cow::Borrowed(content)
+    }
+}
+
+/// Narrows down the candidates with the first matching rule for the first
+/// of `keys` that has one, checking the rules in `registry` before the
+/// built-in ones for each key. Also returns whether the languages come from
+/// a rule without a pattern, like the default to C for `.h` files (see
+/// NOTE(ref: special-dot-h-rule)), which is a fallback rather than evidence
+/// for the languages. The rules checked are added to `checked` if given.
+pub(crate) fn get_languages_from_heuristics_with_fallback(
+    keys: &[HeuristicsKey],
+    candidates: &[Language],
+    content: &str,
+    registry: &LanguageRegistry,
+    mut checked: Option<&mut Vec<HeuristicRule>>,
+) -> (Vec<Language>, bool) {
+    let content = &normalize_line_breaks(content)[..];
+    for key in keys {
+        let (languages, fallback) =
+            get_languages_for_key(*key, candidates, content, registry, checked.as_deref_mut());
+        if !languages.is_empty() {
+            return (languages, fallback);
+        }
+    }
+    (vec![], false)
+}
+
+/// Whether any rule for `keys` applies to the candidates, and so could
+/// narrow them down depending on the content.
+pub(crate) fn any_rule_applies(
+    keys: &[HeuristicsKey],
+    candidates: &[Language],
+    registry: &LanguageRegistry,
+) -> bool {
+    let applies = |languages: &[Language]| {
+        languages
+            .iter()
+            .all(|language| candidates.contains(language))
+    };
+    keys.iter().any(|key| {
+        let key = key.table_key();
+        registry
+            .heuristics(&key)
+            .iter()
+            .any(|rule| applies(&rule.languages))
+            || DISAMBIGUATIONS
+                .get(&key[..])
+                .copied()
+                .unwrap_or_default()
+                .iter()
+                .any(|rule| applies(rule.languages))
+    })
+}
+
+fn get_languages_for_key(
+    key: HeuristicsKey,
     candidates: &[Language],
     content: &str,
-) -> Vec<Language> {
-    match DISAMBIGUATIONS.get(extension) {
-        Some(rules) => {
-            let rules = rules.iter().filter(|rule| {
-                rule.languages
-                    .iter()
-                    .all(|language| candidates.contains(language))
+    registry: &LanguageRegistry,
+    mut checked: Option<&mut Vec<HeuristicRule>>,
+) -> (Vec<Language>, bool) {
+    let key = key.table_key();
+    let applies = |languages: &[Language]| {
+        languages
+            .iter()
+            .all(|language| candidates.contains(language))
+    };
+    let mut record = |custom: bool, languages: &[Language], pattern: Option<String>, outcome| {
+        if let Some(checked) = checked.as_deref_mut() {
+            checked.push(HeuristicRule {
+                key: key.to_string(),
+                custom,
+                languages: languages.to_vec(),
+                pattern,
+                outcome,
             });
-            for rule in rules {
-                if let Some(pattern) = &rule.pattern {
-                    if pattern.matches(content) {
-                        return rule.languages.to_vec();
-                    };
-                } else {
-                    // if there is no pattern then it is a match by default
-                    return rule.languages.to_vec();
-                };
-            }
-            vec![]
         }
-        None => vec![],
+    };
+
+    for rule in registry.heuristics(&key) {
+        let outcome = if !applies(&rule.languages) {
+            RuleOutcome::Skipped
+        } else if rule.matches(content) {
+            RuleOutcome::Matched
+        } else {
+            RuleOutcome::NotMatched
+        };
+        record(
+            true,
+            &rule.languages,
+            rule.pattern.as_ref().map(|pattern| pattern.describe(false)),
+            outcome,
+        );
+        if outcome == RuleOutcome::Matched {
+            return (rule.languages.clone(), false);
+        }
+    }
+
+    for rule in DISAMBIGUATIONS.get(&key[..]).copied().unwrap_or_default() {
+        // if there is no pattern then it is a match by default
+        let outcome = if !applies(rule.languages) {
+            RuleOutcome::Skipped
+        } else if match &rule.pattern {
+            Some(pattern) => pattern.matches(content),
+            None => true,
+        } {
+            RuleOutcome::Matched
+        } else {
+            RuleOutcome::NotMatched
+        };
+        record(
+            false,
+            rule.languages,
+            rule.pattern.as_ref().map(|pattern| pattern.describe(false)),
+            outcome,
+        );
+        if outcome == RuleOutcome::Matched {
+            return (rule.languages.to_vec(), rule.pattern.is_none());
+        }
     }
+    (vec![], false)
 }
 
 #[cfg(test)]
@@ -78,10 +345,40 @@ mod tests {
     use super::*;
     use crate::ids;
 
+    fn get_languages_from_heuristics(
+        extension: &str,
+        candidates: &[Language],
+        content: &str,
+        registry: &LanguageRegistry,
+    ) -> Vec<Language> {
+        get_languages_from_heuristics_with_fallback(
+            &[HeuristicsKey::Extension(extension)],
+            candidates,
+            content,
+            registry,
+            None,
+        )
+        .0
+    }
+
+    fn get_languages_for_keys(
+        keys: &[HeuristicsKey],
+        candidates: &[Language],
+        content: &str,
+        registry: &LanguageRegistry,
+    ) -> Vec<Language> {
+        get_languages_from_heuristics_with_fallback(keys, candidates, content, registry, None).0
+    }
+
     #[test]
     fn test_heuristics_get_languages_positive_pattern() {
         assert_eq!(
-            get_languages_from_heuristics(".es", &[ids::Erlang, ids::JavaScript], "'use strict';"),
+            get_languages_from_heuristics(
+                ".es",
+                &[ids::Erlang, ids::JavaScript],
+                "'use strict';",
+                &LanguageRegistry::default()
+            ),
             vec![ids::JavaScript]
         );
     }
@@ -92,7 +389,8 @@ mod tests {
             get_languages_from_heuristics(
                 ".sql",
                 &[ids::PLSQL, ids::PLpgSQL, ids::SQL, ids::SQLPL, ids::TSQL],
-                "LALA THIS IS SQL"
+                "LALA THIS IS SQL",
+                &LanguageRegistry::default()
             ),
             vec![ids::SQL]
         );
@@ -104,7 +402,8 @@ mod tests {
             get_languages_from_heuristics(
                 ".pro",
                 &[ids::Proguard, ids::Prolog, ids::INI, ids::QMake, ids::IDL],
-                "HEADERS SOURCES"
+                "HEADERS SOURCES",
+                &LanguageRegistry::default()
             ),
             vec![ids::QMake]
         );
@@ -116,7 +415,8 @@ mod tests {
             get_languages_from_heuristics(
                 ".pro",
                 &[ids::Proguard, ids::Prolog, ids::INI, ids::QMake, ids::IDL],
-                "HEADERS"
+                "HEADERS",
+                &LanguageRegistry::default()
             ),
             vec![]
         );
@@ -128,7 +428,8 @@ mod tests {
             get_languages_from_heuristics(
                 ".ms",
                 &[ids::Roff, ids::Unix_Assembly, ids::MAXScript],
-                ".include:"
+                ".include:",
+                &LanguageRegistry::default()
             ),
             vec![ids::Unix_Assembly]
         );
@@ -137,7 +438,12 @@ mod tests {
     #[test]
     fn test_heuristics_get_languages_or_pattern() {
         assert_eq!(
-            get_languages_from_heuristics(".p", &[ids::Gnuplot, ids::OpenEdge_ABL], "plot"),
+            get_languages_from_heuristics(
+                ".p",
+                &[ids::Gnuplot, ids::OpenEdge_ABL],
+                "plot",
+                &LanguageRegistry::default()
+            ),
             vec![ids::Gnuplot]
         );
     }
@@ -145,7 +451,12 @@ mod tests {
     #[test]
     fn test_heuristics_get_languages_named_pattern() {
         assert_eq!(
-            get_languages_from_heuristics(".h", &[ids::Objective_C, ids::Cpp], "std::out"),
+            get_languages_from_heuristics(
+                ".h",
+                &[ids::Objective_C, ids::Cpp],
+                "std::out",
+                &LanguageRegistry::default()
+            ),
             vec![ids::Cpp]
         );
     }
@@ -153,7 +464,12 @@ mod tests {
     #[test]
     fn test_heuristics_get_languages_default_pattern() {
         assert_eq!(
-            get_languages_from_heuristics(".man", &[ids::Roff_Manpage, ids::Roff], "alskdjfahij"),
+            get_languages_from_heuristics(
+                ".man",
+                &[ids::Roff_Manpage, ids::Roff],
+                "alskdjfahij",
+                &LanguageRegistry::default()
+            ),
             vec![ids::Roff]
         );
     }
@@ -165,9 +481,160 @@ mod tests {
                 ".1in",
                 &[ids::Roff_Manpage, ids::Roff],
                 r#".TH LYXCLIENT 1 "@LYX_DATE@" "Version @VERSION@" "lyxclient @VERSION@"
-.SH NAME"#
+.SH NAME"#,
+                &LanguageRegistry::default()
             ),
             vec![ids::Roff_Manpage]
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_heuristics_built_in_patterns_compile() {
+        fn check<'a>(pattern: &'a Pattern, failures: &mut Vec<&'a str>) {
+            match pattern {
+                Pattern::Positive(pattern) | Pattern::Negative(pattern) => {
+                    if build_regex(pattern).is_err() {
+                        failures.push(pattern);
+                    }
+                }
+                Pattern::And(patterns) | Pattern::Or(patterns) => {
+                    patterns.iter().for_each(|pattern| check(pattern, failures))
+                }
+            }
+        }
+        let mut failures = vec![];
+        for rules in DISAMBIGUATIONS.values() {
+            for pattern in rules.iter().filter_map(|rule| rule.pattern.as_ref()) {
+                check(pattern, &mut failures);
+            }
+        }
+        if cfg!(feature = "pcre2") {
+            assert_eq!(failures, Vec::<&str>::new());
+        } else {
+            // fancy-regex doesn't support subroutine calls.
+            for pattern in failures {
+                assert!(pattern.contains("\\g<"), "{:?} doesn't compile", pattern);
+            }
+        }
+    }
+
+    #[test]
+    fn test_heuristics_crlf() {
+        assert_eq!(
+            get_languages_from_heuristics(
+                ".1in",
+                &[ids::Roff_Manpage, ids::Roff],
+                ".TH LYXCLIENT 1 \"@LYX_DATE@\"\r\n.SH NAME\r\n",
+                &LanguageRegistry::default()
+            ),
+            vec![ids::Roff_Manpage]
+        );
+    }
+
+    #[test]
+    fn test_heuristics_custom_rules_first() {
+        let mut registry = LanguageRegistry::new();
+        registry
+            .add_heuristic(".es", &[ids::Erlang], Some("'use strict'"))
+            .unwrap();
+        let candidates = &[ids::Erlang, ids::JavaScript];
+        assert_eq!(
+            get_languages_from_heuristics(".es", candidates, "'use strict';", &registry),
+            vec![ids::Erlang]
+        );
+        // Falls back to the built-in rules if no custom rule matches.
+        assert_eq!(
+            get_languages_from_heuristics(".es", candidates, "\"use strict\";", &registry),
+            vec![ids::JavaScript]
+        );
+    }
+
+    #[test]
+    fn test_heuristics_checked_rules() {
+        let mut registry = LanguageRegistry::new();
+        registry
+            .add_heuristics_yml(
+                r#"
+disambiguations:
+- extensions: [".es"]
+  rules:
+  - language: Erlang
+    and:
+    - pattern: ['^-module', '^main']
+    - negative_pattern: 'use strict'
+"#,
+            )
+            .unwrap();
+        let mut checked = vec![];
+        let (languages, fallback) = get_languages_from_heuristics_with_fallback(
+            &[HeuristicsKey::Extension(".es")],
+            &[ids::Erlang, ids::JavaScript],
+            "'use strict';",
+            &registry,
+            Some(&mut checked),
+        );
+        assert_eq!((languages, fallback), (vec![ids::JavaScript], false));
+
+        assert_eq!(checked[0].key, ".es");
+        assert!(checked[0].custom);
+        assert_eq!(
+            checked[0].pattern.as_deref(),
+            Some("(/^-module/ || /^main/) && !/use strict/")
+        );
+        assert_eq!(checked[0].outcome, RuleOutcome::NotMatched);
+        let last = checked.last().unwrap();
+        assert!(!last.custom);
+        assert_eq!(
+            (last.languages.clone(), last.outcome),
+            (vec![ids::JavaScript], RuleOutcome::Matched)
+        );
+    }
+
+    #[test]
+    fn test_heuristics_filename_and_interpreter_keys() {
+        let mut registry = LanguageRegistry::new();
+        registry
+            .add_filename_heuristic("HOSTS", &[ids::INI], Some(r"^\["))
+            .unwrap();
+        registry
+            .add_interpreter_heuristic(
+                "parrot",
+                &[ids::Parrot_Internal_Representation],
+                Some(r"^\.sub\b"),
+            )
+            .unwrap();
+
+        let hosts = &[ids::INI, ids::Hosts_File];
+        let keys = &[HeuristicsKey::Filename("HOSTS")];
+        assert_eq!(
+            get_languages_for_keys(keys, hosts, "[hosts]\n", &registry),
+            vec![ids::INI]
+        );
+        assert_eq!(
+            get_languages_for_keys(keys, hosts, "127.0.0.1 a\n", &registry),
+            vec![]
+        );
+        // Filenames are matched exactly.
+        assert_eq!(
+            get_languages_for_keys(
+                &[HeuristicsKey::Filename("hosts")],
+                hosts,
+                "[hosts]\n",
+                &registry
+            ),
+            vec![]
+        );
+
+        // The first key with a matching rule wins.
+        let parrot = &[ids::Parrot_Assembly, ids::Parrot_Internal_Representation];
+        let keys = &[
+            HeuristicsKey::Extension(".unknown"),
+            HeuristicsKey::Filename("main"),
+            HeuristicsKey::Interpreter("parrot"),
+        ];
+        assert_eq!(
+            get_languages_for_keys(keys, parrot, ".sub main\n", &registry),
+            vec![ids::Parrot_Internal_Representation]
+        );
+    }
+}
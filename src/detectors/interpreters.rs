@@ -1,3 +1,5 @@
+use std::io::{self, BufRead, Read};
+
 use lazy_static::lazy_static;
 use regex::Regex;
 
@@ -5,64 +7,169 @@ use regex::Regex;
 // static INTERPRETERS: phf::Map<&'static str, &[&str]> = ...;
 include!("../generated/interpreter_language_map.rs");
 
-use crate::Language;
+use crate::{registry::merge_languages, Language, LanguageRegistry};
+
+/// Shells whose scripts are searched for a trampoline that runs the rest of
+/// the script with another interpreter, like `exec scala "$0" "$@"`.
+const SHELLS: &[&str] = &["sh", "bash", "zsh", "dash", "ksh", "mksh", "ash"];
+
+/// How many lines after the shebang are searched for a trampoline or for the
+/// interpreter of `nix-shell`.
+const MAX_SCRIPT_LINES: usize = 10;
+
+/// Lines are cut after this many bytes, so that a binary file without line
+/// breaks isn't read whole.
+const MAX_LINE_BYTES: u64 = 4096;
 
-pub(crate) fn get_languages_from_shebang<R: std::io::BufRead>(
+pub(crate) fn get_languages_from_shebang<R: BufRead>(
     reader: R,
+    registry: &LanguageRegistry,
 ) -> Result<Vec<Language>, std::io::Error> {
-    let mut lines = reader.lines();
-    let shebang_line = match lines.next() {
+    Ok(match get_interpreter_from_shebang(reader)? {
+        Some(interpreter) => get_languages_from_interpreter(&interpreter, registry).1,
+        None => vec![],
+    })
+}
+
+/// Returns the interpreter that runs a script, following `nix-shell` and
+/// trampolines in shell scripts, or `None` if there is no shebang.
+pub(crate) fn get_interpreter_from_shebang<R: BufRead>(
+    mut reader: R,
+) -> Result<Option<String>, std::io::Error> {
+    let first_line = match read_line(&mut reader)? {
         Some(line) => line,
-        None => return Ok(vec![]),
-    }?;
-    let mut extra_content = String::new();
-
-    if !shebang_line.starts_with("#!") {
-        return Ok(vec![]);
-    }
-
-    let languages = shebang_line
-        .split('/')
-        .last()
-        .and_then(|interpreter_line| {
-            let mut splits = interpreter_line.split_whitespace();
-            match splits.next() {
-                // #!/usr/bin/env python
-                Some("env") => splits.next(),
-                // #!/usr/bin/sh [exec scala "$0" "$@"]
-                Some("sh") => {
-                    let lines: Vec<String> = lines.take(4).filter_map(|line| line.ok()).collect();
-                    extra_content = lines.join("\n");
-                    lazy_static! {
-                        static ref SHEBANG_HACK_RE: Regex =
-                            Regex::new(r#"exec (\w+).+\$0.+\$@"#).unwrap();
-                    }
-                    let interpreter = SHEBANG_HACK_RE
-                        .captures(&extra_content[..])
-                        .and_then(|captures| captures.get(1))
-                        .map(|interpreter| interpreter.as_str())
-                        .unwrap_or("sh");
-                    Some(interpreter)
-                }
-                // #!/usr/bin/python
-                Some(interpreter) => Some(interpreter),
-                // #!
-                None => None,
+        None => return Ok(None),
+    };
+    let first_line = first_line.strip_prefix('\u{feff}').unwrap_or(&first_line);
+    let mut interpreter = match first_line.strip_prefix("#!").and_then(command_name) {
+        Some(interpreter) => interpreter.to_owned(),
+        None => return Ok(None),
+    };
+
+    let is_nix_shell = interpreter == "nix-shell";
+    if is_nix_shell || SHELLS.contains(&interpreter.as_str()) {
+        for _ in 0..MAX_SCRIPT_LINES {
+            let line = match read_line(&mut reader)? {
+                Some(line) => line,
+                None => break,
+            };
+            let found = if is_nix_shell {
+                nix_shell_interpreter(&line)
+            } else {
+                trampoline_interpreter(&line)
+            };
+            if let Some(found) = found {
+                interpreter = found.to_owned();
+                break;
+            }
+        }
+        // nix-shell runs scripts with bash unless told otherwise.
+        if interpreter == "nix-shell" {
+            interpreter = String::from("bash");
+        }
+    }
+
+    Ok(Some(interpreter))
+}
+
+/// Reads a line without its line ending, replacing invalid UTF-8, or returns
+/// `None` at the end of the content.
+fn read_line<R: BufRead>(reader: &mut R) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    if reader
+        .by_ref()
+        .take(MAX_LINE_BYTES)
+        .read_until(b'\n', &mut line)?
+        == 0
+    {
+        return Ok(None);
+    }
+    let line = String::from_utf8_lossy(&line);
+    Ok(Some(line.trim_end_matches(&['\r', '\n'][..]).to_owned()))
+}
+
+/// Returns the name of the program run by a command, without its directory,
+/// skipping `env` with its options and variable assignments, e.g. `python3`
+/// for `/usr/bin/env -S PYTHONPATH=. python3 -u`.
+fn command_name(command: &str) -> Option<&str> {
+    let mut args = command
+        .split_whitespace()
+        .map(|arg| arg.trim_matches(&['"', '\''][..]));
+    let mut program = file_name(args.next()?);
+    while program == "env" {
+        let arg = loop {
+            let arg = args.next()?;
+            // `-S` splits its value into arguments, like the shebang line
+            // already is, so it may be stuck to the program.
+            let split_string = arg
+                .strip_prefix("--split-string=")
+                .or_else(|| arg.strip_prefix("-S"))
+                .filter(|rest| !rest.is_empty());
+            if let Some(program) = split_string {
+                break program;
             }
-        })
-        .and_then(|interpreter| {
-            // #!/usr/bin/python2.6.3 -> #!/usr/bin/python2
-            lazy_static! {
-                static ref RE: Regex = Regex::new(r#"[0-9]\.[0-9]"#).unwrap();
+            match arg {
+                // Options followed by a value.
+                "-u" | "--unset" | "-C" | "--chdir" | "-P" => {
+                    args.next();
+                }
+                _ if arg.starts_with('-') || arg.contains('=') => {}
+                _ => break arg,
             }
-            let interpreter = RE.split(interpreter).next().unwrap();
+        };
+        program = file_name(arg);
+    }
+    Some(program).filter(|program| !program.is_empty())
+}
 
-            INTERPRETERS.get(interpreter)
-        });
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
 
-    match languages {
-        Some(languages) => Ok(languages.to_vec()),
-        None => Ok(vec![]),
+/// Returns the interpreter in a `nix-shell` shebang line, like `python3` in
+/// `#! nix-shell -i python3 -p python3Packages.requests`.
+fn nix_shell_interpreter(line: &str) -> Option<&str> {
+    let mut args = line.strip_prefix("#!")?.split_whitespace();
+    if args.next().map(file_name) != Some("nix-shell") {
+        return None;
+    }
+    args.skip_while(|&arg| arg != "-i").nth(1)
+}
+
+/// Returns the interpreter that a shell script hands itself over to, like
+/// `scala` in `exec scala "$0" "$@"`.
+fn trampoline_interpreter(line: &str) -> Option<&str> {
+    lazy_static! {
+        static ref TRAMPOLINE_RE: Regex = Regex::new(r#"\bexec\s+(.*\$\{?0\b.*)"#).unwrap();
+        static ref NAME_RE: Regex = Regex::new(r#"^[\w.+-]+$"#).unwrap();
+    }
+    let command = TRAMPOLINE_RE.captures(line)?.get(1)?.as_str();
+    // Skip programs in variables, like `exec "$PYTHON" "$0"`.
+    command_name(command).filter(|&name| NAME_RE.is_match(name))
+}
+
+/// Looks up the languages of an interpreter, or of its name without its
+/// version if the version isn't known, e.g. `python3` for `python3.11` and
+/// `ruby` for `ruby2.7`. Also returns the name the languages were found
+/// under, or the name without any version if there are none.
+pub(crate) fn get_languages_from_interpreter<'a>(
+    interpreter: &'a str,
+    registry: &LanguageRegistry,
+) -> (&'a str, Vec<Language>) {
+    lazy_static! {
+        static ref VERSION_RE: Regex = Regex::new(r#"(\.|[-_]?)[0-9]+$"#).unwrap();
+    }
+    let mut name = interpreter;
+    loop {
+        let built_in = INTERPRETERS.get(name).copied().unwrap_or_default();
+        let languages = merge_languages(built_in, registry.interpreter_languages(name));
+        if !languages.is_empty() {
+            return (name, languages);
+        }
+        match VERSION_RE.find(name) {
+            Some(version) if version.start() > 0 => name = &name[..version.start()],
+            _ => return (name, languages),
+        }
     }
 }
 
@@ -75,22 +182,33 @@ mod tests {
     #[test]
     fn test_shebang_get_languages() {
         assert_eq!(
-            get_languages_from_shebang(Cursor::new("#!/usr/bin/python")).unwrap(),
+            get_languages_from_shebang(
+                Cursor::new("#!/usr/bin/python"),
+                &LanguageRegistry::default()
+            )
+            .unwrap(),
             vec![ids::Python]
         );
     }
     #[test]
     fn test_shebang_get_languages_env() {
         assert_eq!(
-            get_languages_from_shebang(Cursor::new("#!/usr/bin/env node")).unwrap(),
+            get_languages_from_shebang(
+                Cursor::new("#!/usr/bin/env node"),
+                &LanguageRegistry::default()
+            )
+            .unwrap(),
             vec![ids::JavaScript]
         );
     }
 
     #[test]
     fn test_shebang_get_languages_multiple() {
-        let mut parrot_langs =
-            get_languages_from_shebang(Cursor::new("#!/usr/bin/parrot")).unwrap();
+        let mut parrot_langs = get_languages_from_shebang(
+            Cursor::new("#!/usr/bin/parrot"),
+            &LanguageRegistry::default(),
+        )
+        .unwrap();
         parrot_langs.sort();
         assert_eq!(
             parrot_langs,
@@ -101,7 +219,11 @@ mod tests {
     #[test]
     fn test_shebang_get_languages_with_minor_version() {
         assert_eq!(
-            get_languages_from_shebang(Cursor::new("#!/usr/bin/python2.6")).unwrap(),
+            get_languages_from_shebang(
+                Cursor::new("#!/usr/bin/python2.6"),
+                &LanguageRegistry::default()
+            )
+            .unwrap(),
             vec![ids::Python]
         );
     }
@@ -110,39 +232,48 @@ mod tests {
     fn test_shebang_empty_cases() {
         let empty_vec: Vec<Language> = Vec::new();
         assert_eq!(
-            get_languages_from_shebang(Cursor::new("#!/usr/bin/env")).unwrap(),
+            get_languages_from_shebang(Cursor::new("#!/usr/bin/env"), &LanguageRegistry::default())
+                .unwrap(),
             empty_vec
         );
         assert_eq!(
-            get_languages_from_shebang(Cursor::new("#!")).unwrap(),
+            get_languages_from_shebang(Cursor::new("#!"), &LanguageRegistry::default()).unwrap(),
             empty_vec
         );
         assert_eq!(
-            get_languages_from_shebang(Cursor::new("")).unwrap(),
+            get_languages_from_shebang(Cursor::new(""), &LanguageRegistry::default()).unwrap(),
             empty_vec
         );
         assert_eq!(
-            get_languages_from_shebang(Cursor::new("aslkdfjas;ldk")).unwrap(),
+            get_languages_from_shebang(Cursor::new("aslkdfjas;ldk"), &LanguageRegistry::default())
+                .unwrap(),
             empty_vec
         );
         assert_eq!(
-            get_languages_from_shebang(Cursor::new(" #!/usr/bin/python")).unwrap(),
+            get_languages_from_shebang(
+                Cursor::new(" #!/usr/bin/python"),
+                &LanguageRegistry::default()
+            )
+            .unwrap(),
             empty_vec
         );
         assert_eq!(
-            get_languages_from_shebang(Cursor::new(" #!/usr/bin/ ")).unwrap(),
+            get_languages_from_shebang(Cursor::new(" #!/usr/bin/ "), &LanguageRegistry::default())
+                .unwrap(),
             empty_vec
         );
         assert_eq!(
-            get_languages_from_shebang(Cursor::new(" #!/usr/bin")).unwrap(),
+            get_languages_from_shebang(Cursor::new(" #!/usr/bin"), &LanguageRegistry::default())
+                .unwrap(),
             empty_vec
         );
         assert_eq!(
-            get_languages_from_shebang(Cursor::new(" #!/usr/bin")).unwrap(),
+            get_languages_from_shebang(Cursor::new(" #!/usr/bin"), &LanguageRegistry::default())
+                .unwrap(),
             empty_vec
         );
         assert_eq!(
-            get_languages_from_shebang(Cursor::new("")).unwrap(),
+            get_languages_from_shebang(Cursor::new(""), &LanguageRegistry::default()).unwrap(),
             empty_vec
         );
     }
@@ -156,6 +287,128 @@ mod tests {
             "#,
         );
 
-        assert_eq!(get_languages_from_shebang(content).unwrap(), vec![ids::Scala]);
+        assert_eq!(
+            get_languages_from_shebang(content, &LanguageRegistry::default()).unwrap(),
+            vec![ids::Scala]
+        );
+    }
+
+    /// Shebangs from real scripts, and the language they should be detected
+    /// as, or "" if none.
+    const SHEBANG_CORPUS: &[(&str, &str)] = &[
+        ("#!/usr/bin/env python3\n", "Python"),
+        ("#! /usr/bin/env python\n", "Python"),
+        ("#!/usr/bin/python3.11\n", "Python"),
+        ("#!/usr/local/bin/python2.7 -u\n", "Python"),
+        ("#!/usr/bin/env python3.12\r\nimport sys\r\n", "Python"),
+        ("#!/usr/bin/env -S python3 -u\n", "Python"),
+        ("#!/usr/bin/env -S PYTHONPATH=. python3 -X dev\n", "Python"),
+        ("#!/usr/bin/env -Spython3\n", "Python"),
+        ("#!/usr/bin/env --split-string=python3 -u\n", "Python"),
+        ("#!/usr/bin/env -i PATH=/usr/bin python3\n", "Python"),
+        ("#!/usr/bin/env -u PYTHONHOME python3\n", "Python"),
+        ("#!/usr/bin/env LC_ALL=C.UTF-8 ruby\n", "Ruby"),
+        ("#!/usr/bin/ruby2.7 -w\n", "Ruby"),
+        ("#!/usr/bin/env ruby -w\r\n", "Ruby"),
+        ("\u{feff}#!/usr/bin/env node\n", "JavaScript"),
+        ("#!/usr/bin/env -S deno run --allow-net\n", "TypeScript"),
+        ("#!/usr/bin/env -S ts-node --transpile-only\n", "TypeScript"),
+        ("#!/usr/bin/perl -w\n", "Perl"),
+        ("#!/usr/bin/env perl5.36\n", "Perl"),
+        ("#!/usr/bin/lua5.4\n", "Lua"),
+        ("#!/usr/bin/env guile-3.0\n!#\n", "Scheme"),
+        ("#!/bin/bash\r\necho hi\r\n", "Shell"),
+        ("#!/bin/sh -e\n", "Shell"),
+        ("#!/usr/bin/env bash\nset -euo pipefail\n", "Shell"),
+        ("#!/usr/bin/env zsh\n", "Shell"),
+        (
+            "#!/usr/bin/env nix-shell\n#!nix-shell -i python3 -p python3Packages.requests\n",
+            "Python",
+        ),
+        (
+            "#! /usr/bin/env nix-shell\n#! nix-shell -p jq curl\n#! nix-shell -i bash\n",
+            "Shell",
+        ),
+        ("#!/usr/bin/env nix-shell\n#!nix-shell -p hello\nhello\n", "Shell"),
+        (
+            "#!/bin/sh\n# -*- scheme -*-\nexec guile -e main -s \"$0\" \"$@\"\n!#\n",
+            "Scheme",
+        ),
+        (
+            "#!/bin/sh\n# the next line restarts using tclsh \\\nexec tclsh \"$0\" ${1+\"$@\"}\n",
+            "Tcl",
+        ),
+        (
+            "#!/bin/sh\n''''exec python3 -u -- \"$0\" ${1+\"$@\"} # '''\n",
+            "Python",
+        ),
+        (
+            "#!/usr/bin/env bash\n# Re-run with the right interpreter.\n\nexec /usr/bin/env racket -u \"$0\" \"$@\"\n",
+            "Racket",
+        ),
+        (
+            "#!/bin/zsh\nsetopt err_exit\nexec \"$PYTHON\" \"$0\" \"$@\"\n",
+            "Shell",
+        ),
+        ("#!/bin/bash\nexec make -f Makefile \"$@\"\n", "Shell"),
+        ("#!/usr/bin/env\n", ""),
+        ("#!/usr/bin/env -i\n", ""),
+        ("#!\n", ""),
+        ("#!/usr/bin/\n", ""),
+        ("# !/usr/bin/python\n", ""),
+        ("\x7fELF\x02\x01\x01", ""),
+    ];
+
+    #[test]
+    fn test_shebang_corpus() {
+        let registry = LanguageRegistry::default();
+        for (script, name) in SHEBANG_CORPUS {
+            let languages = get_languages_from_shebang(Cursor::new(script), &registry).unwrap();
+            match registry.find(name) {
+                Some(language) => assert!(
+                    languages.contains(&language),
+                    "{:?} detected as {:?}",
+                    script,
+                    languages
+                ),
+                None => assert!(languages.is_empty(), "{:?}", script),
+            }
+        }
+    }
+
+    #[test]
+    fn test_shebang_invalid_utf8() {
+        assert_eq!(
+            get_languages_from_shebang(
+                Cursor::new(b"#!/usr/bin/python\n\xff\xfe"),
+                &LanguageRegistry::default()
+            )
+            .unwrap(),
+            vec![ids::Python]
+        );
+    }
+
+    #[test]
+    fn test_get_languages_from_interpreter_name() {
+        let registry = LanguageRegistry::default();
+        assert_eq!(
+            get_languages_from_interpreter("python3.11", &registry),
+            ("python3", vec![ids::Python])
+        );
+        assert_eq!(
+            get_languages_from_interpreter("unknown-2", &registry),
+            ("unknown", vec![])
+        );
+        assert_eq!(
+            get_interpreter_from_shebang(Cursor::new(
+                "#!/usr/bin/env nix-shell\n#!nix-shell -i parrot\n"
+            ))
+            .unwrap(),
+            Some(String::from("parrot"))
+        );
+        assert_eq!(
+            get_interpreter_from_shebang(Cursor::new("print(1)\n")).unwrap(),
+            None
+        );
     }
 }
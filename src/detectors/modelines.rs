@@ -0,0 +1,96 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::{Language, LanguageRegistry};
+
+// Linguist only looks for modelines in the first and last few lines.
+const SEARCH_SCOPE: usize = 5;
+
+/// Returns the languages named by an Emacs or Vim modeline, like
+/// `-*- mode: ruby -*-` or `vim: set ft=ruby:`, in the first or last
+/// lines of `content`. Names are matched against the aliases of languages.
+pub(crate) fn get_languages_from_modeline(
+    content: &str,
+    registry: &LanguageRegistry,
+) -> Vec<Language> {
+    let lines: Vec<&str> = content.lines().collect();
+    let tail = lines.len().saturating_sub(SEARCH_SCOPE).max(SEARCH_SCOPE);
+    lines
+        .iter()
+        .take(SEARCH_SCOPE)
+        .chain(lines.iter().skip(tail))
+        .find_map(|line| emacs_mode(line).or_else(|| vim_filetype(line)))
+        .map(|mode| registry.find_by_alias(&mode))
+        .unwrap_or_default()
+}
+
+/// `-*- ruby -*-` or `-*- mode: ruby; coding: utf-8 -*-`
+fn emacs_mode(line: &str) -> Option<String> {
+    let start = line.find("-*-")? + 3;
+    let end = start + line[start..].find("-*-")?;
+    let inner = line[start..end].trim();
+    if !inner.contains(':') {
+        return Some(inner.to_lowercase());
+    }
+    inner.split(';').find_map(|variable| {
+        let (name, value) = variable.split_once(':')?;
+        if name.trim().eq_ignore_ascii_case("mode") {
+            Some(value.trim().to_lowercase())
+        } else {
+            None
+        }
+    })
+}
+
+/// `vim: set ft=ruby:`, `vi: filetype=ruby` or `ex: syntax=ruby`
+fn vim_filetype(line: &str) -> Option<String> {
+    lazy_static! {
+        static ref VIM_RE: Regex = Regex::new(
+            r"(?:^|\s)(?:vi|vim[<=>]?\d*|ex):.*?\b(?:ft|filetype|syntax)\s*=\s*([\w+-]+)"
+        )
+        .unwrap();
+    }
+    VIM_RE
+        .captures(line)
+        .map(|captures| captures[1].to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids;
+
+    fn modeline(content: &str) -> Vec<Language> {
+        get_languages_from_modeline(content, &LanguageRegistry::default())
+    }
+
+    #[test]
+    fn test_emacs_modeline() {
+        assert_eq!(modeline("# -*- ruby -*-\nputs 1\n"), vec![ids::Ruby]);
+        assert_eq!(
+            modeline("/* -*- Mode: C++; tab-width: 4 -*- */"),
+            vec![ids::Cpp]
+        );
+        assert_eq!(modeline("# -*- coding: utf-8 -*-"), vec![]);
+    }
+
+    #[test]
+    fn test_vim_modeline() {
+        assert_eq!(modeline("# vim: set ft=python:"), vec![ids::Python]);
+        assert_eq!(
+            modeline("// vim: filetype=javascript ts=2"),
+            vec![ids::JavaScript]
+        );
+        assert_eq!(modeline("/* vim: set syntax=unknown-language: */"), vec![]);
+        // "vim:" has to be a word of its own.
+        assert_eq!(modeline("avim: ft=python"), vec![]);
+    }
+
+    #[test]
+    fn test_modeline_search_scope() {
+        let middle = format!("{}# vim: ft=python\n{}", "x\n".repeat(5), "x\n".repeat(5));
+        assert_eq!(modeline(&middle), vec![]);
+        let last = format!("{}# vim: ft=python\n", "x\n".repeat(10));
+        assert_eq!(modeline(&last), vec![ids::Python]);
+    }
+}
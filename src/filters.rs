@@ -1,5 +1,15 @@
+#[cfg(feature = "breakdown")]
 mod documentation;
+mod generated;
+#[cfg(feature = "breakdown")]
 mod vendor;
 
+#[cfg(feature = "breakdown")]
 pub(crate) use documentation::add_documentation_override;
+#[cfg(feature = "breakdown")]
+pub use documentation::is_documentation;
+pub use generated::is_generated;
+#[cfg(feature = "breakdown")]
 pub(crate) use vendor::add_vendor_override;
+#[cfg(feature = "breakdown")]
+pub use vendor::is_vendor;
@@ -1,4 +1,20 @@
-use ignore::overrides::OverrideBuilder;
+use ignore::overrides::{Override, OverrideBuilder};
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref DOCUMENTATION: Override = add_documentation_override(OverrideBuilder::new("./"))
+        .build()
+        .expect("documentation patterns are valid");
+}
+
+/// Returns true if `path` is documentation, like `docs/` or a README, which
+/// [`get_language_breakdown`](crate::get_language_breakdown) skips.
+///
+/// `path` is relative to the root of a repository, and directories
+/// should end with a `/`.
+pub fn is_documentation(path: &str) -> bool {
+    DOCUMENTATION.matched(path, path.ends_with('/')).is_ignore()
+}
 
 pub(crate) fn add_documentation_override(mut builder: OverrideBuilder) -> OverrideBuilder {
     // Documentation directories
@@ -56,4 +72,12 @@ mod tests {
         assert!(doco.matched("/dir/CHANGELOG", false).is_ignore());
         assert!(doco.matched("/dir/NOT", false).is_none());
     }
+
+    #[test]
+    fn test_is_documentation() {
+        assert!(is_documentation("docs/"));
+        assert!(is_documentation("project/docs/index.md"));
+        assert!(is_documentation("README.md"));
+        assert!(!is_documentation("src/docs.rs"));
+    }
 }
@@ -0,0 +1,219 @@
+//! A port of the rules in Linguist's `generated.rb` that are cheap to
+//! check, which covers lock files, minified files and source maps,
+//! and the headers that common code generators write.
+
+// Files that are always generated, like lock files.
+const GENERATED_FILENAMES: &[&str] = &[
+    ".pnp.cjs",
+    ".pnp.js",
+    ".pnp.loader.mjs",
+    ".terraform.lock.hcl",
+    "Cargo.lock",
+    "Cargo.toml.orig",
+    "Gopkg.lock",
+    "MODULE.bazel.lock",
+    "Package.resolved",
+    "Pipfile.lock",
+    "bun.lock",
+    "bun.lockb",
+    "composer.lock",
+    "deno.lock",
+    "flake.lock",
+    "glide.lock",
+    "npm-shrinkwrap.json",
+    "package-lock.json",
+    "pdm.lock",
+    "pnpm-lock.yaml",
+    "poetry.lock",
+    "uv.lock",
+];
+
+// Directories whose contents are generated.
+const GENERATED_DIRECTORIES: &[&str] = &[".idea", "Godeps", "Pods", "htmlcov", "node_modules"];
+
+// Suffixes of generated files, compared case-insensitively.
+const GENERATED_SUFFIXES: &[&str] = &[
+    ".css.map",
+    ".designer.cs",
+    ".designer.vb",
+    ".feature.cs",
+    ".js.map",
+    ".nib",
+    ".xcuserstate",
+    ".xcworkspacedata",
+    ".zep.c",
+    ".zep.h",
+    ".zep.php",
+];
+
+const PROTOBUF_EXTENSIONS: &[&str] = &[".cc", ".cpp", ".h", ".java", ".m", ".php", ".py", ".rb"];
+
+const THRIFT_EXTENSIONS: &[&str] = &[
+    ".cc", ".cpp", ".go", ".h", ".java", ".js", ".m", ".php", ".py", ".rb",
+];
+
+/// Returns true if the file at `path` with the given `content` was
+/// generated, using the same rules as Linguist, which excludes generated
+/// files from language statistics. `path` is relative to the root of a
+/// repository and is only used for its directories, name and extension.
+///
+/// Only the rules that don't need a parser are implemented, so
+/// e.g. compiled CoffeeScript isn't recognized.
+pub fn is_generated(path: &str, content: &[u8]) -> bool {
+    is_generated_path(path) || is_generated_content(path, content)
+}
+
+fn is_generated_path(path: &str) -> bool {
+    let mut components: Vec<&str> = path.split('/').collect();
+    let filename = components.pop().unwrap_or_default();
+
+    if GENERATED_FILENAMES.contains(&filename) {
+        return true;
+    }
+    let lowercase = filename.to_ascii_lowercase();
+    if GENERATED_SUFFIXES
+        .iter()
+        .any(|suffix| lowercase.ends_with(suffix))
+    {
+        return true;
+    }
+    components
+        .iter()
+        .any(|component| GENERATED_DIRECTORIES.contains(component))
+        || components
+            .windows(2)
+            .any(|pair| pair == ["Carthage", "Build"])
+}
+
+fn is_generated_content(path: &str, content: &[u8]) -> bool {
+    let filename = path.rsplit('/').next().unwrap_or_default();
+    let extension = filename
+        .rfind('.')
+        .map(|pos| filename[pos..].to_ascii_lowercase())
+        .unwrap_or_default();
+    let extension = extension.as_str();
+
+    let content = String::from_utf8_lossy(content);
+    let lines: Vec<&str> = content.lines().collect();
+    let line = |index: usize| lines.get(index).copied().unwrap_or_default();
+    let head = |count: usize, needle: &str| lines.iter().take(count).any(|l| l.contains(needle));
+
+    if (extension == ".js" || extension == ".css")
+        && (is_minified(&lines) || has_source_map(&lines))
+    {
+        return true;
+    }
+    if extension == ".map"
+        && (line(0).starts_with("{\"version\":")
+            || line(0).starts_with("/** Begin line maps. **/{"))
+    {
+        return true;
+    }
+
+    // Headers written by code generators, most of which say not to edit the file.
+    (extension == ".go" && head(40, "Code generated by"))
+        || (PROTOBUF_EXTENSIONS.contains(&extension)
+            && head(
+                2,
+                "Generated by the protocol buffer compiler.  DO NOT EDIT!",
+            ))
+        || (extension == ".js" && head(1, "// GENERATED CODE -- DO NOT EDIT!"))
+        || (extension == ".ts" && head(5, "Code generated by protoc-gen-ts"))
+        || (THRIFT_EXTENSIONS.contains(&extension) && head(6, "Autogenerated by Thrift Compiler"))
+        || (extension == ".h"
+            && line(0) == "/* DO NOT EDIT THIS FILE - it is machine generated */"
+            && line(1) == "#include <jni.h>")
+        || (extension == ".yml"
+            && lines.len() >= 2
+            && lines[lines.len() - 2].contains("recorded_with: VCR"))
+        || (extension == ".meta" && line(0).starts_with("fileFormatVersion: "))
+        || (extension == ".rb"
+            && line(2).starts_with("# This file is automatically generated by Racc"))
+        || (extension == ".java"
+            && (line(0).starts_with("/* The following code was generated by JFlex ")
+                || line(0)
+                    .starts_with("// This is a generated file. Not intended for manual editing.")))
+        || (extension == ".rd" && line(0).contains("% Generated by roxygen2: do not edit by hand"))
+        || (extension == ".js"
+            && (line(0).starts_with("/* parser generated by jison ")
+                || line(0).starts_with("/* generated by jison-lex ")))
+        || (extension == ".dart"
+            && lines.iter().take(3).any(|l| {
+                l.to_ascii_lowercase()
+                    .contains("generated code. do not modify")
+            }))
+        || (filename == "ppport.h" && head(10, "Automatically created by Devel::PPPort"))
+        || ((extension == ".c" || extension == ".cpp") && line(0).contains("Generated by Cython "))
+        || ((extension == ".c" || extension == ".h")
+            && line(0).starts_with("/* GIMP ")
+            && line(0).contains("C-Source image dump"))
+        || (extension == ".rbi" && head(5, "DO NOT EDIT MANUALLY"))
+}
+
+/// Minified files have an average line length of over 110 characters.
+fn is_minified(lines: &[&str]) -> bool {
+    !lines.is_empty() && lines.iter().map(|line| line.len()).sum::<usize>() / lines.len() > 110
+}
+
+/// Compiled files refer to their source maps in one of their last two lines.
+fn has_source_map(lines: &[&str]) -> bool {
+    lines.iter().rev().take(2).any(|line| {
+        [
+            "//# sourceMappingURL",
+            "//@ sourceMappingURL",
+            "/*# sourceMappingURL",
+        ]
+        .iter()
+        .any(|prefix| line.starts_with(prefix))
+            || line.contains("sourceURL=")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_paths() {
+        assert!(is_generated("Cargo.lock", b""));
+        assert!(is_generated("web/package-lock.json", b"{}"));
+        assert!(is_generated("app/Form1.Designer.cs", b""));
+        assert!(is_generated("dist/app.js.map", b""));
+        assert!(is_generated(
+            "ios/Pods/AFNetworking/AFURLSessionManager.m",
+            b""
+        ));
+        assert!(is_generated(
+            "Carthage/Build/iOS/Alamofire.framework/Headers/Alamofire.h",
+            b""
+        ));
+        assert!(!is_generated("Cargo.toml", b""));
+        assert!(!is_generated("src/Pods.swift", b""));
+    }
+
+    #[test]
+    fn test_generated_content() {
+        let go = b"// Code generated by protoc-gen-go. DO NOT EDIT.\n\npackage api\n";
+        assert!(is_generated("api/api.pb.go", go));
+        assert!(!is_generated("api/api.go", b"package api\n"));
+
+        let python = b"# Generated by the protocol buffer compiler.  DO NOT EDIT!\n";
+        assert!(is_generated("api_pb2.py", python));
+        // The header only counts for the languages protoc generates.
+        assert!(!is_generated("api_pb2.lua", python));
+
+        let jni = b"/* DO NOT EDIT THIS FILE - it is machine generated */\n#include <jni.h>\n";
+        assert!(is_generated("Native.h", jni));
+    }
+
+    #[test]
+    fn test_generated_minified() {
+        let minified = format!("{}\n", "var a=1;".repeat(20));
+        assert!(is_generated("app.js", minified.as_bytes()));
+        assert!(!is_generated("app.py", minified.as_bytes()));
+        assert!(!is_generated("app.js", b"var a = 1;\nvar b = 2;\n"));
+
+        let compiled = b"var a = 1;\n//# sourceMappingURL=app.js.map\n";
+        assert!(is_generated("app.js", compiled));
+    }
+}
@@ -1,4 +1,20 @@
-use ignore::overrides::OverrideBuilder;
+use ignore::overrides::{Override, OverrideBuilder};
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref VENDOR: Override = add_vendor_override(OverrideBuilder::new("./"))
+        .build()
+        .expect("vendor patterns are valid");
+}
+
+/// Returns true if `path` is vendored, like `node_modules/` or minified JavaScript, which
+/// [`get_language_breakdown`](crate::get_language_breakdown) skips.
+///
+/// `path` is relative to the root of a repository, and directories
+/// should end with a `/`.
+pub fn is_vendor(path: &str) -> bool {
+    VENDOR.matched(path, path.ends_with('/')).is_ignore()
+}
 
 pub(crate) fn add_vendor_override(mut builder: OverrideBuilder) -> OverrideBuilder {
     // Caches
@@ -427,4 +443,12 @@ mod tests {
         assert!(vo.matched("dir/microsoftAjax.js", false).is_ignore());
         assert!(vo.matched("dir/microsoftajax.js", false).is_ignore());
     }
+
+    #[test]
+    fn test_is_vendor() {
+        assert!(is_vendor("node_modules/"));
+        assert!(is_vendor("web/node_modules/left-pad/index.js"));
+        assert!(is_vendor("static/jquery.min.js"));
+        assert!(!is_vendor("src/main.rs"));
+    }
 }
@@ -0,0 +1,132 @@
+static FOLDED_FILENAME_TO_LANGUAGE_MAP: phf::Map<&'static str, &[crate::Language]> = {
+    use crate::ids;
+    ::phf::Map {
+    key: 10121458955350035957,
+    disps: &[
+        (1, 18),
+        (0, 4),
+        (0, 5),
+        (0, 12),
+        (1, 0),
+        (0, 29),
+        (2, 36),
+        (0, 47),
+        (0, 53),
+        (2, 45),
+        (3, 78),
+        (0, 33),
+        (0, 1),
+        (3, 26),
+        (13, 48),
+        (34, 16),
+        (23, 44),
+        (0, 3),
+    ],
+    entries: &[
+        ("berksfile", &[ids::Ruby, ]),
+        ("cmakelists.txt", &[ids::CMake, ]),
+        ("web.debug.config", &[ids::XML, ]),
+        ("android.bp", &[ids::Soong, ]),
+        ("appraisals", &[ids::Ruby, ]),
+        ("nuget.config", &[ids::XML, ]),
+        ("guardfile", &[ids::Ruby, ]),
+        ("brewfile", &[ids::Ruby, ]),
+        ("nukefile", &[ids::Nu, ]),
+        ("rakefile", &[ids::Ruby, ]),
+        ("earthfile", &[ids::Earthly, ]),
+        ("build.bazel", &[ids::Starlark, ]),
+        ("thorfile", &[ids::Ruby, ]),
+        ("puppetfile", &[ids::Ruby, ]),
+        ("caddyfile", &[ids::Caddyfile, ]),
+        ("modulefile", &[ids::Puppet, ]),
+        ("jakefile", &[ids::JavaScript, ]),
+        ("fastfile", &[ids::Ruby, ]),
+        ("rexfile", &[ids::Perl, ]),
+        ("web.config", &[ids::XML, ]),
+        ("gemfile.lock", &[ids::Gemfile_lock, ]),
+        ("settings.stylecop", &[ids::XML, ]),
+        ("makefile.inc", &[ids::Makefile, ]),
+        ("makefile", &[ids::Makefile, ]),
+        ("cargo.toml.orig", &[ids::TOML, ]),
+        ("makefile.wat", &[ids::Makefile, ]),
+        ("lexer.x", &[ids::Lex, ]),
+        ("dockerfile", &[ids::Dockerfile, ]),
+        ("makefile.frag", &[ids::Makefile, ]),
+        ("license.mysql", &[ids::Text, ]),
+        ("module.bazel", &[ids::Starlark, ]),
+        ("cask", &[ids::Emacs_Lisp, ]),
+        ("snakefile", &[ids::Snakemake, ]),
+        ("jenkinsfile", &[ids::Groovy, ]),
+        ("readme.mysql", &[ids::Text, ]),
+        ("emakefile", &[ids::Erlang, ]),
+        ("steepfile", &[ids::Ruby, ]),
+        ("vagrantfile", &[ids::Ruby, ]),
+        ("justfile", &[ids::Just, ]),
+        ("bsdmakefile", &[ids::Makefile, ]),
+        ("mavenfile", &[ids::Ruby, ]),
+        ("readme.me", &[ids::Text, ]),
+        ("capfile", &[ids::Ruby, ]),
+        ("xcompose", &[ids::XCompose, ]),
+        ("web.release.config", &[ids::XML, ]),
+        ("sconscript", &[ids::Python, ]),
+        ("sha256sums.txt", &[ids::Checksums, ]),
+        ("tiltfile", &[ids::Starlark, ]),
+        (".xcompose", &[ids::XCompose, ]),
+        ("deliverfile", &[ids::Ruby, ]),
+        ("project.ede", &[ids::Emacs_Lisp, ]),
+        ("app.config", &[ids::XML, ]),
+        ("makefile.boot", &[ids::Makefile, ]),
+        ("cakefile", &[ids::CoffeeScript, ]),
+        ("fakefile", &[ids::Fancy, ]),
+        ("snapfile", &[ids::Ruby, ]),
+        ("makefile.am", &[ids::Makefile, ]),
+        ("jarfile", &[ids::Ruby, ]),
+        ("gnumakefile", &[ids::Makefile, ]),
+        ("gemfile", &[ids::Ruby, ]),
+        ("workspace.bazel", &[ids::Starlark, ]),
+        ("install.mysql", &[ids::Text, ]),
+        ("module.bazel.lock", &[ids::JSON, ]),
+        ("procfile", &[ids::Procfile, ]),
+        ("citation.cff", &[ids::YAML, ]),
+        ("kbuild", &[ids::Makefile, ]),
+        ("copyright.regex", &[ids::Text, ]),
+        (".justfile", &[ids::Just, ]),
+        ("readme.nss", &[ids::Text, ]),
+        ("makefile.pl", &[ids::Perl, ]),
+        ("singularity", &[ids::Singularity, ]),
+        ("pipfile.lock", &[ids::JSON, ]),
+        ("containerfile", &[ids::Dockerfile, ]),
+        ("podfile", &[ids::Ruby, ]),
+        ("gopkg.lock", &[ids::TOML, ]),
+        ("dangerfile", &[ids::Ruby, ]),
+        ("makefile.in", &[ids::Makefile, ]),
+        ("notebook", &[ids::Jupyter_Notebook, ]),
+        ("slakefile", &[ids::LiveScript, ]),
+        (".rprofile", &[ids::R, ]),
+        ("phakefile", &[ids::PHP, ]),
+        ("buildfile", &[ids::Ruby, ]),
+        ("pipfile", &[ids::TOML, ]),
+        ("sconstruct", &[ids::Python, ]),
+        ("cargo.lock", &[ids::TOML, ]),
+        ("copying.regex", &[ids::Text, ]),
+    ],
+}
+};
+
+static FILENAME_PATTERNS: &[(&str, &[crate::Language])] = {
+    use crate::ids;
+    &[
+        ("dockerfile.*", &[ids::Dockerfile, ]),
+        ("dockerfile-*", &[ids::Dockerfile, ]),
+        ("containerfile.*", &[ids::Dockerfile, ]),
+        ("makefile.*", &[ids::Makefile, ]),
+        ("jenkinsfile.*", &[ids::Groovy, ]),
+        ("*.build", &[ids::Starlark, ]),
+        ("*.build.bazel", &[ids::Starlark, ]),
+        ("requirements-*.txt", &[ids::Pip_Requirements, ]),
+        ("requirements/*.txt", &[ids::Pip_Requirements, ]),
+        (".github/workflows/*.yml", &[ids::YAML, ]),
+        (".github/workflows/*.yaml", &[ids::YAML, ]),
+    ]
+};
+
@@ -4,9 +4,36 @@
 use phf_shared::{PhfBorrow, PhfHash};
 use std::{convert::TryFrom, fmt, hash::Hasher};
 
+#[cfg(feature = "breakdown")]
+mod breakdown;
 mod detectors;
 mod filters;
+mod lines;
+mod registry;
+mod segments;
 
+#[cfg(feature = "breakdown")]
+pub use breakdown::{
+    get_language_breakdown, get_language_breakdown_with_details,
+    get_language_breakdown_with_skipped, FileDetection, SkippedFile,
+};
+pub use detectors::{
+    detect, detect_bytes, detect_bytes_with_options, detect_explain, detect_notebook_language,
+    detect_notebook_language_with_options, detect_snippet, detect_snippet_with_options,
+    detect_with_options, languages_by_extension, ClassifierTokens, DetectOptions, Detection,
+    Explanation, Guess, HeuristicRule, ModelMode, ReadLimit, RuleOutcome, SnippetOptions, Stage,
+    StageExplanation, TokenInfluence,
+};
+pub use filters::is_generated;
+#[cfg(feature = "breakdown")]
+pub use filters::{is_documentation, is_vendor};
+/// Training and serialization of custom classifier models, see [`DetectOptions`].
+pub use langur_classifier as classifier;
+pub use lines::{count_lines, LineStats};
+pub use registry::{CustomLanguage, LanguageRegistry, RegistryError};
+pub use segments::{detect_segments, detect_segments_with_options, Segment};
+
+#[cfg(feature = "cli")]
 #[doc(hidden)]
 pub mod cli;
 
@@ -14,10 +41,58 @@ include!("generated/languages.rs");
 
 impl std::fmt::Debug for Language {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "ids::{}", LANGUAGE_DATA_MAP.get(self).unwrap().name)
+        match LANGUAGE_DATA_MAP.get(self) {
+            Some(data) => write!(f, "ids::{}", data.name),
+            None => write!(f, "Language({:?})", self.name()),
+        }
     }
 }
 
+impl Language {
+    /// The name of the language, as listed in Linguist's languages.yml file
+    /// or as registered with a [`LanguageRegistry`].
+    pub fn name(&self) -> &'static str {
+        language_data(self).name
+    }
+
+    /// The `language_id` from Linguist's languages.yml file, which is stable
+    /// across releases. `Language::try_from` does the reverse.
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    /// Categorization for the language.
+    pub fn language_type(&self) -> LanguageType {
+        language_data(self).language_type
+    }
+
+    /// The css hex color used to represent the language on github. For example, "#dea584".
+    pub fn color(&self) -> Option<&'static str> {
+        language_data(self).color
+    }
+
+    /// The parent language. For example, the group for TSX would be TypeScript.
+    pub fn group(&self) -> Option<Language> {
+        language_data(self).group
+    }
+
+    /// Non-empty list of aliases allowed for this language.
+    ///
+    /// Always contains at least the lowercased name of the language.
+    pub fn aliases(&self) -> &'static [&'static str] {
+        language_data(self).aliases
+    }
+}
+
+/// Looks up the data for built-in languages as well as the ones registered
+/// at runtime.
+pub(crate) fn language_data(language: &Language) -> &'static LanguageData {
+    LANGUAGE_DATA_MAP
+        .get(language)
+        .or_else(|| registry::custom_language_data(language))
+        .expect("languages are either built-in or registered")
+}
+
 impl PhfBorrow<Language> for Language {
     fn borrow(&self) -> &Language {
         self
@@ -40,9 +115,31 @@ impl TryFrom<i64> for Language {
     }
 }
 
+/// Looks up a non-deprecated built-in language by its name, which is case-sensitive.
+///
+/// # Examples
+/// ```
+/// use langur::{ids, Language};
+/// use std::convert::TryFrom;
+///
+/// assert_eq!(Language::try_from("Rust"), Ok(ids::Rust));
+/// assert_eq!(Language::try_from("rust"), Err(()));
+/// ```
+impl TryFrom<&str> for Language {
+    type Error = ();
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        Language::VARIANTS
+            .iter()
+            .find(|language| language.name() == name)
+            .copied()
+            .ok_or(())
+    }
+}
+
 /// The set of possible language types
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
-enum LanguageType {
+#[derive(Debug, Copy, Clone, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LanguageType {
     Data,
     Markup,
     Programming,
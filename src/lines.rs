@@ -0,0 +1,169 @@
+//! Counting lines of code, comments and blank lines, using the comments
+//! found by the tokenizer with the language's dialect.
+
+use std::ops::{Add, AddAssign, Range};
+
+use langur_tokenizer::{Dialect, Token, Tokenizer};
+
+use crate::Language;
+
+/// The number of lines of code, comments and blank lines in a file,
+/// see [`count_lines`].
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct LineStats {
+    /// Lines with anything other than comments and whitespace.
+    pub code: usize,
+    /// Lines with only comments and whitespace.
+    pub comment: usize,
+    /// Lines with only whitespace.
+    pub blank: usize,
+}
+
+impl LineStats {
+    /// Returns the total number of lines.
+    pub fn lines(&self) -> usize {
+        self.code + self.comment + self.blank
+    }
+}
+
+impl Add for LineStats {
+    type Output = LineStats;
+
+    fn add(self, other: LineStats) -> LineStats {
+        LineStats {
+            code: self.code + other.code,
+            comment: self.comment + other.comment,
+            blank: self.blank + other.blank,
+        }
+    }
+}
+
+impl AddAssign for LineStats {
+    fn add_assign(&mut self, other: LineStats) {
+        *self = *self + other;
+    }
+}
+
+/// Counts the lines of code, comments and blank lines in `content`.
+///
+/// Comments are found by the tokenizer with the [`Dialect`] of `language`,
+/// so e.g. `#include` is code in C. Lines with both code and comments count
+/// as code. For languages without a dialect, like Lisps, every line that
+/// isn't blank counts as code.
+///
+/// # Examples
+/// ```
+/// use langur::{count_lines, ids, LineStats};
+///
+/// let content = "// Prints a greeting\nfn main() {\n\n    println!(\"hi\"); // Inline\n}\n";
+/// assert_eq!(
+///     count_lines(content, ids::Rust),
+///     LineStats { code: 3, comment: 1, blank: 1 }
+/// );
+/// ```
+pub fn count_lines(content: &str, language: Language) -> LineStats {
+    let comments = match Dialect::for_language(language.name()) {
+        Some(dialect) => comment_ranges(content, dialect),
+        None => vec![],
+    };
+
+    let mut stats = LineStats::default();
+    // The first comment that doesn't end before the current position.
+    let mut comment = 0;
+    let mut start = 0;
+    for line in content.split_inclusive('\n') {
+        let mut has_code = false;
+        let mut has_comment = false;
+        for (offset, ch) in line.char_indices() {
+            let position = start + offset;
+            while comment < comments.len() && comments[comment].end <= position {
+                comment += 1;
+            }
+            if ch.is_whitespace() {
+                continue;
+            }
+            if comment < comments.len() && comments[comment].start <= position {
+                has_comment = true;
+            } else {
+                has_code = true;
+            }
+        }
+        if has_code {
+            stats.code += 1;
+        } else if has_comment {
+            stats.comment += 1;
+        } else {
+            stats.blank += 1;
+        }
+        start += line.len();
+    }
+    stats
+}
+
+/// Returns the byte ranges of the comments in `content`.
+fn comment_ranges(content: &str, dialect: &Dialect) -> Vec<Range<usize>> {
+    Tokenizer::with_dialect(content, dialect)
+        .tokens_with_spans()
+        .filter_map(|(token, span)| match token {
+            Token::LineComment(..) | Token::BlockComment(..) => Some(span),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids;
+
+    #[test]
+    fn test_count_lines() {
+        let content = "/*\n * Docs\n */\n#include <stdio.h>\n\nint main() { /* inline */\n    return 0; // done\n}\n";
+        assert_eq!(
+            count_lines(content, ids::C),
+            LineStats {
+                code: 4,
+                comment: 3,
+                blank: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_count_lines_comment_syntax() {
+        let content = "# Title\n\nx = 1 // 2\n// not a comment\n";
+        assert_eq!(
+            count_lines(content, ids::Python),
+            LineStats {
+                code: 2,
+                comment: 1,
+                blank: 1
+            }
+        );
+        // Strings aren't comments.
+        assert_eq!(count_lines("s = \"# no\"\n", ids::Python).code, 1);
+        assert_eq!(
+            count_lines("--[[\n block\n]]\nx = '--'\n", ids::Lua),
+            LineStats {
+                code: 1,
+                comment: 3,
+                blank: 0
+            }
+        );
+        // Without a known syntax, everything is code.
+        assert_eq!(
+            count_lines("; comment\n(+ 1 2)\n\n", ids::Common_Lisp),
+            LineStats {
+                code: 2,
+                comment: 0,
+                blank: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_count_lines_without_trailing_newline() {
+        assert_eq!(count_lines("", ids::Rust), LineStats::default());
+        assert_eq!(count_lines("-- query\nSELECT 1", ids::SQL).lines(), 2);
+    }
+}
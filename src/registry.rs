@@ -0,0 +1,853 @@
+//! Languages registered at runtime, on top of the built-in ones from Linguist.
+
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    error, fmt, fs, io,
+    path::Path,
+    sync::RwLock,
+};
+
+use crate::{
+    detectors::heuristics::{CustomPattern, CustomRule, HeuristicsKey},
+    Language, LanguageData, LanguageType, LANGUAGE_ID_SET,
+};
+
+lazy_static! {
+    /// Data for every language registered at runtime, shared by all registries
+    /// so that [`Language::name`] works for them too. Entries are never removed,
+    /// and registering the same language again reuses its entry.
+    static ref CUSTOM_LANGUAGE_DATA: RwLock<HashMap<Language, &'static LanguageData>> =
+        RwLock::new(HashMap::new());
+}
+
+pub(crate) fn custom_language_data(language: &Language) -> Option<&'static LanguageData> {
+    CUSTOM_LANGUAGE_DATA.read().unwrap().get(language).copied()
+}
+
+/// A language to add to a [`LanguageRegistry`].
+///
+/// # Examples
+/// ```
+/// use langur::{CustomLanguage, LanguageRegistry, LanguageType};
+///
+/// let mut language = CustomLanguage::new("Widget Rules", LanguageType::Programming);
+/// language.extensions = vec![".wrules".to_owned()];
+///
+/// let mut registry = LanguageRegistry::new();
+/// let widget_rules = registry.add_language(language).unwrap();
+/// assert_eq!(widget_rules.name(), "Widget Rules");
+/// ```
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub struct CustomLanguage {
+    pub name: String,
+    pub language_type: LanguageType,
+    /// The css hex color used to represent the language. For example, "#dea584".
+    pub color: Option<String>,
+    /// Name of the parent language.
+    pub group: Option<String>,
+    pub aliases: Vec<String>,
+    /// Extensions, including the leading dot. For example, ".rs".
+    pub extensions: Vec<String>,
+    /// Filenames, which also match case-insensitively if they're in mixed
+    /// case like `Widgetfile`. Filenames with a `*` are path patterns like
+    /// the built-in `Dockerfile.*`, where `*` matches anything but `/`.
+    pub filenames: Vec<String>,
+    pub interpreters: Vec<String>,
+    /// Linguist's language_id. If missing, a negative id is derived from the
+    /// name, which can't collide with the ids of Linguist's languages.
+    pub language_id: Option<i64>,
+}
+
+impl CustomLanguage {
+    pub fn new(name: &str, language_type: LanguageType) -> Self {
+        CustomLanguage {
+            name: name.to_owned(),
+            language_type,
+            color: None,
+            group: None,
+            aliases: Vec::new(),
+            extensions: Vec::new(),
+            filenames: Vec::new(),
+            interpreters: Vec::new(),
+            language_id: None,
+        }
+    }
+}
+
+/// The languages that detection can pick from: the built-in languages plus
+/// the ones added to the registry.
+///
+/// Adding a language with the same name as a built-in language adds its
+/// extensions, filenames and interpreters to the built-in language.
+/// Extensions, filenames and interpreters shared with other languages make
+/// both languages candidates, which are then narrowed down by heuristics
+/// and the classifier, the same way as for the built-in languages.
+///
+/// Pass a registry to detection through [`DetectOptions`](crate::DetectOptions).
+#[derive(Debug, Clone, Default)]
+pub struct LanguageRegistry {
+    languages: Vec<Language>,
+    extensions: HashMap<String, Vec<Language>>,
+    filenames: HashMap<String, Vec<Language>>,
+    folded_filenames: HashMap<String, Vec<Language>>,
+    /// Lowercased, in the order they were added.
+    filename_patterns: Vec<(String, Vec<Language>)>,
+    interpreters: HashMap<String, Vec<Language>>,
+    heuristics: HashMap<String, Vec<CustomRule>>,
+}
+
+impl LanguageRegistry {
+    /// Creates a registry with only the built-in languages.
+    pub fn new() -> Self {
+        LanguageRegistry::default()
+    }
+
+    /// The languages that were added to this registry, in the order they
+    /// were first added.
+    pub fn custom_languages(&self) -> &[Language] {
+        &self.languages
+    }
+
+    /// Finds a built-in or added language by name.
+    pub fn find(&self, name: &str) -> Option<Language> {
+        self.languages
+            .iter()
+            .chain(Language::VARIANTS.iter())
+            .find(|language| language.name() == name)
+            .copied()
+    }
+
+    /// Finds the built-in or added languages with a name or alias that
+    /// matches `alias` case-insensitively, like the names used by modelines,
+    /// code fences and `lang` attributes.
+    pub(crate) fn find_by_alias(&self, alias: &str) -> Vec<Language> {
+        let alias = alias.to_lowercase();
+        Language::VARIANTS
+            .iter()
+            .chain(self.languages.iter())
+            .filter(|language| {
+                language.name().to_lowercase() == alias
+                    || language.aliases().contains(&alias.as_str())
+            })
+            .copied()
+            .collect()
+    }
+
+    pub fn add_language(&mut self, language: CustomLanguage) -> Result<Language, RegistryError> {
+        let id = match Language::VARIANTS
+            .iter()
+            .find(|built_in| built_in.name() == language.name)
+        {
+            Some(built_in) => *built_in,
+            None => self.register(&language)?,
+        };
+
+        for extension in language.extensions.iter() {
+            add_unique(&mut self.extensions, extension.to_ascii_lowercase(), id);
+        }
+        for filename in language.filenames.iter() {
+            if filename.contains('*') {
+                let pattern = filename.to_lowercase();
+                match self
+                    .filename_patterns
+                    .iter_mut()
+                    .find(|(existing, _)| *existing == pattern)
+                {
+                    Some((_, languages)) if languages.contains(&id) => {}
+                    Some((_, languages)) => languages.push(id),
+                    None => self.filename_patterns.push((pattern, vec![id])),
+                }
+                continue;
+            }
+            if is_mixed_case(filename) {
+                add_unique(&mut self.folded_filenames, filename.to_lowercase(), id);
+            }
+            add_unique(&mut self.filenames, filename.clone(), id);
+        }
+        for interpreter in language.interpreters.iter() {
+            add_unique(&mut self.interpreters, interpreter.clone(), id);
+        }
+        if !self.languages.contains(&id) {
+            self.languages.push(id);
+        }
+        Ok(id)
+    }
+
+    /// Adds a heuristic for files with the given extension. Heuristics added
+    /// to the registry are checked before the built-in ones, in the order they
+    /// were added. A heuristic only applies if all of `languages` are
+    /// candidates, and narrows the candidates down to `languages` if the PCRE2
+    /// `pattern` matches or is None.
+    pub fn add_heuristic(
+        &mut self,
+        extension: &str,
+        languages: &[Language],
+        pattern: Option<&str>,
+    ) -> Result<(), RegistryError> {
+        let extension = extension.to_ascii_lowercase();
+        self.add_custom_rule(HeuristicsKey::Extension(&extension), languages, pattern)
+    }
+
+    /// Same as [`add_heuristic`](Self::add_heuristic), but for files with
+    /// exactly the given name, like `HOSTS`, which are checked when the
+    /// extension's rules don't settle on a language.
+    pub fn add_filename_heuristic(
+        &mut self,
+        filename: &str,
+        languages: &[Language],
+        pattern: Option<&str>,
+    ) -> Result<(), RegistryError> {
+        self.add_custom_rule(HeuristicsKey::Filename(filename), languages, pattern)
+    }
+
+    /// Same as [`add_heuristic`](Self::add_heuristic), but for scripts run
+    /// by the given interpreter, like `parrot`, which are checked after the
+    /// extension's and filename's rules.
+    pub fn add_interpreter_heuristic(
+        &mut self,
+        interpreter: &str,
+        languages: &[Language],
+        pattern: Option<&str>,
+    ) -> Result<(), RegistryError> {
+        self.add_custom_rule(HeuristicsKey::Interpreter(interpreter), languages, pattern)
+    }
+
+    fn add_custom_rule(
+        &mut self,
+        key: HeuristicsKey,
+        languages: &[Language],
+        pattern: Option<&str>,
+    ) -> Result<(), RegistryError> {
+        let pattern = pattern.map(CustomPattern::positive).transpose()?;
+        self.heuristics
+            .entry(key.table_key().into_owned())
+            .or_default()
+            .push(CustomRule {
+                languages: languages.to_vec(),
+                pattern,
+            });
+        Ok(())
+    }
+
+    /// Adds the languages in a file with the same format as Linguist's
+    /// languages.yml, returning them sorted by name.
+    pub fn load_languages_yml<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<Vec<Language>, RegistryError> {
+        self.add_languages_yml(&fs::read_to_string(path)?)
+    }
+
+    /// Same as [`load_languages_yml`](Self::load_languages_yml), but with
+    /// the content of the file. If any of the languages can't be added, none
+    /// of them are.
+    pub fn add_languages_yml(&mut self, yaml: &str) -> Result<Vec<Language>, RegistryError> {
+        let parsed: BTreeMap<String, ParsedLanguage> = serde_yaml::from_str(yaml)?;
+
+        // Languages can be grouped under languages from the same file, so
+        // add parents before their children.
+        let mut pending: Vec<_> = parsed.into_iter().collect();
+        let mut languages = Vec::new();
+        while !pending.is_empty() {
+            let pending_names: Vec<String> = pending.iter().map(|(name, _)| name.clone()).collect();
+            let (ready, rest): (Vec<_>, Vec<_>) = pending.into_iter().partition(|(_, parsed)| {
+                !matches!(&parsed.group, Some(group) if pending_names.contains(group))
+            });
+            if ready.is_empty() {
+                // The remaining languages are grouped under each other.
+                let group = rest[0].1.group.clone().unwrap();
+                return Err(RegistryError::UnknownLanguage(group));
+            }
+            languages.extend(
+                ready
+                    .into_iter()
+                    .map(|(name, parsed)| parsed.into_custom_language(name)),
+            );
+            pending = rest;
+        }
+
+        for (index, language) in languages.iter().enumerate() {
+            self.check(language, &languages[..index])?;
+        }
+        let mut added = languages
+            .into_iter()
+            .map(|language| self.add_language(language))
+            .collect::<Result<Vec<_>, _>>()?;
+        added.sort_by_key(|language| language.name());
+        Ok(added)
+    }
+
+    /// Adds the heuristics in a file with the same format as Linguist's
+    /// heuristics.yml. Languages are looked up by name, so any custom
+    /// languages used in the heuristics need to be added first.
+    pub fn load_heuristics_yml<P: AsRef<Path>>(&mut self, path: P) -> Result<(), RegistryError> {
+        self.add_heuristics_yml(&fs::read_to_string(path)?)
+    }
+
+    /// Same as [`load_heuristics_yml`](Self::load_heuristics_yml), but with
+    /// the content of the file.
+    pub fn add_heuristics_yml(&mut self, yaml: &str) -> Result<(), RegistryError> {
+        let heuristics: ParsedHeuristics = serde_yaml::from_str(yaml)?;
+        for disambiguation in heuristics.disambiguations.iter() {
+            let rules = disambiguation
+                .rules
+                .iter()
+                .map(|rule| {
+                    let languages = rule
+                        .language
+                        .iter()
+                        .map(|name| {
+                            self.find(name)
+                                .ok_or_else(|| RegistryError::UnknownLanguage(name.clone()))
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let pattern = rule
+                        .pattern
+                        .as_ref()
+                        .map(|pattern| pattern.compile(&heuristics.named_patterns))
+                        .transpose()?;
+                    Ok(CustomRule { languages, pattern })
+                })
+                .collect::<Result<Vec<_>, RegistryError>>()?;
+
+            let extensions: Vec<String> = disambiguation
+                .extensions
+                .iter()
+                .map(|extension| extension.to_ascii_lowercase())
+                .collect();
+            let keys = extensions
+                .iter()
+                .map(|extension| HeuristicsKey::Extension(extension))
+                .chain(
+                    disambiguation
+                        .filenames
+                        .iter()
+                        .map(|filename| HeuristicsKey::Filename(filename)),
+                )
+                .chain(
+                    disambiguation
+                        .interpreters
+                        .iter()
+                        .map(|interpreter| HeuristicsKey::Interpreter(interpreter)),
+                );
+            for key in keys {
+                self.heuristics
+                    .entry(key.table_key().into_owned())
+                    .or_default()
+                    .extend(rules.iter().cloned());
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn extension_languages(&self, extension: &str) -> &[Language] {
+        lookup(&self.extensions, extension)
+    }
+
+    pub(crate) fn extension_key(&self, extension: &str) -> Option<&str> {
+        self.extensions
+            .get_key_value(extension)
+            .map(|(key, _)| key.as_str())
+    }
+
+    pub(crate) fn filename_languages(&self, filename: &str) -> &[Language] {
+        lookup(&self.filenames, filename)
+    }
+
+    /// The languages of the mixed-case filenames that lowercase to `filename`.
+    pub(crate) fn folded_filename_languages(&self, filename: &str) -> &[Language] {
+        lookup(&self.folded_filenames, filename)
+    }
+
+    pub(crate) fn filename_patterns(&self) -> &[(String, Vec<Language>)] {
+        &self.filename_patterns
+    }
+
+    pub(crate) fn interpreter_languages(&self, interpreter: &str) -> &[Language] {
+        lookup(&self.interpreters, interpreter)
+    }
+
+    /// The rules for a [`HeuristicsKey::table_key`].
+    pub(crate) fn heuristics(&self, key: &str) -> &[CustomRule] {
+        self.heuristics
+            .get(key)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Checks that [`add_language`](Self::add_language) would succeed for
+    /// `language` once the languages in `pending` are added before it.
+    fn check(
+        &self,
+        language: &CustomLanguage,
+        pending: &[CustomLanguage],
+    ) -> Result<(), RegistryError> {
+        if is_built_in(&language.name) {
+            return Ok(());
+        }
+        let id = custom_id(language);
+        let registered_name = custom_language_data(&id).map(|data| data.name);
+        let pending_name = pending
+            .iter()
+            .find(|other| !is_built_in(&other.name) && custom_id(other) == id)
+            .map(|other| other.name.as_str());
+        let other_name = registered_name
+            .or(pending_name)
+            .filter(|name| *name != language.name);
+        if LANGUAGE_ID_SET.contains(&id.id) || other_name.is_some() {
+            return Err(RegistryError::IdConflict {
+                name: language.name.clone(),
+                id: id.id,
+            });
+        }
+        match &language.group {
+            Some(group)
+                if self.find(group).is_none()
+                    && !pending.iter().any(|other| other.name == *group) =>
+            {
+                Err(RegistryError::UnknownLanguage(group.clone()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Adds the data for a language that isn't built-in, returning its id.
+    fn register(&self, language: &CustomLanguage) -> Result<Language, RegistryError> {
+        self.check(language, &[])?;
+        let id = custom_id(language);
+        let group = language.group.as_ref().and_then(|group| self.find(group));
+
+        let mut registered = CUSTOM_LANGUAGE_DATA.write().unwrap();
+        if let Some(data) = registered.get(&id) {
+            // Another thread may have registered the id since the check.
+            if data.name != language.name {
+                return Err(RegistryError::IdConflict {
+                    name: language.name.clone(),
+                    id: id.id,
+                });
+            }
+            return Ok(id);
+        }
+
+        let mut aliases: Vec<&'static str> = language
+            .aliases
+            .iter()
+            .map(|alias| leak(alias.clone()))
+            .collect();
+        let lowercase_name = language.name.to_lowercase();
+        if !language.aliases.contains(&lowercase_name) {
+            aliases.insert(0, leak(lowercase_name));
+        }
+        let data = LanguageData {
+            name: leak(language.name.clone()),
+            language_type: language.language_type,
+            color: language.color.clone().map(leak),
+            group,
+            aliases: Box::leak(aliases.into_boxed_slice()),
+        };
+        registered.insert(id, Box::leak(Box::new(data)));
+        Ok(id)
+    }
+}
+
+fn is_built_in(name: &str) -> bool {
+    Language::VARIANTS
+        .iter()
+        .any(|built_in| built_in.name() == name)
+}
+
+/// The id of a language that isn't built-in.
+fn custom_id(language: &CustomLanguage) -> Language {
+    Language {
+        id: language
+            .language_id
+            .unwrap_or_else(|| derive_id(&language.name)),
+    }
+}
+
+fn lookup<'a>(map: &'a HashMap<String, Vec<Language>>, key: &str) -> &'a [Language] {
+    map.get(key).map(Vec::as_slice).unwrap_or_default()
+}
+
+/// Whether `filename` is in mixed case, like `Dockerfile`. All-uppercase and
+/// all-lowercase names are conventions that don't fold, e.g. a script named
+/// `install` isn't the INSTALL text file.
+fn is_mixed_case(filename: &str) -> bool {
+    filename.to_lowercase() != filename && filename.to_uppercase() != filename
+}
+
+fn add_unique(map: &mut HashMap<String, Vec<Language>>, key: String, language: Language) {
+    let languages = map.entry(key).or_default();
+    if !languages.contains(&language) {
+        languages.push(language);
+    }
+}
+
+/// Appends the languages from `custom` that aren't already in `built_in`.
+pub(crate) fn merge_languages(built_in: &[Language], custom: &[Language]) -> Vec<Language> {
+    let mut languages = built_in.to_vec();
+    for language in custom {
+        if !languages.contains(language) {
+            languages.push(*language);
+        }
+    }
+    languages
+}
+
+/// FNV-1a hash of the name, mapped to a negative number.
+fn derive_id(name: &str) -> i64 {
+    let hash = name.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    -((hash >> 1) as i64) - 1
+}
+
+fn leak(s: String) -> &'static str {
+    Box::leak(s.into_boxed_str())
+}
+
+/// The errors that can happen when adding languages or heuristics to a
+/// [`LanguageRegistry`].
+#[derive(Debug)]
+pub enum RegistryError {
+    Io(io::Error),
+    Yaml(serde_yaml::Error),
+    /// The language's id is already used by a language with a different name.
+    IdConflict {
+        name: String,
+        id: i64,
+    },
+    /// A language referenced by name, e.g. as a group or in a heuristic,
+    /// doesn't exist.
+    UnknownLanguage(String),
+    UnknownNamedPattern(String),
+    InvalidPattern {
+        pattern: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::Io(e) => write!(f, "{}", e),
+            RegistryError::Yaml(e) => write!(f, "{}", e),
+            RegistryError::IdConflict { name, id } => {
+                write!(f, "{} has the same id as another language: {}", name, id)
+            }
+            RegistryError::UnknownLanguage(name) => write!(f, "unknown language: {}", name),
+            RegistryError::UnknownNamedPattern(name) => {
+                write!(f, "unknown named pattern: {}", name)
+            }
+            RegistryError::InvalidPattern { pattern, message } => {
+                write!(f, "invalid pattern {:?}: {}", pattern, message)
+            }
+        }
+    }
+}
+
+impl error::Error for RegistryError {}
+
+impl From<io::Error> for RegistryError {
+    fn from(e: io::Error) -> Self {
+        RegistryError::Io(e)
+    }
+}
+
+impl From<serde_yaml::Error> for RegistryError {
+    fn from(e: serde_yaml::Error) -> Self {
+        RegistryError::Yaml(e)
+    }
+}
+
+/// A single language entry in a languages.yml file.
+#[derive(Deserialize)]
+struct ParsedLanguage {
+    #[serde(rename = "type")]
+    language_type: LanguageType,
+    color: Option<String>,
+    group: Option<String>,
+    aliases: Option<Vec<String>>,
+    extensions: Option<Vec<String>>,
+    filenames: Option<Vec<String>>,
+    interpreters: Option<Vec<String>>,
+    language_id: Option<i64>,
+}
+
+impl ParsedLanguage {
+    fn into_custom_language(self, name: String) -> CustomLanguage {
+        CustomLanguage {
+            name,
+            language_type: self.language_type,
+            color: self.color,
+            group: self.group,
+            aliases: self.aliases.unwrap_or_default(),
+            extensions: self.extensions.unwrap_or_default(),
+            filenames: self.filenames.unwrap_or_default(),
+            interpreters: self.interpreters.unwrap_or_default(),
+            language_id: self.language_id,
+        }
+    }
+}
+
+// The heuristics.yml structures below mirror the ones in the codegen tool.
+
+#[derive(Deserialize)]
+struct ParsedHeuristics {
+    disambiguations: Vec<ParsedDisambiguation>,
+    #[serde(default)]
+    named_patterns: HashMap<String, MaybeMany<String>>,
+}
+
+#[derive(Deserialize)]
+struct ParsedDisambiguation {
+    #[serde(default)]
+    extensions: Vec<String>,
+    #[serde(default)]
+    filenames: Vec<String>,
+    #[serde(default)]
+    interpreters: Vec<String>,
+    rules: Vec<ParsedRule>,
+}
+
+#[derive(Deserialize)]
+struct ParsedRule {
+    language: MaybeMany<String>,
+    #[serde(flatten)]
+    pattern: Option<ParsedPattern>,
+}
+
+#[derive(Deserialize)]
+enum ParsedPattern {
+    #[serde(rename = "and")]
+    And(Vec<ParsedPattern>),
+    #[serde(rename = "named_pattern")]
+    Named(String),
+    #[serde(rename = "negative_pattern")]
+    Negative(String),
+    #[serde(rename = "pattern")]
+    Positive(MaybeMany<String>),
+}
+
+impl ParsedPattern {
+    fn compile(
+        &self,
+        named_patterns: &HashMap<String, MaybeMany<String>>,
+    ) -> Result<CustomPattern, RegistryError> {
+        match self {
+            ParsedPattern::And(patterns) => Ok(CustomPattern::And(
+                patterns
+                    .iter()
+                    .map(|pattern| pattern.compile(named_patterns))
+                    .collect::<Result<_, _>>()?,
+            )),
+            ParsedPattern::Named(name) => match named_patterns.get(name) {
+                Some(patterns) => compile_positive(patterns),
+                None => Err(RegistryError::UnknownNamedPattern(name.clone())),
+            },
+            ParsedPattern::Negative(pattern) => CustomPattern::negative(pattern),
+            ParsedPattern::Positive(patterns) => compile_positive(patterns),
+        }
+    }
+}
+
+fn compile_positive(patterns: &MaybeMany<String>) -> Result<CustomPattern, RegistryError> {
+    match patterns {
+        MaybeMany::One(pattern) => CustomPattern::positive(pattern),
+        MaybeMany::Many(patterns) => Ok(CustomPattern::Or(
+            patterns
+                .iter()
+                .map(|pattern| CustomPattern::positive(pattern))
+                .collect::<Result<_, _>>()?,
+        )),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MaybeMany<T> {
+    Many(Vec<T>),
+    One(T),
+}
+
+impl<T> MaybeMany<T> {
+    fn iter(&self) -> impl Iterator<Item = &T> {
+        match self {
+            MaybeMany::Many(values) => values.iter(),
+            MaybeMany::One(value) => std::slice::from_ref(value).iter(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids;
+
+    const LANGUAGES_YML: &str = r##"
+Widget Rules:
+  type: programming
+  color: "#123456"
+  extensions:
+  - ".wrules"
+  - ".h"
+  filenames:
+  - Widgetfile
+  interpreters:
+  - widgetc
+Widget Rules Test:
+  type: programming
+  group: Widget Rules
+  extensions:
+  - ".wrtest"
+Rust:
+  type: programming
+  extensions:
+  - ".rust"
+"##;
+
+    #[test]
+    fn test_add_languages_yml() {
+        let mut registry = LanguageRegistry::new();
+        let languages = registry.add_languages_yml(LANGUAGES_YML).unwrap();
+        let names: Vec<_> = languages.iter().map(|language| language.name()).collect();
+        assert_eq!(names, vec!["Rust", "Widget Rules", "Widget Rules Test"]);
+
+        let widget_rules = registry.find("Widget Rules").unwrap();
+        let data = crate::language_data(&widget_rules);
+        assert_eq!(data.color, Some("#123456"));
+        assert_eq!(data.language_type, LanguageType::Programming);
+        assert_eq!(data.aliases, &["widget rules"]);
+        assert!(widget_rules.id < 0);
+
+        let test = registry.find("Widget Rules Test").unwrap();
+        assert_eq!(crate::language_data(&test).group, Some(widget_rules));
+
+        assert_eq!(registry.extension_languages(".wrules"), &[widget_rules]);
+        assert_eq!(registry.extension_languages(".rust"), &[ids::Rust]);
+        assert_eq!(registry.filename_languages("Widgetfile"), &[widget_rules]);
+        assert_eq!(registry.interpreter_languages("widgetc"), &[widget_rules]);
+
+        // Adding the same languages again doesn't duplicate them.
+        let again = registry.add_languages_yml(LANGUAGES_YML).unwrap();
+        assert_eq!(again, languages);
+        assert_eq!(registry.custom_languages().len(), 3);
+        assert_eq!(registry.extension_languages(".wrules"), &[widget_rules]);
+    }
+
+    #[test]
+    fn test_add_language_id_conflict() {
+        let mut registry = LanguageRegistry::new();
+        let mut language = CustomLanguage::new("Not Rust", LanguageType::Programming);
+        language.language_id = Some(327); // Rust's id
+        assert!(matches!(
+            registry.add_language(language),
+            Err(RegistryError::IdConflict { .. })
+        ));
+
+        let mut language = CustomLanguage::new("Widget Group", LanguageType::Programming);
+        language.group = Some("Missing Parent".to_owned());
+        assert!(matches!(
+            registry.add_language(language),
+            Err(RegistryError::UnknownLanguage(_))
+        ));
+    }
+
+    #[test]
+    fn test_add_languages_yml_error() {
+        let mut registry = LanguageRegistry::new();
+        // The second language has Rust's id, so the first isn't added either.
+        let yaml = r#"
+Doohickey:
+  type: programming
+  extensions:
+  - ".doohickey"
+Not Rust Either:
+  type: programming
+  language_id: 327
+"#;
+        assert!(matches!(
+            registry.add_languages_yml(yaml),
+            Err(RegistryError::IdConflict { .. })
+        ));
+        assert!(registry.custom_languages().is_empty());
+        assert!(registry.extension_languages(".doohickey").is_empty());
+        let doohickey = CustomLanguage::new("Doohickey", LanguageType::Programming);
+        assert!(custom_language_data(&custom_id(&doohickey)).is_none());
+
+        // Ids also conflict with the other languages in the same file.
+        let yaml = r#"
+Thingamajig:
+  type: programming
+  language_id: -5
+Whatsit:
+  type: programming
+  language_id: -5
+"#;
+        assert!(matches!(
+            registry.add_languages_yml(yaml),
+            Err(RegistryError::IdConflict { .. })
+        ));
+        assert!(registry.custom_languages().is_empty());
+        assert!(custom_language_data(&Language { id: -5 }).is_none());
+    }
+
+    #[test]
+    fn test_add_heuristics_yml() {
+        let mut registry = LanguageRegistry::new();
+        registry.add_languages_yml(LANGUAGES_YML).unwrap();
+        registry
+            .add_heuristics_yml(
+                r#"
+disambiguations:
+- extensions: [".h"]
+  rules:
+  - language: Widget Rules
+    and:
+    - named_pattern: widget
+    - negative_pattern: '^#include'
+named_patterns:
+  widget:
+  - '^widget\s'
+  - '^gadget\s'
+"#,
+            )
+            .unwrap();
+        let rules = registry.heuristics(".h");
+        assert_eq!(rules.len(), 1);
+        assert!(rules[0].matches("gadget foo"));
+        assert!(!rules[0].matches("#include <widget.h>\nwidget foo"));
+
+        registry
+            .add_heuristics_yml(
+                r#"
+disambiguations:
+- filenames: [Widgetfile]
+  interpreters: [widgetsh]
+  rules:
+  - language: Widget Rules
+    pattern: '^widget\s'
+"#,
+            )
+            .unwrap();
+        assert_eq!(registry.heuristics("filename:Widgetfile").len(), 1);
+        assert_eq!(registry.heuristics("interpreter:widgetsh").len(), 1);
+        assert!(registry.heuristics("filename:widgetfile").is_empty());
+
+        assert!(matches!(
+            registry.add_heuristics_yml(
+                "disambiguations:\n- extensions: ['.h']\n  rules:\n  - language: Nope\n"
+            ),
+            Err(RegistryError::UnknownLanguage(_))
+        ));
+        assert!(matches!(
+            registry.add_heuristics_yml(
+                "disambiguations:\n- extensions: ['.h']\n  rules:\n  - language: C\n    pattern: '('\n"
+            ),
+            Err(RegistryError::InvalidPattern { .. })
+        ));
+    }
+}
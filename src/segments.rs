@@ -0,0 +1,216 @@
+//! Splitting files that embed other languages, like Markdown with fenced
+//! code blocks or HTML with `<script>` tags, into parts in a single language.
+
+mod erb;
+mod html;
+mod markdown;
+pub(crate) mod notebook;
+
+use std::{ops::Range, path::Path};
+
+use crate::{detectors, ids, DetectOptions, Language, LanguageRegistry};
+
+/// A part of a file in a single language, see [`detect_segments`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    /// The byte range of the segment in the file's content.
+    pub range: Range<usize>,
+    pub language: Language,
+}
+
+/// Splits a file into segments by language, for files whose language embeds
+/// code in other languages:
+///
+/// - Markdown, RMarkdown and MDX: fenced code blocks, in the language named
+///   by their info string, e.g. ```` ```rust ````.
+/// - HTML, Vue and Svelte: `<script>` and `<style>` elements, in the language
+///   named by their `lang` or `type` attribute, defaulting to JavaScript
+///   and CSS.
+/// - HTML+ERB: Ruby code in `<% %>` tags, along with the above.
+/// - Jupyter notebooks: the source of code cells in the kernel's language and
+///   of Markdown cells. Ranges cover the JSON-encoded source.
+///
+/// The language of the file itself is detected like [`detect_bytes`](crate::detect_bytes),
+/// and the segments cover all of `content` in order, with the parts that
+/// aren't embedded code in the file's language. Blocks in languages that
+/// can't be determined are left in the file's language. Returns a single
+/// segment for files in other languages, and no segments if the language
+/// of the file can't be determined.
+///
+/// # Examples
+/// ```
+/// use langur::{detect_segments, ids};
+/// use std::path::Path;
+///
+/// let content = "# Example\n\n```rust\nfn main() {}\n```\n";
+/// let segments = detect_segments(Path::new("README.md"), content);
+/// assert_eq!(segments[1].language, ids::Rust);
+/// assert_eq!(&content[segments[1].range.clone()], "fn main() {}\n");
+/// ```
+pub fn detect_segments(path: &Path, content: &str) -> Vec<Segment> {
+    detect_segments_with_options(path, content, &DetectOptions::default())
+}
+
+/// Same as [`detect_segments`], but with customized behavior for detecting
+/// the language of the file, and the registry from `options` for looking up
+/// the languages of embedded code.
+pub fn detect_segments_with_options(
+    path: &Path,
+    content: &str,
+    options: &DetectOptions,
+) -> Vec<Segment> {
+    let host = match detectors::detect_bytes_with_options(path, content.as_bytes(), options) {
+        Ok(Some(detection)) => detection.language(),
+        _ => return vec![],
+    };
+    let built_in_registry;
+    let registry = match options.registry.as_deref() {
+        Some(registry) => registry,
+        None => {
+            built_in_registry = LanguageRegistry::new();
+            &built_in_registry
+        }
+    };
+
+    let embedded = match host {
+        ids::Markdown | ids::RMarkdown | ids::MDX => markdown::fenced_code(content, registry),
+        ids::HTML | ids::Vue | ids::Svelte => html::script_and_style(content, registry),
+        ids::HTML_ERB => {
+            let ruby = erb::ruby_code(content);
+            let html = html::script_and_style(content, registry);
+            let mut embedded = subtract(html, &ruby);
+            embedded.extend(ruby);
+            embedded.sort_by_key(|(range, _)| range.start);
+            embedded
+        }
+        ids::Jupyter_Notebook => notebook::cells(content, registry),
+        _ => vec![],
+    };
+    fill_gaps(host, content.len(), embedded)
+}
+
+/// Removes `holes` from the ranges of `segments`, splitting them as needed.
+fn subtract(
+    segments: Vec<(Range<usize>, Language)>,
+    holes: &[(Range<usize>, Language)],
+) -> Vec<(Range<usize>, Language)> {
+    let mut result = Vec::new();
+    for (range, language) in segments {
+        let mut start = range.start;
+        for (hole, _) in holes {
+            if hole.end <= start || hole.start >= range.end {
+                continue;
+            }
+            if hole.start > start {
+                result.push((start..hole.start, language));
+            }
+            start = start.max(hole.end);
+        }
+        if start < range.end {
+            result.push((start..range.end, language));
+        }
+    }
+    result
+}
+
+/// Turns sorted, non-overlapping embedded segments into segments that cover
+/// `0..len`, with the gaps in the `host` language. Empty segments are dropped.
+fn fill_gaps(host: Language, len: usize, embedded: Vec<(Range<usize>, Language)>) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut position = 0;
+    for (range, language) in embedded {
+        if range.is_empty() {
+            continue;
+        }
+        if range.start > position {
+            segments.push(Segment {
+                range: position..range.start,
+                language: host,
+            });
+        }
+        segments.push(Segment {
+            range: range.clone(),
+            language,
+        });
+        position = range.end;
+    }
+    if position < len {
+        segments.push(Segment {
+            range: position..len,
+            language: host,
+        });
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn languages(path: &str, content: &str) -> Vec<(Language, String)> {
+        detect_segments(Path::new(path), content)
+            .into_iter()
+            .map(|segment| (segment.language, content[segment.range].to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn test_segments_cover_content() {
+        let content = "<p>hi</p>\n<script>let a = 1;</script>\n<style>p {}</style>\n";
+        let segments = detect_segments(Path::new("index.html"), content);
+        assert_eq!(segments.first().unwrap().range.start, 0);
+        assert_eq!(segments.last().unwrap().range.end, content.len());
+        for pair in segments.windows(2) {
+            assert_eq!(pair[0].range.end, pair[1].range.start);
+        }
+        assert_eq!(
+            segments
+                .iter()
+                .map(|segment| segment.language)
+                .collect::<Vec<_>>(),
+            vec![ids::HTML, ids::JavaScript, ids::HTML, ids::CSS, ids::HTML]
+        );
+    }
+
+    #[test]
+    fn test_segments_vue() {
+        let content = "<template>\n  <p>{{ a }}</p>\n</template>\n\n<script lang=\"ts\">\nexport default {}\n</script>\n\n<style lang=\"scss\" scoped>\np { a { b: c } }\n</style>\n";
+        assert_eq!(
+            languages("App.vue", content)
+                .into_iter()
+                .filter(|(language, _)| *language != ids::Vue)
+                .collect::<Vec<_>>(),
+            vec![
+                (ids::TypeScript, "\nexport default {}\n".to_owned()),
+                (ids::SCSS, "\np { a { b: c } }\n".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_segments_erb() {
+        let content = "<p><%= @name %></p>\n<script>var a = <%= @a %>;</script>\n";
+        assert_eq!(
+            languages("show.html.erb", content),
+            vec![
+                (ids::HTML_ERB, "<p><%=".to_owned()),
+                (ids::Ruby, " @name ".to_owned()),
+                (ids::HTML_ERB, "%></p>\n<script>".to_owned()),
+                (ids::JavaScript, "var a = <%=".to_owned()),
+                (ids::Ruby, " @a ".to_owned()),
+                (ids::JavaScript, "%>;".to_owned()),
+                (ids::HTML_ERB, "</script>\n".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_segments_other_languages() {
+        assert_eq!(
+            languages("main.rs", "fn main() {}\n"),
+            vec![(ids::Rust, "fn main() {}\n".to_owned())]
+        );
+        assert_eq!(languages("unknown", "???"), vec![]);
+        assert_eq!(languages("empty.md", ""), vec![]);
+    }
+}
@@ -0,0 +1,59 @@
+use std::ops::Range;
+
+use crate::{ids, Language};
+
+/// Returns the Ruby code in ERB tags like `<% if a %>` and `<%= a -%>`,
+/// without the tags' delimiters. Comments (`<%# %>`) and escaped tags
+/// (`<%% %>`) are skipped.
+pub(super) fn ruby_code(content: &str) -> Vec<(Range<usize>, Language)> {
+    let mut segments = Vec::new();
+    let mut position = 0;
+    while let Some(offset) = content[position..].find("<%") {
+        let open = position + offset + 2;
+        let rest = &content[open..];
+        let code_start = open + (rest.len() - rest.trim_start_matches(&['=', '-'][..]).len());
+        let (code_end, next) = match content[code_start..].find("%>") {
+            Some(offset) => (code_start + offset, code_start + offset + 2),
+            None => (content.len(), content.len()),
+        };
+        if !rest.starts_with('#') && !rest.starts_with('%') {
+            let code = &content[code_start..code_end];
+            let code_end = code_end - (code.len() - code.trim_end_matches('-').len());
+            segments.push((code_start..code_end, ids::Ruby));
+        }
+        position = next;
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code(content: &str) -> Vec<&str> {
+        ruby_code(content)
+            .into_iter()
+            .map(|(range, _)| &content[range])
+            .collect()
+    }
+
+    #[test]
+    fn test_ruby_code() {
+        assert_eq!(
+            code("<% if a %>\n<p><%= a.name -%></p>\n<%- end %>"),
+            vec![" if a ", " a.name ", " end "]
+        );
+        assert_eq!(code("<%== raw %>"), vec![" raw "]);
+    }
+
+    #[test]
+    fn test_skipped_tags() {
+        assert_eq!(code("<%# comment %><%% literal %>"), Vec::<&str>::new());
+        assert_eq!(code("<p>no tags</p>"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_unclosed_tag() {
+        assert_eq!(code("<p><%= a"), vec![" a"]);
+    }
+}
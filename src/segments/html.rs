@@ -0,0 +1,158 @@
+use std::ops::Range;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::{ids, Language, LanguageRegistry};
+
+lazy_static! {
+    static ref OPEN_TAG_RE: Regex = Regex::new(r"(?i)<(script|style|template)\b([^>]*)>").unwrap();
+    static ref SCRIPT_CLOSE_RE: Regex = Regex::new(r"(?i)</script\s*>").unwrap();
+    static ref STYLE_CLOSE_RE: Regex = Regex::new(r"(?i)</style\s*>").unwrap();
+    static ref TEMPLATE_CLOSE_RE: Regex = Regex::new(r"(?i)</template\s*>").unwrap();
+    static ref ATTRIBUTE_RE: Regex =
+        Regex::new(r#"(?i)(?:^|\s)(lang|type)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).unwrap();
+}
+
+/// Returns the contents of `<script>` and `<style>` elements, and of
+/// `<template>` elements with a `lang` attribute like in Vue components.
+/// Elements in languages that can't be determined are skipped.
+pub(super) fn script_and_style(
+    content: &str,
+    registry: &LanguageRegistry,
+) -> Vec<(Range<usize>, Language)> {
+    let mut segments = Vec::new();
+    let mut position = 0;
+    while let Some(captures) = OPEN_TAG_RE.captures(&content[position..]) {
+        let tag = captures[1].to_ascii_lowercase();
+        let close_re: &Regex = match tag.as_str() {
+            "script" => &SCRIPT_CLOSE_RE,
+            "style" => &STYLE_CLOSE_RE,
+            _ => &TEMPLATE_CLOSE_RE,
+        };
+        let body_start = position + captures.get(0).unwrap().end();
+        let (body_end, next) = match close_re.find_at(content, body_start) {
+            Some(close) => (close.start(), close.end()),
+            None => (content.len(), content.len()),
+        };
+        if let Some(language) = element_language(&tag, &captures[2], registry) {
+            segments.push((body_start..body_end, language));
+        }
+        position = next;
+    }
+    segments
+}
+
+fn element_language(tag: &str, attributes: &str, registry: &LanguageRegistry) -> Option<Language> {
+    let mut lang = None;
+    let mut mime = None;
+    for captures in ATTRIBUTE_RE.captures_iter(attributes) {
+        let value = captures
+            .get(2)
+            .or_else(|| captures.get(3))
+            .or_else(|| captures.get(4))
+            .map(|value| value.as_str().trim())
+            .unwrap_or_default();
+        if captures[1].eq_ignore_ascii_case("lang") {
+            lang = Some(value);
+        } else {
+            mime = Some(value);
+        }
+    }
+
+    if let Some(lang) = lang {
+        return registry.find_by_alias(lang).first().copied();
+    }
+    match (tag, mime) {
+        ("template", _) => None,
+        ("script", None) | ("script", Some("")) => Some(ids::JavaScript),
+        ("style", None) | ("style", Some("")) => Some(ids::CSS),
+        (_, Some(mime)) => mime_language(mime, registry),
+        _ => None,
+    }
+}
+
+/// The language of a MIME type like `text/javascript`, `module` or
+/// `application/x-typescript`.
+fn mime_language(mime: &str, registry: &LanguageRegistry) -> Option<Language> {
+    let mime = mime.to_ascii_lowercase();
+    let name = mime
+        .trim_start_matches("text/")
+        .trim_start_matches("application/")
+        .trim_start_matches("x-");
+    match name {
+        "module" | "babel" | "ecmascript" => Some(ids::JavaScript),
+        "json" | "ld+json" | "importmap" => Some(ids::JSON),
+        _ => registry.find_by_alias(name).first().copied(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn elements(content: &str) -> Vec<(&str, Language)> {
+        script_and_style(content, &LanguageRegistry::default())
+            .into_iter()
+            .map(|(range, language)| (&content[range], language))
+            .collect()
+    }
+
+    #[test]
+    fn test_script_and_style() {
+        let content = "<html><head><STYLE media=\"all\">p {}</STYLE></head>\n<body><script src=\"a.js\"></script><script>\nlet a = 1;\n</script ></body></html>";
+        assert_eq!(
+            elements(content),
+            vec![
+                ("p {}", ids::CSS),
+                ("", ids::JavaScript),
+                ("\nlet a = 1;\n", ids::JavaScript)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_script_types() {
+        assert_eq!(
+            elements("<script type=\"module\">import a from 'a';</script>"),
+            vec![("import a from 'a';", ids::JavaScript)]
+        );
+        assert_eq!(
+            elements("<script type='application/ld+json'>{}</script>"),
+            vec![("{}", ids::JSON)]
+        );
+        assert_eq!(
+            elements("<script type=text/typescript>let a: number;</script>"),
+            vec![("let a: number;", ids::TypeScript)]
+        );
+        assert_eq!(
+            elements("<script type=\"text/x-handlebars-template\">{{a}}</script>"),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_lang_attribute() {
+        assert_eq!(
+            elements("<template lang=\"pug\">\np hi\n</template>\n<template><p>hi</p></template>"),
+            vec![("\np hi\n", ids::Pug)]
+        );
+        assert_eq!(
+            elements("<style lang=\"less\" scoped>a { .b; }</style>"),
+            vec![("a { .b; }", ids::Less)]
+        );
+        // "data-lang" isn't "lang".
+        assert_eq!(
+            elements("<style data-lang=\"less\">a {}</style>"),
+            vec![("a {}", ids::CSS)]
+        );
+    }
+
+    #[test]
+    fn test_unclosed_element() {
+        assert_eq!(
+            elements("<script>let a = 1;"),
+            vec![("let a = 1;", ids::JavaScript)]
+        );
+    }
+}
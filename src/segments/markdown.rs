@@ -0,0 +1,121 @@
+use std::ops::Range;
+
+use crate::{Language, LanguageRegistry};
+
+/// Returns the bodies of fenced code blocks whose info string names a
+/// language, like ```` ```rust ```` or ```` ```{r} ```` in RMarkdown.
+/// A block that isn't closed runs to the end of the content.
+pub(super) fn fenced_code(
+    content: &str,
+    registry: &LanguageRegistry,
+) -> Vec<(Range<usize>, Language)> {
+    let mut segments = Vec::new();
+    // The fence character and length, the language and where the body starts.
+    let mut open: Option<(char, usize, Option<Language>, usize)> = None;
+    let mut position = 0;
+    for line in content.split_inclusive('\n') {
+        let start = position;
+        position += line.len();
+        let fence = match fence(line) {
+            Some(fence) => fence,
+            None => continue,
+        };
+        match open {
+            Some((character, length, language, body)) => {
+                if fence.0 == character && fence.1 >= length && fence.2.trim().is_empty() {
+                    if let Some(language) = language {
+                        segments.push((body..start, language));
+                    }
+                    open = None;
+                }
+            }
+            None => {
+                let (character, length, info) = fence;
+                if character == '`' && info.contains('`') {
+                    continue;
+                }
+                open = Some((character, length, info_language(info, registry), position));
+            }
+        }
+    }
+    if let Some((_, _, Some(language), body)) = open {
+        segments.push((body..content.len(), language));
+    }
+    segments
+}
+
+/// Parses a line indented by at most three spaces that starts with three or
+/// more backticks or tildes, returning the character, the number of times
+/// it's repeated and the rest of the line.
+fn fence(line: &str) -> Option<(char, usize, &str)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let character = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let length = trimmed.len() - trimmed.trim_start_matches(character).len();
+    if length < 3 {
+        return None;
+    }
+    Some((character, length, &trimmed[length..]))
+}
+
+/// The language named by the first word of an info string, with the braces
+/// of RMarkdown chunks like `{r setup}` removed.
+fn info_language(info: &str, registry: &LanguageRegistry) -> Option<Language> {
+    let name = info
+        .trim()
+        .trim_start_matches('{')
+        .split(|c: char| c.is_whitespace() || c == ',' || c == '}')
+        .next()?;
+    if name.is_empty() {
+        return None;
+    }
+    registry.find_by_alias(name).first().copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids;
+
+    fn blocks(content: &str) -> Vec<(&str, Language)> {
+        fenced_code(content, &LanguageRegistry::default())
+            .into_iter()
+            .map(|(range, language)| (&content[range], language))
+            .collect()
+    }
+
+    #[test]
+    fn test_fenced_code() {
+        let content = "# Title\n\n```rust\nfn main() {}\n```\n\n~~~~ python\nprint(1)\n```\n~~~~\n";
+        assert_eq!(
+            blocks(content),
+            vec![
+                ("fn main() {}\n", ids::Rust),
+                ("print(1)\n```\n", ids::Python)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fenced_code_info_strings() {
+        assert_eq!(
+            blocks("```{r setup, include=FALSE}\nx <- 1\n```\n"),
+            vec![("x <- 1\n", ids::R)]
+        );
+        assert_eq!(blocks("```\nplain\n```\n"), vec![]);
+        assert_eq!(blocks("```not-a-language\nplain\n```\n"), vec![]);
+        // Indented code blocks and inline code aren't fences.
+        assert_eq!(blocks("    ```rust\n    fn main() {}\n    ```\n"), vec![]);
+        assert_eq!(blocks("``` `rust`\nfn main() {}\n```\n"), vec![]);
+    }
+
+    #[test]
+    fn test_fenced_code_unclosed() {
+        assert_eq!(
+            blocks("```js\nlet a = 1;\n"),
+            vec![("let a = 1;\n", ids::JavaScript)]
+        );
+    }
+}
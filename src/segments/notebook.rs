@@ -0,0 +1,343 @@
+//! Cells of Jupyter notebooks, which are JSON documents in the
+//! [nbformat 4](https://nbformat.readthedocs.io/en/latest/format_description.html)
+//! format. The JSON is scanned by hand, since the ranges of the cells'
+//! sources in the file are needed along with their values.
+
+use std::ops::Range;
+
+use crate::{ids, Language, LanguageRegistry};
+
+/// Returns the sources of Markdown cells, and of code cells if the
+/// language of the notebook's kernel is known. Ranges cover the source as
+/// it's encoded in the JSON, i.e. a string or an array of strings.
+pub(super) fn cells(content: &str, registry: &LanguageRegistry) -> Vec<(Range<usize>, Language)> {
+    let notebook = match parse(content) {
+        Some(notebook) => notebook,
+        None => return vec![],
+    };
+    let kernel = language_of(&notebook, registry);
+    let cells = match notebook.get("cells") {
+        Some(Value {
+            kind: Kind::Array(cells),
+            ..
+        }) => cells,
+        _ => return vec![],
+    };
+    cells
+        .iter()
+        .filter_map(|cell| {
+            let language = match cell.get("cell_type")?.as_str()? {
+                "markdown" => ids::Markdown,
+                "code" => kernel?,
+                _ => return None,
+            };
+            Some((cell.get("source")?.range.clone(), language))
+        })
+        .collect()
+}
+
+/// Returns the language of a notebook's kernel, from
+/// `metadata.kernelspec.language` or `metadata.language_info.name`.
+pub(crate) fn kernel_language(content: &str, registry: &LanguageRegistry) -> Option<Language> {
+    language_of(&parse(content)?, registry)
+}
+
+/// Returns the sources of a notebook's code cells, joined with newlines,
+/// or `None` if the notebook isn't valid JSON.
+pub(crate) fn code(content: &str) -> Option<String> {
+    let notebook = parse(content)?;
+    let cells = match notebook.get("cells") {
+        Some(Value {
+            kind: Kind::Array(cells),
+            ..
+        }) => cells,
+        _ => return Some(String::new()),
+    };
+    let mut code = String::new();
+    for cell in cells {
+        if cell.get("cell_type").and_then(Value::as_str) != Some("code") {
+            continue;
+        }
+        match cell.get("source").map(|source| &source.kind) {
+            Some(Kind::String(source)) => code.push_str(source),
+            Some(Kind::Array(lines)) => lines
+                .iter()
+                .filter_map(Value::as_str)
+                .for_each(|line| code.push_str(line)),
+            _ => continue,
+        }
+        code.push('\n');
+    }
+    Some(code)
+}
+
+fn language_of(notebook: &Value, registry: &LanguageRegistry) -> Option<Language> {
+    let metadata = notebook.get("metadata")?;
+    [("kernelspec", "language"), ("language_info", "name")]
+        .iter()
+        .filter_map(|(object, key)| metadata.get(object)?.get(key)?.as_str())
+        .find_map(|name| registry.find_by_alias(name).first().copied())
+}
+
+#[derive(Debug)]
+struct Value {
+    range: Range<usize>,
+    kind: Kind,
+}
+
+#[derive(Debug)]
+enum Kind {
+    Object(Vec<(String, Value)>),
+    Array(Vec<Value>),
+    String(String),
+    // Numbers, booleans and null, which notebooks don't need to be read for.
+    Other,
+}
+
+impl Value {
+    fn get(&self, key: &str) -> Option<&Value> {
+        match &self.kind {
+            Kind::Object(members) => members
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match &self.kind {
+            Kind::String(string) => Some(string),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a JSON document, returning `None` if it's invalid.
+fn parse(content: &str) -> Option<Value> {
+    let mut parser = Parser {
+        content,
+        position: 0,
+    };
+    let value = parser.value()?;
+    parser.whitespace();
+    if parser.position == content.len() {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+struct Parser<'a> {
+    content: &'a str,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.content.as_bytes().get(self.position).copied()
+    }
+
+    fn whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        self.whitespace();
+        if self.peek()? == byte {
+            self.position += 1;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn value(&mut self) -> Option<Value> {
+        self.whitespace();
+        let start = self.position;
+        let kind = match self.peek()? {
+            b'{' => self.object()?,
+            b'[' => self.array()?,
+            b'"' => Kind::String(self.string()?),
+            _ => {
+                while let Some(b'-' | b'+' | b'.' | b'0'..=b'9' | b'a'..=b'z' | b'E') = self.peek()
+                {
+                    self.position += 1;
+                }
+                if self.position == start {
+                    return None;
+                }
+                Kind::Other
+            }
+        };
+        Some(Value {
+            range: start..self.position,
+            kind,
+        })
+    }
+
+    fn object(&mut self) -> Option<Kind> {
+        self.expect(b'{')?;
+        let mut members = Vec::new();
+        self.whitespace();
+        if self.peek()? == b'}' {
+            self.position += 1;
+            return Some(Kind::Object(members));
+        }
+        loop {
+            self.whitespace();
+            let name = self.string()?;
+            self.expect(b':')?;
+            members.push((name, self.value()?));
+            self.whitespace();
+            match self.peek()? {
+                b',' => self.position += 1,
+                b'}' => {
+                    self.position += 1;
+                    return Some(Kind::Object(members));
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    fn array(&mut self) -> Option<Kind> {
+        self.expect(b'[')?;
+        let mut values = Vec::new();
+        self.whitespace();
+        if self.peek()? == b']' {
+            self.position += 1;
+            return Some(Kind::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            self.whitespace();
+            match self.peek()? {
+                b',' => self.position += 1,
+                b']' => {
+                    self.position += 1;
+                    return Some(Kind::Array(values));
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    /// Parses a string, decoding escapes other than `\u`, which are
+    /// replaced with U+FFFD since only names and cell types are compared.
+    fn string(&mut self) -> Option<String> {
+        if self.peek()? != b'"' {
+            return None;
+        }
+        self.position += 1;
+        let mut string = String::new();
+        let mut chars = self.content[self.position..].char_indices();
+        while let Some((offset, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.position += offset + 1;
+                    return Some(string);
+                }
+                '\\' => {
+                    let (_, escaped) = chars.next()?;
+                    string.push(match escaped {
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'u' => {
+                            for _ in 0..4 {
+                                chars.next()?;
+                            }
+                            char::REPLACEMENT_CHARACTER
+                        }
+                        other => other,
+                    });
+                }
+                c => string.push(c),
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTEBOOK: &str = r##"{
+ "cells": [
+  {
+   "cell_type": "markdown",
+   "metadata": {},
+   "source": ["# Title\n", "Some \"text\""]
+  },
+  {
+   "cell_type": "code",
+   "execution_count": 1,
+   "metadata": {},
+   "outputs": [],
+   "source": "print(1)"
+  },
+  {
+   "cell_type": "raw",
+   "metadata": {},
+   "source": []
+  }
+ ],
+ "metadata": {
+  "kernelspec": {"display_name": "Python 3", "language": "python", "name": "python3"},
+  "language_info": {"name": "python", "version": "3.11.4"}
+ },
+ "nbformat": 4,
+ "nbformat_minor": 5
+}"##;
+
+    #[test]
+    fn test_cells() {
+        let cells: Vec<(&str, Language)> = cells(NOTEBOOK, &LanguageRegistry::default())
+            .into_iter()
+            .map(|(range, language)| (&NOTEBOOK[range], language))
+            .collect();
+        assert_eq!(
+            cells,
+            vec![
+                (r##"["# Title\n", "Some \"text\""]"##, ids::Markdown),
+                (r#""print(1)""#, ids::Python),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_kernel_language() {
+        let registry = LanguageRegistry::default();
+        assert_eq!(kernel_language(NOTEBOOK, &registry), Some(ids::Python));
+        let julia = r#"{"metadata": {"language_info": {"name": "julia"}}, "cells": []}"#;
+        assert_eq!(kernel_language(julia, &registry), Some(ids::Julia));
+        assert_eq!(kernel_language(r#"{"metadata": {}}"#, &registry), None);
+        assert_eq!(kernel_language("{\"metadata\": ", &registry), None);
+    }
+
+    #[test]
+    fn test_code() {
+        let notebook = r##"{"cells": [
+            {"cell_type": "code", "source": ["import os\n", "os.getcwd()"]},
+            {"cell_type": "markdown", "source": "# Title"},
+            {"cell_type": "code", "source": "print(\"hi\")"}
+        ]}"##;
+        assert_eq!(
+            code(notebook).as_deref(),
+            Some("import os\nos.getcwd()\nprint(\"hi\")\n")
+        );
+        assert_eq!(code("not json"), None);
+    }
+
+    #[test]
+    fn test_code_cells_without_kernel() {
+        let notebook = r#"{"cells": [{"cell_type": "code", "source": "x"}], "metadata": {}}"#;
+        assert_eq!(cells(notebook, &LanguageRegistry::default()), vec![]);
+    }
+}
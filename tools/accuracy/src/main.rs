@@ -0,0 +1,148 @@
+//! Held-out accuracy evaluation for langur's detection.
+//!
+//! The Linguist samples are split into k folds. For each fold, the classifier
+//! is retrained in memory on the other folds, and every file in the fold is
+//! run through the full detection pipeline with that model. This avoids
+//! scoring the classifier on the same files it was trained on.
+
+mod report;
+
+use clap::{App, Arg};
+use codegen::{
+    classifier_ngram_order, classifier_training_options, linguist_root_dir, linguist_samples,
+    read_sample,
+};
+use langur::{DetectOptions, ModelMode};
+use langur_classifier::Corpus;
+use std::{path::PathBuf, sync::Arc};
+
+use report::{Baseline, Outcome, Report};
+
+const DEFAULT_FOLDS: &str = "10";
+
+fn main() {
+    let matches = App::new("accuracy")
+        .about("Reports cross-validated detection accuracy on Linguist's samples")
+        .arg(
+            Arg::with_name("folds")
+                .short("k")
+                .long("folds")
+                .takes_value(true)
+                .default_value(DEFAULT_FOLDS)
+                .help("Number of folds to split the samples into"),
+        )
+        .arg(
+            Arg::with_name("save-baseline")
+                .long("save-baseline")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Saves the detection of every sample, for use with --baseline"),
+        )
+        .arg(
+            Arg::with_name("baseline")
+                .long("baseline")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Compares against a file saved with --save-baseline, exiting with an error if any sample regressed"),
+        )
+        .arg(
+            Arg::with_name("ngrams")
+                .long("ngrams")
+                .takes_value(true)
+                .value_name("ORDER")
+                .help("Trains the classifier with runs of up to ORDER tokens as features, defaults to LANGUR_NGRAM_ORDER or 1. Compare with --baseline to see the accuracy delta"),
+        )
+        .arg(
+            Arg::with_name("margin")
+                .long("margin")
+                .takes_value(true)
+                .value_name("NATS")
+                .help("Stops classifying once the best candidate leads by NATS, see DetectOptions::classifier_margin. Prints the number of tokens scored, to compare with a run without it"),
+        )
+        .arg(
+            Arg::with_name("all-languages")
+                .long("all-languages")
+                .help("Prints precision and recall for all languages, not just the ones with errors"),
+        )
+        .get_matches();
+
+    let folds: usize = matches
+        .value_of("folds")
+        .unwrap()
+        .parse()
+        .expect("--folds must be a number");
+    assert!(folds >= 2, "--folds must be at least 2");
+
+    let ngram_order = matches
+        .value_of("ngrams")
+        .map(|order| order.parse().expect("--ngrams must be a number"))
+        .unwrap_or_else(classifier_ngram_order);
+
+    let margin = matches.value_of("margin").map(|margin| {
+        let margin: f64 = margin.parse().expect("--margin must be a number");
+        assert!(margin > 0.0, "--margin must be positive");
+        margin
+    });
+
+    let outcomes = cross_validate(folds, ngram_order, margin);
+    let report = Report::new(&outcomes);
+    report.print_summary(folds);
+    report.print_languages(matches.is_present("all-languages"));
+    report.print_confusions();
+
+    if let Some(path) = matches.value_of("save-baseline") {
+        Baseline::save(&outcomes, path).expect("failed to save baseline");
+    }
+
+    if let Some(path) = matches.value_of("baseline") {
+        let baseline = Baseline::load(path).expect("failed to load baseline");
+        if !baseline.print_comparison(&outcomes) {
+            std::process::exit(1);
+        }
+    }
+}
+
+fn cross_validate(folds: usize, ngram_order: usize, margin: Option<f64>) -> Vec<Outcome> {
+    let samples: Vec<(String, PathBuf, String)> = linguist_samples(&linguist_root_dir())
+        .into_iter()
+        .map(|(language, path)| {
+            let content = read_sample(&path);
+            (language, path, content)
+        })
+        .collect();
+    let training_options = classifier_training_options();
+
+    let mut outcomes = Vec::with_capacity(samples.len());
+    for fold in 0..folds {
+        let mut corpus = Corpus::new().with_ngram_order(ngram_order);
+        for (i, (language, _, content)) in samples.iter().enumerate() {
+            if i % folds != fold {
+                corpus.add_sample(language, content);
+            }
+        }
+        let mut options = DetectOptions::default();
+        options.classifier_model = Some(Arc::new(corpus.train(&training_options)));
+        options.classifier_model_mode = ModelMode::Override;
+        options.classifier_margin = margin;
+
+        for (i, (language, path, _)) in samples.iter().enumerate() {
+            if i % folds != fold {
+                continue;
+            }
+            // Same detection as detect_with_options, along with the number
+            // of tokens the classifier scored.
+            let explanation = langur::detect_explain(path, &options)
+                .unwrap_or_else(|e| panic!("failed to detect {}: {}", path.display(), e));
+            let detection = explanation.detection;
+            outcomes.push(Outcome {
+                path: path.clone(),
+                language: language.clone(),
+                detected: detection.map(|detection| detection.language().name().to_owned()),
+                strategy: detection.map(|detection| detection.variant().to_owned()),
+                classifier_tokens: explanation.classifier_tokens,
+            });
+        }
+    }
+    outcomes.sort_by(|a, b| a.path.cmp(&b.path));
+    outcomes
+}
@@ -0,0 +1,277 @@
+use langur::ClassifierTokens;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+const NO_DETECTION: &str = "(none)";
+
+/// The result of detecting a single held-out sample.
+pub struct Outcome {
+    pub path: PathBuf,
+    /// The language the sample belongs to.
+    pub language: String,
+    pub detected: Option<String>,
+    pub strategy: Option<String>,
+    pub classifier_tokens: Option<ClassifierTokens>,
+}
+
+impl Outcome {
+    fn is_correct(&self) -> bool {
+        self.detected.as_ref() == Some(&self.language)
+    }
+
+    fn detected_name(&self) -> &str {
+        self.detected.as_deref().unwrap_or(NO_DETECTION)
+    }
+}
+
+#[derive(Default)]
+struct LanguageCounts {
+    true_positives: usize,
+    false_positives: usize,
+    false_negatives: usize,
+}
+
+impl LanguageCounts {
+    fn precision(&self) -> f64 {
+        ratio(
+            self.true_positives,
+            self.true_positives + self.false_positives,
+        )
+    }
+
+    fn recall(&self) -> f64 {
+        ratio(
+            self.true_positives,
+            self.true_positives + self.false_negatives,
+        )
+    }
+}
+
+pub struct Report<'a> {
+    outcomes: &'a [Outcome],
+    languages: BTreeMap<&'a str, LanguageCounts>,
+    /// Off-diagonal entries of the confusion matrix, keyed by (actual, detected).
+    confusions: HashMap<(&'a str, &'a str), usize>,
+}
+
+impl<'a> Report<'a> {
+    pub fn new(outcomes: &'a [Outcome]) -> Self {
+        let mut languages: BTreeMap<&str, LanguageCounts> = BTreeMap::new();
+        let mut confusions = HashMap::new();
+        for outcome in outcomes {
+            if outcome.is_correct() {
+                languages
+                    .entry(&outcome.language)
+                    .or_default()
+                    .true_positives += 1;
+                continue;
+            }
+            languages
+                .entry(&outcome.language)
+                .or_default()
+                .false_negatives += 1;
+            if let Some(detected) = &outcome.detected {
+                languages.entry(detected).or_default().false_positives += 1;
+            }
+            *confusions
+                .entry((&outcome.language[..], outcome.detected_name()))
+                .or_insert(0) += 1;
+        }
+        Report {
+            outcomes,
+            languages,
+            confusions,
+        }
+    }
+
+    pub fn print_summary(&self, folds: usize) {
+        let correct = self.outcomes.iter().filter(|o| o.is_correct()).count();
+        println!(
+            "Held-out accuracy ({} folds): {:.2}% ({}/{})",
+            folds,
+            ratio(correct, self.outcomes.len()) * 100.0,
+            correct,
+            self.outcomes.len()
+        );
+
+        let mut strategies: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+        for outcome in self.outcomes {
+            let strategy = outcome.strategy.as_deref().unwrap_or(NO_DETECTION);
+            let (correct, total) = strategies.entry(strategy).or_default();
+            *total += 1;
+            if outcome.is_correct() {
+                *correct += 1;
+            }
+        }
+        for (strategy, (correct, total)) in strategies {
+            println!(
+                "  {:<12} {:>7.2}% ({}/{})",
+                strategy,
+                ratio(correct, total) * 100.0,
+                correct,
+                total
+            );
+        }
+
+        let classifier_tokens: Vec<ClassifierTokens> = self
+            .outcomes
+            .iter()
+            .filter_map(|outcome| outcome.classifier_tokens)
+            .collect();
+        println!(
+            "Classified {} samples, {} of them early, scoring {} tokens",
+            classifier_tokens.len(),
+            classifier_tokens
+                .iter()
+                .filter(|tokens| tokens.total.is_none())
+                .count(),
+            classifier_tokens
+                .iter()
+                .map(|tokens| tokens.scored)
+                .sum::<usize>()
+        );
+    }
+
+    pub fn print_languages(&self, all_languages: bool) {
+        println!();
+        println!(
+            "{:<32} {:>9} {:>9} {:>8}",
+            "Language", "Precision", "Recall", "Samples"
+        );
+        for (language, counts) in self.languages.iter() {
+            if !all_languages && counts.false_positives == 0 && counts.false_negatives == 0 {
+                continue;
+            }
+            println!(
+                "{:<32} {:>8.2}% {:>8.2}% {:>8}",
+                language,
+                counts.precision() * 100.0,
+                counts.recall() * 100.0,
+                counts.true_positives + counts.false_negatives
+            );
+        }
+    }
+
+    /// Prints the non-zero off-diagonal entries of the confusion matrix,
+    /// most frequent first.
+    pub fn print_confusions(&self) {
+        let mut confusions: Vec<_> = self.confusions.iter().collect();
+        confusions.sort_by(|(a_key, a_count), (b_key, b_count)| {
+            b_count.cmp(a_count).then(a_key.cmp(b_key))
+        });
+
+        println!();
+        println!("Confusions (actual -> detected):");
+        for ((language, detected), count) in confusions {
+            println!("{:>6}  {} -> {}", count, language, detected);
+        }
+    }
+}
+
+/// Detections saved from a previous run, keyed by sample path.
+pub struct Baseline {
+    detections: HashMap<PathBuf, (String, String)>,
+}
+
+impl Baseline {
+    pub fn save<P: AsRef<Path>>(outcomes: &[Outcome], path: P) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        for outcome in outcomes {
+            writeln!(
+                file,
+                "{}\t{}\t{}",
+                outcome.path.display(),
+                outcome.language,
+                outcome.detected_name()
+            )?;
+        }
+        file.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Baseline> {
+        let content = fs::read_to_string(path)?;
+        let mut detections = HashMap::new();
+        for line in content.lines() {
+            let mut fields = line.split('\t');
+            match (fields.next(), fields.next(), fields.next()) {
+                (Some(path), Some(language), Some(detected)) => {
+                    detections.insert(
+                        PathBuf::from(path),
+                        (language.to_owned(), detected.to_owned()),
+                    );
+                }
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("malformed baseline line: {:?}", line),
+                    ))
+                }
+            }
+        }
+        Ok(Baseline { detections })
+    }
+
+    /// Prints the samples whose detection changed from correct to incorrect
+    /// or vice versa. Returns false if any sample regressed.
+    pub fn print_comparison(&self, outcomes: &[Outcome]) -> bool {
+        let baseline_correct = self
+            .detections
+            .values()
+            .filter(|(language, detected)| language == detected)
+            .count();
+        let correct = outcomes.iter().filter(|o| o.is_correct()).count();
+        println!();
+        println!(
+            "Baseline accuracy: {:.2}% ({}/{}), change: {:+.2}%",
+            ratio(baseline_correct, self.detections.len()) * 100.0,
+            baseline_correct,
+            self.detections.len(),
+            (ratio(correct, outcomes.len()) - ratio(baseline_correct, self.detections.len()))
+                * 100.0
+        );
+
+        let mut regressions = BTreeSet::new();
+        let mut fixes = BTreeSet::new();
+        for outcome in outcomes {
+            let previously = match self.detections.get(&outcome.path) {
+                Some((_, detected)) => detected,
+                // New samples have nothing to be compared against.
+                None => continue,
+            };
+            let was_correct = previously == &outcome.language;
+            let line = format!(
+                "{} ({}, detected {}, previously {})",
+                outcome.path.display(),
+                outcome.language,
+                outcome.detected_name(),
+                previously
+            );
+            match (was_correct, outcome.is_correct()) {
+                (true, false) => regressions.insert(line),
+                (false, true) => fixes.insert(line),
+                _ => false,
+            };
+        }
+
+        println!("Regressions ({}):", regressions.len());
+        for line in regressions.iter() {
+            println!("  {}", line);
+        }
+        println!("Fixes ({}):", fixes.len());
+        for line in fixes.iter() {
+            println!("  {}", line);
+        }
+        regressions.is_empty()
+    }
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f64 {
+    if denominator == 0 {
+        return 0.0;
+    }
+    (numerator as f64) / (denominator as f64)
+}
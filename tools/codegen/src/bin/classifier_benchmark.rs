@@ -0,0 +1,132 @@
+use codegen::{
+    classifier_ngram_order, classifier_training_options, linguist_root_dir, linguist_samples,
+    read_sample,
+};
+use langur_classifier::{CompactModel, Corpus, Model, Precision};
+use phf_codegen::Map as PhfMap;
+use std::time::{Duration, Instant};
+
+/// Compares the serialized classifier model embedded in Langur with the
+/// `phf::Map` source that codegen used to emit for the same model: the size
+/// of what gets compiled in, how long decoding takes, and how fast samples
+/// are scored against every language.
+///
+/// Compile time and binary size are measured by building Langur itself,
+/// see Development.md.
+fn main() {
+    let samples: Vec<(String, String)> = linguist_samples(&linguist_root_dir())
+        .into_iter()
+        .map(|(language, path)| (language, read_sample(&path)))
+        .collect();
+    let mut corpus = Corpus::new().with_ngram_order(classifier_ngram_order());
+    for (language, content) in samples.iter() {
+        corpus.add_sample(language, content);
+    }
+    let model = corpus.train(&classifier_training_options());
+
+    println!("Size of the embedded model");
+    println!("{:>12}  phf::Map source", phf_source(&model).len());
+    let mut encoded = Vec::new();
+    for (name, precision) in [("f32", Precision::F32), ("u16", Precision::U16)] {
+        let mut bytes = Vec::new();
+        model.write_to(&mut bytes, precision).unwrap();
+        println!("{:>12}  binary, {} log probabilities", bytes.len(), name);
+        encoded.push((name, bytes));
+    }
+
+    println!();
+    println!("Decoding");
+    for (name, bytes) in encoded.iter() {
+        let (elapsed, _) = time(|| CompactModel::from_bytes(bytes).unwrap());
+        println!("{:>12.2?}  {}", elapsed, name);
+    }
+
+    let mut names: Vec<&str> = model.languages.keys().map(String::as_str).collect();
+    names.sort_unstable();
+    let tokenized: Vec<(&str, Vec<&str>)> = samples
+        .iter()
+        .map(|(language, content)| {
+            (
+                language.as_str(),
+                langur_classifier::tokens(content).collect(),
+            )
+        })
+        .collect();
+
+    println!();
+    println!(
+        "Scoring {} samples against {} languages",
+        tokenized.len(),
+        names.len()
+    );
+    let (elapsed, expected) = time(|| {
+        tokenized
+            .iter()
+            .map(|(_, tokens)| model.classify_tokens(tokens, &names))
+            .collect::<Vec<_>>()
+    });
+    print_speed("string keys, f64", elapsed, tokenized.len());
+    for (name, bytes) in encoded.iter() {
+        let compact = CompactModel::from_bytes(bytes).unwrap();
+        let (elapsed, predicted) = time(|| {
+            tokenized
+                .iter()
+                .map(|(_, tokens)| {
+                    let features = langur_classifier::features(tokens, compact.ngram_order());
+                    let token_indices = compact.token_indices(features.iter().map(AsRef::as_ref));
+                    // Keeps the first of equal scores, like `Model::classify_tokens`.
+                    let mut best: Option<(&str, f64)> = None;
+                    for &name in names.iter() {
+                        let score = compact.score(name, &token_indices).unwrap();
+                        match best {
+                            Some((_, best_score)) if best_score >= score => {}
+                            _ => best = Some((name, score)),
+                        }
+                    }
+                    best.map(|(name, _)| name)
+                })
+                .collect::<Vec<_>>()
+        });
+        let changed = expected
+            .iter()
+            .zip(predicted.iter())
+            .filter(|(a, b)| a != b)
+            .count();
+        print_speed(name, elapsed, tokenized.len());
+        println!("{:>12}  predictions changed by {}", changed, name);
+    }
+}
+
+/// The source that codegen emitted before the model was serialized, keyed by
+/// name rather than by language id.
+fn phf_source(model: &Model) -> String {
+    let mut languages = PhfMap::new();
+    for (language, language_model) in model.languages.iter() {
+        let mut tokens = PhfMap::new();
+        for (token, log_prob) in language_model.token_log_probs.iter() {
+            tokens.entry(&token[..], &format!("{:.8}f64", log_prob)[..]);
+        }
+        let value = format!(
+            "LanguageTokenModel {{ log_prior: {:.8}f64, unseen_log_prob: {:.8}f64, tokens: {} }}",
+            language_model.log_prior,
+            language_model.unseen_log_prob,
+            tokens.build(),
+        );
+        languages.entry(&language[..], &value[..]);
+    }
+    languages.build().to_string()
+}
+
+fn time<T>(f: impl FnOnce() -> T) -> (Duration, T) {
+    let start = Instant::now();
+    let result = f();
+    (start.elapsed(), result)
+}
+
+fn print_speed(name: &str, elapsed: Duration, samples: usize) {
+    println!(
+        "{:>12.0}  samples/s, {}",
+        samples as f64 / elapsed.as_secs_f64(),
+        name
+    );
+}
@@ -0,0 +1,121 @@
+use codegen::{
+    classifier_training_options, linguist_root_dir, linguist_samples, parse_languages_yml,
+    read_sample,
+};
+use langur_classifier::{
+    cross_validate, LabeledSample, Priors, Smoothing, TrainingOptions, MAX_NGRAM_ORDER,
+};
+use std::collections::HashMap;
+
+const DEFAULT_FOLDS: usize = 5;
+
+/// Prints the cross-validated accuracy of the classifier on Linguist's samples
+/// for the built-in training scheme, smoothed variants of it and n-grams
+/// with it, so that changes to training can be
+/// compared on held-out data.
+///
+/// Each sample is only scored against the languages sharing its extension,
+/// matching what the classifier sees during detection.
+fn main() {
+    let folds = std::env::args()
+        .nth(1)
+        .map(|folds| folds.parse().expect("FOLDS must be a number"))
+        .unwrap_or(DEFAULT_FOLDS);
+
+    let mut extension_map: HashMap<String, Vec<String>> = HashMap::new();
+    for (name, language) in parse_languages_yml() {
+        for extension in language.extensions.unwrap_or_default() {
+            extension_map
+                .entry(extension.to_ascii_lowercase())
+                .or_default()
+                .push(name.clone());
+        }
+    }
+
+    let samples: Vec<LabeledSample> = linguist_samples(&linguist_root_dir())
+        .into_iter()
+        .map(|(language, path)| {
+            let filename = path.file_name().unwrap().to_string_lossy();
+            let mut candidates = get_extension_languages(&filename, &extension_map)
+                .cloned()
+                .unwrap_or_default();
+            candidates.sort();
+            LabeledSample {
+                language,
+                content: read_sample(&path),
+                candidates,
+            }
+        })
+        .collect();
+
+    let mut configurations = vec![
+        ("unsmoothed, uniform priors", TrainingOptions::default()),
+        (
+            "laplace, uniform priors",
+            TrainingOptions {
+                smoothing: Smoothing::Laplace { alpha: 1.0 },
+                priors: Priors::Uniform,
+            },
+        ),
+        (
+            "laplace, sample count priors",
+            TrainingOptions {
+                smoothing: Smoothing::Laplace { alpha: 1.0 },
+                priors: Priors::SampleCounts,
+            },
+        ),
+    ];
+    let built_in_options = classifier_training_options();
+    if let Priors::Table(_) = built_in_options.priors {
+        configurations.push((
+            "unsmoothed, language_priors.yml priors",
+            built_in_options.clone(),
+        ));
+    }
+
+    let results: Vec<_> = configurations
+        .iter()
+        .map(|(name, options)| (name, options, cross_validate(&samples, folds, options, 1)))
+        .collect();
+    println!(
+        "Cross-validated classifier accuracy ({} folds, {} ambiguous samples)",
+        folds, results[0].2.total
+    );
+    for (name, options, result) in results.iter() {
+        let built_in = if **options == built_in_options {
+            " (built-in)"
+        } else {
+            ""
+        };
+        println!("{:>7.2}%  {}{}", result.accuracy() * 100.0, name, built_in);
+    }
+
+    let unigram_accuracy = results
+        .iter()
+        .find(|(_, options, _)| **options == built_in_options)
+        .unwrap()
+        .2
+        .accuracy();
+    for ngram_order in 2..=MAX_NGRAM_ORDER {
+        let result = cross_validate(&samples, folds, &built_in_options, ngram_order);
+        println!(
+            "{:>7.2}%  {}-grams, {:+.2} points over single tokens",
+            result.accuracy() * 100.0,
+            ngram_order,
+            (result.accuracy() - unigram_accuracy) * 100.0
+        );
+    }
+}
+
+/// Same as langur's extension lookup: the longest known extension wins.
+fn get_extension_languages<'a>(
+    filename: &str,
+    extension_map: &'a HashMap<String, Vec<String>>,
+) -> Option<&'a Vec<String>> {
+    let filename = filename.strip_prefix('.').unwrap_or(filename);
+    let filename = filename.to_ascii_lowercase();
+    filename
+        .char_indices()
+        .filter(|(_, ch)| *ch == '.')
+        .find_map(|(pos, _)| extension_map.get(&filename[pos..]))
+}
@@ -1,7 +1,9 @@
 use codegen::{
-    parse_deprecated_languages_yml, parse_languages_yml, DeprecatedLanguage, DeprecatedLanguageMap,
-    LanguageId, ParsedLanguage, ParsedLanguageMap,
+    classifier_ngram_order, classifier_training_options, linguist_root_dir, linguist_samples,
+    parse_deprecated_languages_yml, parse_filename_patterns_yml, parse_languages_yml, read_sample,
+    DeprecatedLanguage, DeprecatedLanguageMap, LanguageId, ParsedLanguage, ParsedLanguageMap,
 };
+use langur_classifier::{Corpus, Precision};
 
 use pcre2::bytes::Regex as PCRERegex;
 use phf_codegen::Map as PhfMap;
@@ -10,9 +12,8 @@ use phf_codegen::Set as PhfSet;
 use serde::Deserialize;
 use std::{
     collections::HashMap,
-    fs::{self, File},
+    fs::File,
     io::{BufWriter, Write},
-    iter,
     num::NonZeroU8,
     path::{Path, PathBuf},
 };
@@ -20,18 +21,17 @@ use std::{
 const DISAMBIGUATION_HEURISTICS_FILE: &str = "src/generated/disambiguation_heuristics_map.rs";
 const EXTENSION_MAP_FILE: &str = "src/generated/extension_language_map.rs";
 const FILENAME_MAP_FILE: &str = "src/generated/filename_language_map.rs";
+const FILENAME_PATTERN_FILE: &str = "src/generated/filename_pattern_map.rs";
 const INTERPRETER_MAP_FILE: &str = "src/generated/interpreter_language_map.rs";
 const ALIASES_MAP_FILE: &str = "src/generated/aliases_language_map.rs";
 const LANGUAGE_DATA_FILE: &str = "src/generated/language_data_map.rs";
 const LANGUAGE_LIST_FILE: &str = "src/generated/languages.rs";
-const TOKEN_LOG_PROBABILITY_FILE: &str = "src/generated/token_log_probabilities.rs";
+const TOKEN_LOG_PROBABILITY_FILE: &str = "src/generated/token_log_probabilities.bin";
 
 const HEURISTICS_SOURCE_FILE: &str = "lib/linguist/heuristics.yml";
 
-const MAX_TOKEN_BYTES: usize = 32;
-
 fn main() {
-    let linguist_root_dir = PathBuf::from("external/com_github_linguist");
+    let linguist_root_dir = linguist_root_dir();
     let parsed_map = parse_languages_yml();
     let deprecated_map = parse_deprecated_languages_yml();
     let language_table = LanguageTable::new(parsed_map, deprecated_map, &linguist_root_dir);
@@ -39,6 +39,7 @@ fn main() {
     language_table.write_language_list();
     language_table.write_language_data();
     language_table.create_filename_map();
+    language_table.create_filename_pattern_map();
     language_table.create_extension_map();
     language_table.create_interpreter_map();
     language_table.create_aliases_map();
@@ -326,6 +327,71 @@ impl Language {{
         .unwrap();
     }
 
+    /// Create the fallbacks for filenames that don't match exactly: a mapping
+    /// from lowercased filename -> list of languages, and the path patterns
+    /// from filename_patterns.yml.
+    ///
+    /// Only filenames in mixed case, like Dockerfile or CMakeLists.txt, are
+    /// case-folded. All-uppercase and all-lowercase names are conventions,
+    /// e.g. a script named `install` isn't the INSTALL text file.
+    fn create_filename_pattern_map(&self) {
+        let mut temp_map: HashMap<String, Vec<LanguageId>> = HashMap::new();
+        for (_, id) in self.sorted_names.iter() {
+            if id.is_deprecated() {
+                continue;
+            }
+            if let Some(filenames) = &self.id_to_data_map.get(id).unwrap().dto.filenames {
+                for filename in filenames.iter() {
+                    let lowercase = filename.to_lowercase();
+                    if lowercase != *filename && filename.to_uppercase() != *filename {
+                        temp_map.entry(lowercase).or_default().push(id.clone());
+                    }
+                }
+            }
+        }
+
+        let mut folded_filename_map = PhfMap::new();
+        for (filename, ids) in temp_map.iter_mut() {
+            ids.sort();
+            ids.dedup();
+            folded_filename_map.entry(filename, &LanguageId::slice_to_string(ids, " "));
+        }
+
+        let mut patterns = String::new();
+        for pattern in parse_filename_patterns_yml() {
+            let id = self
+                .sorted_names
+                .iter()
+                .find(|(name, _)| *name == pattern.language)
+                .map(|(_, id)| id)
+                .unwrap_or_else(|| panic!("Unknown language {}", pattern.language));
+            patterns.push_str(&format!(
+                "        ({:?}, &[{}, ]),\n",
+                pattern.pattern.to_lowercase(),
+                id.prefixed_id()
+            ));
+        }
+
+        let built_map = folded_filename_map.build();
+        let mut file = BufWriter::new(File::create(FILENAME_PATTERN_FILE).unwrap());
+        writeln!(
+            &mut file,
+            "static FOLDED_FILENAME_TO_LANGUAGE_MAP: phf::Map<&'static str, &[crate::Language]> = {{
+    use crate::ids;
+    {}
+}};
+
+static FILENAME_PATTERNS: &[(&str, &[crate::Language])] = {{
+    use crate::ids;
+    &[
+{}    ]
+}};
+",
+            built_map, patterns,
+        )
+        .unwrap();
+    }
+
     fn create_interpreter_map(&self) {
         let mut temp_map: HashMap<&str, Vec<LanguageId>> = HashMap::new();
         for (_, id) in self.sorted_names.iter() {
@@ -423,107 +489,53 @@ impl Language {{
     }
 
     fn train_classifier(&self) {
-        let mut temp_token_count: HashMap<LanguageId, HashMap<String, i32>> = HashMap::new();
-        let mut temp_total_tokens_count = HashMap::new();
-
-        fs::read_dir(self.linguist_root_dir.join("samples"))
-            .unwrap()
-            .map(|entry| entry.unwrap())
-            .filter(|entry| entry.path().is_dir())
-            .flat_map(|language_dir| {
-                let path = language_dir.path();
-                let language = path.file_name().unwrap();
-                let language = language.to_string_lossy().into_owned();
-                let language = match &language[..] {
-                    "Fstar" => String::from("F*"),
-                    _ => language,
-                };
-                let id = self
-                    .parsed_map
-                    .get(&language)
-                    .unwrap_or_else(|| panic!("missing entry for {}", language))
-                    .id(&language);
-
-                let file_paths = fs::read_dir(language_dir.path())
-                    .unwrap()
-                    .map(|entry| entry.unwrap().path())
-                    .filter(|path| path.is_file());
-
-                let id_iter = iter::repeat(id);
-                file_paths.zip(id_iter)
-            })
-            .for_each(|(entry, id)| {
-                let content = fs::read(entry).unwrap();
-
-                // When tokenizing an invalid utf8 string, just set it to ""
-                // Add better error handling here in the future but unure of the best
-                // way to handle it now
-                let tokens = langur_tokenizer::get_key_tokens(
-                    std::str::from_utf8(&content[..]).unwrap_or(""),
-                );
-
-                for token in tokens {
-                    if token.len() <= MAX_TOKEN_BYTES {
-                        let total_tokens = temp_total_tokens_count.entry(id.clone()).or_insert(0);
-                        *total_tokens += 1;
-
-                        let tokens_count =
-                            temp_token_count.entry(id.clone()).or_insert(HashMap::new());
-
-                        let count = tokens_count.entry(String::from(token)).or_insert(0);
-                        *count += 1;
-                    }
-                }
-            });
-
-        // Write token log probabilities
-        let mut language_token_log_probabilities = PhfMap::new();
-        for (id, token_count_map) in temp_token_count.iter() {
-            let total_tokens = *temp_total_tokens_count.get(id).unwrap() as f64;
-            let mut token_log_probabilities = PhfMap::new();
-            for (token, token_count) in token_count_map.iter() {
-                let probability = (*token_count as f64) / (total_tokens);
-                let log_probability = probability.ln();
-                // 8 digits is somewhat arbitrarily chosen to avoid
-                // differences across environments.
-                token_log_probabilities
-                    .entry(&token[..], &format!("{:.8}f64", log_probability)[..]);
-            }
-            let codegen_log_prob_map = format!("{}", token_log_probabilities.build());
-            language_token_log_probabilities.entry(id, &codegen_log_prob_map[..]);
+        let mut corpus = Corpus::new().with_ngram_order(classifier_ngram_order());
+        for (language, path) in linguist_samples(&self.linguist_root_dir) {
+            corpus.add_sample(&language, &read_sample(&path));
         }
-
-        let built_map = language_token_log_probabilities.build();
-        let mut file = BufWriter::new(File::create(TOKEN_LOG_PROBABILITY_FILE).unwrap());
-        file.write_all("#[allow(clippy::approx_constant)]\n\n".as_bytes())
+        let model = corpus.train(&classifier_training_options());
+
+        // The names in the model are Linguist names, make sure they all
+        // map to a language before they're looked up at runtime.
+        for language in model.languages.keys() {
+            assert!(
+                self.parsed_map.contains_key(language),
+                "missing entry for {}",
+                language
+            );
+        }
+        model
+            .save(TOKEN_LOG_PROBABILITY_FILE, Precision::U16)
             .unwrap();
-        writeln!(
-            &mut file,
-"static TOKEN_LOG_PROBABILITIES: phf::Map<crate::Language, phf::Map<&'static str, f64>> = {{
-    use crate::ids;
-
-    {}
-}};
-",
-            built_map,
-        )
-        .unwrap();
     }
 
     fn create_disambiguation_heuristics_map(&self, heuristics: Heuristics<String>) {
         let mut temp_map: HashMap<String, String> = HashMap::new();
         for dis in heuristics.disambiguations.into_iter() {
             let rules = dis.rules;
-            let extensions = dis.extensions;
-            for ext in extensions.iter() {
-                let extension = ext.clone().to_ascii_lowercase();
-                let key = extension;
+            // Filenames and interpreters share the table with extensions, see
+            // HeuristicsKey in src/detectors/heuristics.rs.
+            let keys = dis
+                .extensions
+                .iter()
+                .map(|ext| ext.to_ascii_lowercase())
+                .chain(
+                    dis.filenames
+                        .iter()
+                        .map(|filename| format!("filename:{}", filename)),
+                )
+                .chain(
+                    dis.interpreters
+                        .iter()
+                        .map(|interpreter| format!("interpreter:{}", interpreter)),
+                );
+            for key in keys {
                 // NOTE(def: special-dot-h-rule)
                 // Adding a rule to default to C for .h if the
                 // Objective-C and C++ patterns don't match.
                 // The classifer was unreliable for distinguishing
                 // between C and C++ for .h
-                let rules: Vec<_> = if ext == ".h" {
+                let rules: Vec<_> = if key == ".h" {
                     let mut new_rules = rules.clone();
                     new_rules.push(Rule {
                         language: MaybeMany::One(String::from("C")),
@@ -578,7 +590,12 @@ struct Heuristics<L> {
 
 #[derive(Deserialize)]
 struct Disambiguation<L> {
+    #[serde(default)]
     extensions: Vec<String>,
+    #[serde(default)]
+    filenames: Vec<String>,
+    #[serde(default)]
+    interpreters: Vec<String>,
     rules: Vec<Rule<L>>,
 }
 
@@ -1,3 +1,4 @@
+use langur_classifier::{Priors, TrainingOptions};
 use lazy_static::lazy_static;
 use phf_shared::*;
 use regex::Regex;
@@ -5,10 +6,10 @@ use serde::Deserialize;
 use std::{
     collections::HashMap,
     fmt::{Display, Write as _},
-    fs::File,
+    fs::{self, File},
     hash::{Hash, Hasher},
     num::NonZeroU8,
-    path::PathBuf,
+    path::{Path, PathBuf},
     rc::Rc,
 };
 
@@ -16,8 +17,12 @@ pub type ParsedLanguageMap = HashMap<String, ParsedLanguage>;
 
 const LANGUAGE_SOURCE_FILE: &str = "lib/linguist/languages.yml";
 
+/// The Linguist checkout that Bazel fetches, or `$LINGUIST_ROOT` if set,
+/// e.g. to run the classifier tools with Cargo.
 pub fn linguist_root_dir() -> PathBuf {
-    PathBuf::from("external/com_github_linguist")
+    std::env::var_os("LINGUIST_ROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("external/com_github_linguist"))
 }
 
 pub fn parse_languages_yml() -> ParsedLanguageMap {
@@ -31,6 +36,89 @@ pub fn parse_deprecated_languages_yml() -> DeprecatedLanguageMap {
     serde_yaml::from_reader(File::open(PathBuf::from(DEPRECATED_LANGUAGES_FILE)).unwrap()).unwrap()
 }
 
+const LANGUAGE_PRIORS_FILE: &str = "tools/codegen/data/language_priors.yml";
+
+/// Relative weights of languages used as classifier priors.
+pub fn parse_language_priors_yml() -> HashMap<String, f64> {
+    serde_yaml::from_reader(File::open(PathBuf::from(LANGUAGE_PRIORS_FILE)).unwrap()).unwrap()
+}
+
+const FILENAME_PATTERNS_FILE: &str = "tools/codegen/data/filename_patterns.yml";
+
+/// A path pattern for files that Linguist's exact filenames don't cover.
+#[derive(Debug, Deserialize)]
+pub struct FilenamePattern {
+    pub pattern: String,
+    pub language: String,
+}
+
+/// Path patterns in the order they're checked, see filename_patterns.yml.
+pub fn parse_filename_patterns_yml() -> Vec<FilenamePattern> {
+    serde_yaml::from_reader(File::open(PathBuf::from(FILENAME_PATTERNS_FILE)).unwrap()).unwrap()
+}
+
+/// Options for training the built-in classifier.
+///
+/// Tokens are unsmoothed, see [`TrainingOptions::default`]. Priors come
+/// from language_priors.yml if it has any entries, and are uniform otherwise.
+pub fn classifier_training_options() -> TrainingOptions {
+    let priors_table = parse_language_priors_yml();
+    if priors_table.is_empty() {
+        TrainingOptions::default()
+    } else {
+        TrainingOptions {
+            priors: Priors::Table(priors_table),
+            ..TrainingOptions::default()
+        }
+    }
+}
+
+/// The n-gram order of the built-in classifier, from `LANGUR_NGRAM_ORDER`,
+/// or 1 for single tokens. `classifier_report` compares the held-out
+/// accuracy of each order.
+pub fn classifier_ngram_order() -> usize {
+    match std::env::var("LANGUR_NGRAM_ORDER") {
+        Ok(order) => order.parse().expect("LANGUR_NGRAM_ORDER must be a number"),
+        Err(_) => 1,
+    }
+}
+
+/// Lists the files in Linguist's samples directory along with the name of
+/// the language they are a sample for, sorted by path.
+pub fn linguist_samples(linguist_root_dir: &Path) -> Vec<(String, PathBuf)> {
+    let mut samples: Vec<_> = fs::read_dir(linguist_root_dir.join("samples"))
+        .unwrap()
+        .map(|entry| entry.unwrap())
+        .filter(|entry| entry.path().is_dir())
+        .flat_map(|language_dir| {
+            let path = language_dir.path();
+            let language = path.file_name().unwrap();
+            let language = language.to_string_lossy().into_owned();
+            let language = match &language[..] {
+                "Fstar" => String::from("F*"),
+                _ => language,
+            };
+
+            fs::read_dir(language_dir.path())
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| path.is_file())
+                .map(move |path| (language.clone(), path))
+        })
+        .collect();
+    samples.sort_by(|(_, a), (_, b)| a.cmp(b));
+    samples
+}
+
+/// Reads a sample file for training.
+pub fn read_sample(path: &Path) -> String {
+    let content = fs::read(path).unwrap();
+    // When tokenizing an invalid utf8 string, just set it to ""
+    // Add better error handling here in the future but unure of the best
+    // way to handle it now
+    String::from_utf8(content).unwrap_or_default()
+}
+
 /// Represents a single language entry in the languages.yml file.
 #[derive(Deserialize, Clone)]
 pub struct ParsedLanguage {